@@ -386,6 +386,12 @@ PyObject *ZstdCompressionParameters_estimated_compression_context_size(
         ZSTD_estimateCCtxSize_usingCCtxParams(self->params));
 }
 
+PyObject *ZstdCompressionParameters_estimated_compression_stream_size(
+    ZstdCompressionParametersObject *self) {
+    return PyLong_FromSize_t(
+        ZSTD_estimateCStreamSize_usingCCtxParams(self->params));
+}
+
 static void
 ZstdCompressionParameters_dealloc(ZstdCompressionParametersObject *self) {
     if (self->params) {
@@ -440,6 +446,9 @@ static PyMethodDef ZstdCompressionParameters_methods[] = {
     {"estimated_compression_context_size",
      (PyCFunction)ZstdCompressionParameters_estimated_compression_context_size,
      METH_NOARGS, NULL},
+    {"estimated_compression_stream_size",
+     (PyCFunction)ZstdCompressionParameters_estimated_compression_stream_size,
+     METH_NOARGS, NULL},
     {NULL, NULL}};
 
 #define GET_SET_ENTRY(name)                                                    \
@@ -21,6 +21,9 @@ void constants_module_init(PyObject *mod) {
     PyObject *version;
     PyObject *zstdVersion;
     PyObject *frameHeader;
+    PyObject *outputLimitError;
+    PyObject *cancelledError;
+    PyObject *progressCancel;
 
     version = PyUnicode_FromString(PYTHON_ZSTANDARD_VERSION);
     PyModule_AddObject(mod, "__version__", version);
@@ -28,9 +31,20 @@ void constants_module_init(PyObject *mod) {
     ZstdError = PyErr_NewException("zstd.ZstdError", NULL, NULL);
     PyModule_AddObject(mod, "ZstdError", ZstdError);
 
+    outputLimitError =
+        PyErr_NewException("zstd.ZstdOutputLimitError", ZstdError, NULL);
+    PyModule_AddObject(mod, "ZstdOutputLimitError", outputLimitError);
+
+    cancelledError =
+        PyErr_NewException("zstd.ZstdCancelledError", ZstdError, NULL);
+    PyModule_AddObject(mod, "ZstdCancelledError", cancelledError);
+
     PyModule_AddIntConstant(mod, "FLUSH_BLOCK", 0);
     PyModule_AddIntConstant(mod, "FLUSH_FRAME", 1);
 
+    progressCancel = PyObject_CallObject((PyObject *)&PyBaseObject_Type, NULL);
+    PyModule_AddObject(mod, "PROGRESS_CANCEL", progressCancel);
+
     PyModule_AddIntConstant(mod, "COMPRESSOBJ_FLUSH_FINISH",
                             compressorobj_flush_finish);
     PyModule_AddIntConstant(mod, "COMPRESSOBJ_FLUSH_BLOCK",
@@ -0,0 +1,408 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    crate::{
+        cancellation::CANCELLATION_CHUNK_SIZE,
+        compression_dict::ZstdCompressionDict,
+        compression_parameters::{CCtxParams, ZstdCompressionParameters},
+        compressor_multi::create_cctxs,
+        exceptions::{zstd_error, ZstdError},
+        memory::resolve_accounting,
+        zstd_safe::{CCtx, DCtx, ZstdFailure},
+    },
+    pyo3::{
+        buffer::PyBuffer,
+        exceptions::{PyMemoryError, PyTypeError, PyValueError},
+        prelude::*,
+        wrap_pyfunction,
+    },
+    std::time::{Duration, Instant},
+};
+
+/// Number of consecutive iterations that must fail to beat the fastest
+/// time before a measurement is considered stable.
+const STABLE_ITERATIONS: usize = 3;
+
+/// Result of benchmarking a single compression configuration.
+#[pyclass(module = "zstandard.backend_rust")]
+pub(crate) struct BenchmarkResult {
+    level: Option<i32>,
+    compression_params: Option<Py<ZstdCompressionParameters>>,
+    size: usize,
+    compressed_size: usize,
+    compress_time: Duration,
+    decompress_time: Duration,
+    compress_iterations: usize,
+    decompress_iterations: usize,
+    compressor_memory_size: usize,
+    decompressor_memory_size: usize,
+}
+
+/// Convert a duration to MB/s for processing `size` bytes.
+fn speed(size: usize, time: Duration) -> f64 {
+    if time.as_nanos() == 0 {
+        0.0
+    } else {
+        size as f64 / 1_000_000.0 / time.as_secs_f64()
+    }
+}
+
+#[pymethods]
+impl BenchmarkResult {
+    #[getter]
+    pub fn level(&self) -> Option<i32> {
+        self.level
+    }
+
+    #[getter]
+    fn compression_params(&self, py: Python) -> Option<Py<ZstdCompressionParameters>> {
+        self.compression_params
+            .as_ref()
+            .map(|params| params.clone_ref(py))
+    }
+
+    #[getter]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    #[getter]
+    pub fn compressed_size(&self) -> usize {
+        self.compressed_size
+    }
+
+    #[getter]
+    pub fn ratio(&self) -> f64 {
+        if self.compressed_size == 0 {
+            0.0
+        } else {
+            self.size as f64 / self.compressed_size as f64
+        }
+    }
+
+    #[getter]
+    fn compress_time(&self) -> f64 {
+        self.compress_time.as_secs_f64()
+    }
+
+    #[getter]
+    fn decompress_time(&self) -> f64 {
+        self.decompress_time.as_secs_f64()
+    }
+
+    #[getter]
+    pub fn compress_speed(&self) -> f64 {
+        speed(self.size, self.compress_time)
+    }
+
+    #[getter]
+    pub fn decompress_speed(&self) -> f64 {
+        speed(self.size, self.decompress_time)
+    }
+
+    #[getter]
+    fn compress_iterations(&self) -> usize {
+        self.compress_iterations
+    }
+
+    #[getter]
+    fn decompress_iterations(&self) -> usize {
+        self.decompress_iterations
+    }
+
+    #[getter]
+    pub fn compressor_memory_size(&self) -> usize {
+        self.compressor_memory_size
+    }
+
+    #[getter]
+    pub fn decompressor_memory_size(&self) -> usize {
+        self.decompressor_memory_size
+    }
+}
+
+/// Controls how long operations are repeated.
+#[derive(Clone, Copy)]
+struct Repetition {
+    min_time: Duration,
+    max_iterations: usize,
+}
+
+impl Repetition {
+    /// Run `op` until its fastest time is stable, returning the fastest time
+    /// and the number of iterations.
+    ///
+    /// Timing is considered stable once `min_time` has elapsed and the
+    /// fastest time hasn't improved for `STABLE_ITERATIONS` iterations.
+    fn measure(
+        &self,
+        mut op: impl FnMut() -> Result<(), ZstdFailure>,
+    ) -> Result<(Duration, usize), ZstdFailure> {
+        let start = Instant::now();
+        let mut fastest = Duration::MAX;
+        let mut iterations = 0;
+        let mut since_fastest = 0;
+
+        while iterations < self.max_iterations {
+            let iteration_start = Instant::now();
+            op()?;
+            let elapsed = iteration_start.elapsed();
+
+            iterations += 1;
+
+            if elapsed < fastest {
+                fastest = elapsed;
+                since_fastest = 0;
+            } else {
+                since_fastest += 1;
+            }
+
+            if start.elapsed() >= self.min_time && since_fastest >= STABLE_ITERATIONS {
+                break;
+            }
+        }
+
+        Ok((fastest, iterations))
+    }
+}
+
+/// Compress every sample into its own frame.
+///
+/// Samples are compressed in chunks like `ZstdCompressor.compress()` does, so
+/// the frames match its output.
+fn compress_samples(cctx: &CCtx, samples: &[&[u8]]) -> Result<Vec<Vec<u8>>, ZstdFailure> {
+    samples
+        .iter()
+        .map(|sample| {
+            let mut frame = vec![];
+            cctx.compress_append_chunked(sample, &mut frame, CANCELLATION_CHUNK_SIZE, || false)?;
+
+            Ok(frame)
+        })
+        .collect()
+}
+
+/// Decompress `frame` into `dest`, which must have enough capacity.
+fn decompress_frame(dctx: &DCtx, frame: &[u8], dest: &mut Vec<u8>) -> Result<(), ZstdFailure> {
+    dest.clear();
+
+    let mut in_buffer = zstd_sys::ZSTD_inBuffer {
+        src: frame.as_ptr() as *const _,
+        size: frame.len(),
+        pos: 0,
+    };
+
+    if dctx.decompress_into_vec(dest, &mut in_buffer)? != 0 {
+        Err("frame was not fully decompressed".into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Benchmark a single configuration.
+fn benchmark_config(
+    py: Python,
+    samples: &[&[u8]],
+    params: &CCtxParams,
+    dict_data: &Option<Py<ZstdCompressionDict>>,
+    format: zstd_sys::ZSTD_format_e,
+    repetition: Repetition,
+) -> PyResult<BenchmarkResult> {
+    let accounting = resolve_accounting(py, &None);
+    let cctx = create_cctxs(py, params, dict_data, &accounting, 1)?.remove(0);
+
+    let dctx = DCtx::new(accounting).map_err(PyMemoryError::new_err)?;
+    dctx.set_format(format)
+        .map_err(|msg| ZstdError::new_err(format!("unable to set decoding format: {}", msg)))?;
+    if let Some(dict_data) = dict_data {
+        dict_data.try_borrow_mut(py)?.load_into_dctx(py, &dctx)?;
+    }
+
+    let size = samples.iter().map(|sample| sample.len()).sum();
+
+    let (frames, compress, decompress) = py.allow_threads(|| {
+        let frames = compress_samples(&cctx, samples)
+            .map_err(|msg| zstd_error(msg, format!("error compressing: {}", msg)))?;
+
+        let mut dest = Vec::with_capacity(samples.iter().map(|s| s.len()).max().unwrap_or(0));
+
+        // Ensure data round trips before timing anything.
+        for (frame, sample) in frames.iter().zip(samples) {
+            decompress_frame(&dctx, frame, &mut dest)
+                .map_err(|msg| zstd_error(msg, format!("error decompressing: {}", msg)))?;
+
+            if dest.as_slice() != *sample {
+                return Err(ZstdError::new_err("decompressed data does not match input"));
+            }
+        }
+
+        let compress = repetition
+            .measure(|| compress_samples(&cctx, samples).map(|_| ()))
+            .map_err(|msg| zstd_error(msg, format!("error compressing: {}", msg)))?;
+
+        let decompress = repetition
+            .measure(|| {
+                frames
+                    .iter()
+                    .try_for_each(|frame| decompress_frame(&dctx, frame, &mut dest))
+            })
+            .map_err(|msg| zstd_error(msg, format!("error decompressing: {}", msg)))?;
+
+        Ok((frames, compress, decompress))
+    })?;
+
+    Ok(BenchmarkResult {
+        level: None,
+        compression_params: None,
+        size,
+        compressed_size: frames.iter().map(|frame| frame.len()).sum(),
+        compress_time: compress.0,
+        decompress_time: decompress.0,
+        compress_iterations: compress.1,
+        decompress_iterations: decompress.1,
+        compressor_memory_size: cctx.memory_size(),
+        decompressor_memory_size: dctx.memory_size(),
+    })
+}
+
+/// Benchmark compression and decompression of `data`.
+#[pyfunction(
+    data,
+    levels = "None",
+    params = "None",
+    dict_data = "None",
+    threads = "0",
+    min_time = "1.0",
+    max_iterations = "None"
+)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn benchmark(
+    py: Python,
+    data: &PyAny,
+    levels: Option<Vec<i32>>,
+    params: Option<Vec<Py<ZstdCompressionParameters>>>,
+    dict_data: Option<Py<ZstdCompressionDict>>,
+    threads: i32,
+    min_time: f64,
+    max_iterations: Option<usize>,
+) -> PyResult<Vec<BenchmarkResult>> {
+    // Duration can't represent other values.
+    if !min_time.is_finite() || min_time < 0.0 {
+        return Err(PyValueError::new_err(
+            "min_time must be a non-negative number",
+        ));
+    }
+
+    if max_iterations == Some(0) {
+        return Err(PyValueError::new_err("max_iterations must be positive"));
+    }
+
+    let repetition = Repetition {
+        min_time: Duration::from_secs_f64(min_time),
+        max_iterations: max_iterations.unwrap_or(usize::MAX),
+    };
+
+    // A single bytes-like object or an iterable of them.
+    let buffers = if let Ok(buffer) = PyBuffer::<u8>::get(data) {
+        vec![buffer]
+    } else {
+        data.iter()
+            .map_err(|_| {
+                PyTypeError::new_err("data must be a bytes-like object or an iterable of them")
+            })?
+            .enumerate()
+            .map(|(i, item)| {
+                PyBuffer::<u8>::get(item?).map_err(|_| {
+                    PyTypeError::new_err(format!("item {} not a bytes like object", i))
+                })
+            })
+            .collect::<PyResult<Vec<_>>>()?
+    };
+
+    if buffers.is_empty() {
+        return Err(PyValueError::new_err("data must not be empty"));
+    }
+
+    let samples = buffers
+        .iter()
+        .map(|buffer| unsafe {
+            std::slice::from_raw_parts::<u8>(buffer.buf_ptr() as *const _, buffer.len_bytes())
+        })
+        .collect::<Vec<_>>();
+
+    let levels = match (levels, &params) {
+        (Some(levels), _) => levels,
+        (None, Some(_)) => vec![],
+        (None, None) => vec![3],
+    };
+
+    let threads = if threads < 0 {
+        num_cpus::get() as i32
+    } else {
+        threads
+    };
+
+    let mut results = vec![];
+
+    for level in levels {
+        if level > zstd_safe::max_c_level() {
+            return Err(PyValueError::new_err(format!(
+                "level must be less than {}",
+                zstd_safe::max_c_level() as i32 + 1
+            )));
+        }
+
+        let cctx_params = CCtxParams::create()?;
+        cctx_params.set_parameter(zstd_sys::ZSTD_cParameter::ZSTD_c_compressionLevel, level)?;
+        if threads != 0 {
+            cctx_params.set_parameter(zstd_sys::ZSTD_cParameter::ZSTD_c_nbWorkers, threads)?;
+        }
+
+        let mut result = benchmark_config(
+            py,
+            &samples,
+            &cctx_params,
+            &dict_data,
+            zstd_sys::ZSTD_format_e::ZSTD_f_zstd1,
+            repetition,
+        )?;
+        result.level = Some(level);
+
+        results.push(result);
+    }
+
+    for compression_params in params.unwrap_or_default() {
+        let cctx_params = CCtxParams::create()?;
+        cctx_params.apply_compression_parameters(py, &compression_params)?;
+
+        // ZSTD_c_format.
+        let format = if compression_params
+            .borrow(py)
+            .get_parameter(zstd_sys::ZSTD_cParameter::ZSTD_c_experimentalParam2)?
+            == zstd_sys::ZSTD_format_e::ZSTD_f_zstd1_magicless as _
+        {
+            zstd_sys::ZSTD_format_e::ZSTD_f_zstd1_magicless
+        } else {
+            zstd_sys::ZSTD_format_e::ZSTD_f_zstd1
+        };
+
+        let mut result =
+            benchmark_config(py, &samples, &cctx_params, &dict_data, format, repetition)?;
+        result.compression_params = Some(compression_params);
+
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
+    module.add_class::<BenchmarkResult>()?;
+    module.add_function(wrap_pyfunction!(benchmark, module)?)?;
+
+    Ok(())
+}
@@ -7,14 +7,19 @@
 use {
     crate::exceptions::ZstdError,
     pyo3::{
+        basic::CompareOp,
         buffer::PyBuffer,
-        class::{PyBufferProtocol, PySequenceProtocol},
-        exceptions::{PyIndexError, PyTypeError, PyValueError},
-        ffi::Py_buffer,
+        class::{
+            PyBufferProtocol, PyIterProtocol, PyMappingProtocol, PyNumberProtocol,
+            PyObjectProtocol, PySequenceProtocol,
+        },
+        exceptions::{PyBufferError, PyIndexError, PyTypeError, PyValueError},
+        ffi::{self, Py_buffer},
         prelude::*,
-        types::{PyBytes, PyTuple},
-        AsPyPointer,
+        types::{IntoPyDict, PyBytes, PySlice, PyTuple},
+        AsPyPointer, PyNativeType,
     },
+    std::{cell::Cell, convert::TryInto},
 };
 
 #[repr(C)]
@@ -24,6 +29,206 @@ pub(crate) struct BufferSegment {
     pub length: u64,
 }
 
+/// Obtain offsets from an array of 32 or 64-bit integers.
+///
+/// Returns `None` if `obj` doesn't expose a buffer of such integers.
+pub(crate) fn extract_offsets(py: Python, obj: &PyAny) -> Option<PyResult<Vec<i64>>> {
+    if let Ok(buffer) = PyBuffer::<i32>::get(obj) {
+        Some(
+            buffer
+                .to_vec(py)
+                .map(|v| v.into_iter().map(i64::from).collect()),
+        )
+    } else if let Ok(buffer) = PyBuffer::<i64>::get(obj) {
+        Some(buffer.to_vec(py))
+    } else if let Ok(buffer) = PyBuffer::<u32>::get(obj) {
+        Some(
+            buffer
+                .to_vec(py)
+                .map(|v| v.into_iter().map(i64::from).collect()),
+        )
+    } else if let Ok(buffer) = PyBuffer::<u64>::get(obj) {
+        Some(buffer.to_vec(py).map(|v| {
+            v.into_iter()
+                .map(|offset| std::cmp::min(offset, i64::MAX as u64) as i64)
+                .collect()
+        }))
+    } else {
+        None
+    }
+}
+
+/// Obtain segments from an array of `(offset, length)` pairs.
+///
+/// The array can be raw bytes holding native-endian u64 values or an array of
+/// u64, such as the one exposed by `BufferSegments`.
+fn extract_segments(py: Python, obj: &PyAny) -> PyResult<Vec<BufferSegment>> {
+    let size_error = || {
+        PyValueError::new_err(format!(
+            "segments array size is not a multiple of {}",
+            std::mem::size_of::<BufferSegment>()
+        ))
+    };
+
+    let values = match PyBuffer::<u64>::get(obj) {
+        Ok(buffer) => buffer.to_vec(py)?,
+        Err(_) => {
+            let buffer = PyBuffer::<u8>::get(obj)?;
+
+            if buffer.len_bytes() % std::mem::size_of::<BufferSegment>() != 0 {
+                return Err(size_error());
+            }
+
+            buffer
+                .to_vec(py)?
+                .chunks_exact(8)
+                .map(|value| u64::from_ne_bytes(value.try_into().unwrap()))
+                .collect::<Vec<_>>()
+        }
+    };
+
+    if values.len() % 2 != 0 {
+        return Err(size_error());
+    }
+
+    Ok(values
+        .chunks_exact(2)
+        .map(|pair| BufferSegment {
+            offset: pair[0],
+            length: pair[1],
+        })
+        .collect())
+}
+
+/// Convert Arrow style offsets to segments.
+///
+/// `offsets` holds N + 1 values delimiting N items within a buffer of
+/// `data_len` bytes. Item `i` spans `offsets[i]..offsets[i + 1]`.
+pub(crate) fn segments_from_offsets(
+    offsets: &[i64],
+    data_len: usize,
+) -> PyResult<Vec<BufferSegment>> {
+    let mut segments = Vec::with_capacity(offsets.len().saturating_sub(1));
+
+    for window in offsets.windows(2) {
+        let (start, end) = (window[0], window[1]);
+
+        if start < 0 {
+            return Err(PyValueError::new_err("offsets must not be negative"));
+        }
+        if end < start {
+            return Err(PyValueError::new_err("offsets must be non-decreasing"));
+        }
+        if end as u64 > data_len as u64 {
+            return Err(PyValueError::new_err(
+                "offsets reference memory outside data buffer",
+            ));
+        }
+
+        segments.push(BufferSegment {
+            offset: start as u64,
+            length: (end - start) as u64,
+        });
+    }
+
+    Ok(segments)
+}
+
+/// Identifies serialized `BufferWithSegmentsCollection` data.
+const SERIALIZED_MAGIC: &[u8; 8] = b"ZSTDBWSC";
+
+/// Version of the serialization format.
+const SERIALIZED_VERSION: u32 = 1;
+
+/// Alignment of every section of serialized data.
+const SERIALIZED_ALIGNMENT: usize = 8;
+
+/// Number of zero bytes following `len` bytes of serialized buffer data.
+fn serialized_padding(len: usize) -> usize {
+    (SERIALIZED_ALIGNMENT - len % SERIALIZED_ALIGNMENT) % SERIALIZED_ALIGNMENT
+}
+
+/// Map a file into memory, read-only.
+///
+/// Returns an object exposing the file content via the buffer protocol.
+fn map_file<'p>(py: Python<'p>, path: &PyAny) -> PyResult<&'p PyAny> {
+    let fh = py
+        .import("builtins")?
+        .getattr("open")?
+        .call1((path, "rb"))?;
+
+    let data = (|| -> PyResult<&PyAny> {
+        let fileno = fh.call_method0("fileno")?;
+        let size: u64 = py
+            .import("os")?
+            .call_method1("fstat", (fileno,))?
+            .getattr("st_size")?
+            .extract()?;
+
+        // Empty files can't be mapped.
+        if size == 0 {
+            return Ok(PyBytes::new(py, b""));
+        }
+
+        let mmap = py.import("mmap")?;
+        let kwargs = [("access", mmap.getattr("ACCESS_READ")?)].into_py_dict(py);
+
+        mmap.getattr("mmap")?.call((fileno, 0), Some(kwargs))
+    })();
+
+    // The mapping remains valid after the file is closed.
+    fh.call_method0("close")?;
+
+    data
+}
+
+/// Obtain a view of `start..end` of an object's memory without copying it.
+fn memory_slice<'p>(py: Python<'p>, obj: &PyAny, start: isize, end: isize) -> PyResult<&'p PyAny> {
+    py.import("builtins")?
+        .getattr("memoryview")?
+        .call1((obj,))?
+        .get_item(PySlice::new(py, start, end, 1))
+}
+
+/// Read a little-endian u64 from serialized data.
+fn read_u64(data: &[u8], offset: usize) -> PyResult<u64> {
+    data.get(offset..offset + 8)
+        .map(|value| u64::from_le_bytes(value.try_into().unwrap()))
+        .ok_or_else(|| PyValueError::new_err("serialized data is truncated"))
+}
+
+/// Exposes memory owned by Rust to Python via the buffer protocol.
+///
+/// Allows wrapping output of Rust code without copying it into a `bytes`.
+#[pyclass(module = "zstandard.backend_rust")]
+pub(crate) struct OwnedBuffer {
+    data: Vec<u8>,
+}
+
+#[pyproto]
+impl PyBufferProtocol for OwnedBuffer {
+    fn bf_getbuffer(slf: PyRefMut<Self>, view: *mut Py_buffer, flags: i32) -> PyResult<()> {
+        if unsafe {
+            pyo3::ffi::PyBuffer_FillInfo(
+                view,
+                slf.as_ptr(),
+                slf.data.as_ptr() as *mut _,
+                slf.data.len() as _,
+                1,
+                flags,
+            )
+        } != 0
+        {
+            Err(PyErr::fetch(slf.py()))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn bf_releasebuffer(slf: PyRefMut<Self>, view: *mut Py_buffer) {}
+}
+
 #[pyclass(module = "zstandard.backend_rust", name = "BufferSegment")]
 pub struct ZstdBufferSegment {
     /// The object backing storage. For reference counting.
@@ -56,6 +261,49 @@ impl ZstdBufferSegment {
     }
 }
 
+#[pyproto]
+impl PyObjectProtocol for ZstdBufferSegment {
+    fn __repr__(&self) -> String {
+        format!("BufferSegment(offset={}, length={})", self.offset, self.len)
+    }
+
+    /// Segments compare equal to objects holding the same bytes.
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyResult<PyObject> {
+        let py = other.py();
+
+        let equal = if let Ok(other) = other.extract::<PyRef<ZstdBufferSegment>>() {
+            self.as_slice() == other.as_slice()
+        } else if let Ok(buffer) = PyBuffer::<u8>::get(other) {
+            if buffer.is_c_contiguous() {
+                self.as_slice()
+                    == unsafe {
+                        std::slice::from_raw_parts(
+                            buffer.buf_ptr() as *const u8,
+                            buffer.len_bytes(),
+                        )
+                    }
+            } else {
+                self.as_slice() == buffer.to_vec(py)?.as_slice()
+            }
+        } else {
+            return Ok(py.NotImplemented());
+        };
+
+        match op {
+            CompareOp::Eq => Ok(equal.into_py(py)),
+            CompareOp::Ne => Ok((!equal).into_py(py)),
+            _ => Ok(py.NotImplemented()),
+        }
+    }
+
+    /// Hashes like `bytes` holding the same data, consistent with equality.
+    fn __hash__(&self) -> isize {
+        let data = self.as_slice();
+
+        unsafe { ffi::_Py_HashBytes(data.as_ptr() as *const _, data.len() as _) }
+    }
+}
+
 #[pyproto]
 impl PySequenceProtocol for ZstdBufferSegment {
     fn __len__(&self) -> usize {
@@ -89,33 +337,73 @@ impl PyBufferProtocol for ZstdBufferSegment {
     fn bf_releasebuffer(slf: PyRefMut<Self>, view: *mut Py_buffer) {}
 }
 
+/// Strides of the 2 dimensional buffer exposed by `BufferSegments`.
+static SEGMENTS_STRIDES: [isize; 2] = [
+    std::mem::size_of::<BufferSegment>() as isize,
+    std::mem::size_of::<u64>() as isize,
+];
+
 #[pyclass(module = "zstandard.backend_rust", name = "BufferSegments")]
 pub struct ZstdBufferSegments {
     parent: PyObject,
+    /// Shape of the exposed buffer. Referenced by buffer views.
+    shape: Cell<[isize; 2]>,
 }
 
 #[pyproto]
 impl PyBufferProtocol for ZstdBufferSegments {
+    /// Expose segments as a `(N, 2)` array of u64.
     fn bf_getbuffer(slf: PyRefMut<Self>, view: *mut Py_buffer, flags: i32) -> PyResult<()> {
         let py = slf.py();
 
-        let parent: &PyCell<ZstdBufferWithSegments> = slf.parent.extract(py)?;
+        if flags & ffi::PyBUF_WRITABLE == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("segments buffer is read-only"));
+        }
+        if flags & ffi::PyBUF_F_CONTIGUOUS == ffi::PyBUF_F_CONTIGUOUS {
+            return Err(PyBufferError::new_err(
+                "segments buffer is not Fortran contiguous",
+            ));
+        }
 
-        if unsafe {
-            pyo3::ffi::PyBuffer_FillInfo(
-                view,
-                slf.as_ptr(),
-                parent.borrow().segments.as_ptr() as *const _ as *mut _,
-                (parent.borrow().segments.len() * std::mem::size_of::<BufferSegment>()) as isize,
-                1,
-                flags,
-            )
-        } != 0
-        {
-            Err(PyErr::fetch(py))
-        } else {
-            Ok(())
+        let parent = slf.parent.clone_ref(py);
+        let parent: &PyCell<ZstdBufferWithSegments> = parent.extract(py)?;
+        let parent = parent.borrow();
+        let segments = &parent.segments;
+
+        slf.shape.set([segments.len() as isize, 2]);
+
+        unsafe {
+            (*view).buf = segments.as_ptr() as *mut _;
+            (*view).obj = slf.as_ptr();
+            ffi::Py_INCREF((*view).obj);
+            (*view).len = (segments.len() * std::mem::size_of::<BufferSegment>()) as isize;
+            (*view).readonly = 1;
+            (*view).itemsize = std::mem::size_of::<u64>() as isize;
+            (*view).format = if flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+                b"Q\0".as_ptr() as *mut _
+            } else {
+                std::ptr::null_mut()
+            };
+
+            // Consumers not asking for a shape treat the buffer as bytes.
+            if flags & ffi::PyBUF_ND == ffi::PyBUF_ND {
+                (*view).ndim = 2;
+                (*view).shape = slf.shape.as_ptr() as *mut _;
+            } else {
+                (*view).ndim = 1;
+                (*view).shape = std::ptr::null_mut();
+            }
+
+            (*view).strides = if flags & ffi::PyBUF_STRIDES == ffi::PyBUF_STRIDES {
+                SEGMENTS_STRIDES.as_ptr() as *mut _
+            } else {
+                std::ptr::null_mut()
+            };
+            (*view).suboffsets = std::ptr::null_mut();
+            (*view).internal = std::ptr::null_mut();
         }
+
+        Ok(())
     }
 
     #[allow(unused_variables)]
@@ -136,6 +424,54 @@ impl ZstdBufferWithSegments {
         }
     }
 
+    /// Obtain the segment at index `i`.
+    pub(crate) fn segment(&self, py: Python, i: usize) -> PyResult<ZstdBufferSegment> {
+        if i >= self.segments.len() {
+            return Err(PyIndexError::new_err(format!(
+                "offset must be less than {}",
+                self.segments.len()
+            )));
+        }
+
+        let segment = &self.segments[i];
+
+        Ok(ZstdBufferSegment {
+            _parent: self.source.clone_ref(py),
+            buffer: PyBuffer::get(self.source.extract(py)?)?,
+            offset: segment.offset as _,
+            len: segment.length as _,
+        })
+    }
+
+    /// Construct an instance holding `segments` of this one.
+    ///
+    /// The new instance references the span of memory covered by the segments
+    /// without copying it.
+    fn subset(&self, py: Python, mut segments: Vec<BufferSegment>) -> PyResult<Self> {
+        let start = segments
+            .iter()
+            .map(|segment| segment.offset)
+            .min()
+            .unwrap_or(0);
+        let end = segments
+            .iter()
+            .map(|segment| segment.offset + segment.length)
+            .max()
+            .unwrap_or(0);
+
+        for segment in segments.iter_mut() {
+            segment.offset -= start;
+        }
+
+        let view = memory_slice(py, self.source.as_ref(py), start as _, end as _)?;
+
+        Ok(Self {
+            source: view.into_py(py),
+            buffer: PyBuffer::get(view)?,
+            segments,
+        })
+    }
+
     pub fn get_segment_slice<'p>(&self, _py: Python<'p>, i: usize) -> &'p [u8] {
         let segment = &self.segments[i];
 
@@ -146,36 +482,43 @@ impl ZstdBufferWithSegments {
             )
         }
     }
+
+    /// Construct an instance taking ownership of data, without copying it.
+    ///
+    /// Segments must reference memory within `data`.
+    pub(crate) fn from_vec(
+        py: Python,
+        data: Vec<u8>,
+        segments: Vec<BufferSegment>,
+    ) -> PyResult<Self> {
+        let source = Py::new(py, OwnedBuffer { data })?;
+        let buffer = PyBuffer::get(source.as_ref(py))?;
+
+        Ok(Self {
+            source: source.into_py(py),
+            buffer,
+            segments,
+        })
+    }
 }
 
 #[pymethods]
 impl ZstdBufferWithSegments {
     #[new]
-    pub fn new(py: Python, data: &PyAny, segments: PyBuffer<u8>) -> PyResult<Self> {
+    pub fn new(py: Python, data: &PyAny, segments: &PyAny) -> PyResult<Self> {
         let data_buffer = PyBuffer::get(data)?;
 
-        if segments.len_bytes() % std::mem::size_of::<BufferSegment>() != 0 {
-            return Err(PyValueError::new_err(format!(
-                "segments array size is not a multiple of {}",
-                std::mem::size_of::<BufferSegment>()
-            )));
-        }
-
-        let segments_slice: &[BufferSegment] = unsafe {
-            std::slice::from_raw_parts(
-                segments.buf_ptr() as *const _,
-                segments.len_bytes() / std::mem::size_of::<BufferSegment>(),
-            )
-        };
-
         // Make a copy of the segments data. It is cheap to do so and is a
         // guard against caller changing offsets, which has security implications.
-        let segments = segments_slice.to_vec();
+        let segments = extract_segments(py, segments)?;
 
         // Validate segments data, as blindly trusting it could lead to
         // arbitrary memory access.
         for segment in &segments {
-            if segment.offset + segment.length > data_buffer.len_bytes() as _ {
+            if !matches!(
+                segment.offset.checked_add(segment.length),
+                Some(end) if end <= data_buffer.len_bytes() as u64
+            ) {
                 return Err(PyValueError::new_err(
                     "offset within segments array references memory outside buffer",
                 ));
@@ -189,15 +532,87 @@ impl ZstdBufferWithSegments {
         })
     }
 
+    /// Construct an instance backed by a memory mapped file.
+    #[staticmethod]
+    fn from_file(py: Python, path: &PyAny, segments: &PyAny) -> PyResult<Self> {
+        Self::new(py, map_file(py, path)?, segments)
+    }
+
+    /// Construct an instance from Arrow style offsets.
+    #[staticmethod]
+    fn from_offsets(py: Python, data: &PyAny, offsets: &PyAny) -> PyResult<Self> {
+        let data_buffer = PyBuffer::get(data)?;
+
+        if !data_buffer.is_c_contiguous() {
+            return Err(PyValueError::new_err("data buffer must be contiguous"));
+        }
+
+        let offsets = extract_offsets(py, offsets).ok_or_else(|| {
+            PyTypeError::new_err("offsets must be an array of 32 or 64-bit integers")
+        })??;
+
+        let segments = segments_from_offsets(&offsets, data_buffer.len_bytes())?;
+
+        Ok(Self {
+            source: data.into_py(py),
+            buffer: data_buffer,
+            segments,
+        })
+    }
+
     #[getter]
     fn size(&self) -> usize {
         self.buffer.len_bytes()
     }
 
+    /// Obtain Arrow style offsets describing segments.
+    ///
+    /// Segments must be adjacent to each other.
+    #[args(large = "false")]
+    fn offsets<'p>(&self, py: Python<'p>, large: bool) -> PyResult<&'p PyBytes> {
+        let mut offsets = Vec::with_capacity(self.segments.len() + 1);
+        offsets.push(self.segments.first().map_or(0, |segment| segment.offset));
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            if segment.offset != offsets[i] {
+                return Err(PyValueError::new_err(format!(
+                    "segment {} is not adjacent to previous segment",
+                    i
+                )));
+            }
+
+            offsets.push(segment.offset + segment.length);
+        }
+
+        if large {
+            let data = offsets
+                .iter()
+                .flat_map(|offset| (*offset as i64).to_ne_bytes())
+                .collect::<Vec<_>>();
+
+            Ok(PyBytes::new(py, &data))
+        } else {
+            // offsets always holds at least 1 element.
+            if offsets[offsets.len() - 1] > i32::MAX as u64 {
+                return Err(PyValueError::new_err(
+                    "offsets do not fit in 32-bit integers; use large=True",
+                ));
+            }
+
+            let data = offsets
+                .iter()
+                .flat_map(|offset| (*offset as i32).to_ne_bytes())
+                .collect::<Vec<_>>();
+
+            Ok(PyBytes::new(py, &data))
+        }
+    }
+
     fn segments(slf: PyRef<Self>, py: Python) -> PyResult<ZstdBufferSegments> {
         Ok(ZstdBufferSegments {
             // TODO surely there is a better way to cast self to PyObject?
             parent: unsafe { Py::from_borrowed_ptr(py, slf.as_ptr()) },
+            shape: Cell::new([0, 0]),
         })
     }
 
@@ -211,31 +626,47 @@ impl PySequenceProtocol for ZstdBufferWithSegments {
     fn __len__(&self) -> usize {
         self.segments.len()
     }
+}
 
-    fn __getitem__(&self, key: isize) -> PyResult<ZstdBufferSegment> {
-        let py = unsafe { Python::assume_gil_acquired() };
+#[pyproto]
+impl PyMappingProtocol for ZstdBufferWithSegments {
+    fn __getitem__(&self, key: &PyAny) -> PyResult<PyObject> {
+        let py = key.py();
 
-        if key < 0 {
-            return Err(PyIndexError::new_err("offset must be non-negative"));
+        if let Ok(slice) = key.downcast::<PySlice>() {
+            let indices = slice.indices(self.segments.len() as _)?;
+
+            let segments = (0..indices.slicelength)
+                .map(|i| self.segments[(indices.start + i * indices.step) as usize].clone())
+                .collect::<Vec<_>>();
+
+            return Ok(self.subset(py, segments)?.into_py(py));
         }
 
-        let key = key as usize;
+        let mut key: isize = key.extract()?;
 
-        if key >= self.segments.len() {
-            return Err(PyIndexError::new_err(format!(
-                "offset must be less than {}",
-                self.segments.len()
-            )));
+        if key < 0 {
+            key += self.segments.len() as isize;
         }
 
-        let segment = &self.segments[key];
+        if key < 0 {
+            return Err(PyIndexError::new_err("offset must be non-negative"));
+        }
 
-        Ok(ZstdBufferSegment {
-            _parent: self.source.clone_ref(py),
-            buffer: PyBuffer::get(self.source.extract(py)?)?,
-            offset: segment.offset as _,
-            len: segment.length as _,
-        })
+        Ok(self.segment(py, key as usize)?.into_py(py))
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for ZstdBufferWithSegments {
+    fn __iter__(slf: &PyCell<Self>) -> ZstdBufferSegmentIterator {
+        let py = slf.py();
+
+        ZstdBufferSegmentIterator {
+            buffers: vec![slf.into_py(py)],
+            buffer_index: 0,
+            segment_index: 0,
+        }
     }
 }
 
@@ -273,6 +704,69 @@ pub struct ZstdBufferWithSegmentsCollection {
     first_elements: Vec<usize>,
 }
 
+impl ZstdBufferWithSegmentsCollection {
+    /// Construct an instance from buffers without validating them.
+    ///
+    /// Unlike `new()`, buffers may be empty.
+    pub(crate) fn from_buffers(py: Python, buffers: Vec<Py<ZstdBufferWithSegments>>) -> Self {
+        let mut first_elements = Vec::with_capacity(buffers.len());
+        let mut offset = 0;
+
+        for buffer in &buffers {
+            offset += buffer.borrow(py).segments.len();
+            first_elements.push(offset);
+        }
+
+        Self {
+            buffers: buffers
+                .into_iter()
+                .map(|buffer| buffer.into_py(py))
+                .collect(),
+            first_elements,
+        }
+    }
+
+    /// Serialize everything but buffer data.
+    ///
+    /// Layout, with all integers little-endian:
+    ///
+    /// * 8 byte magic and u32 format version.
+    /// * u32 number of buffers.
+    /// * u64 data size and u64 segment count of each buffer.
+    /// * u64 offset and u64 length of every segment, grouped by buffer.
+    ///   Offsets are relative to the start of the buffer's data.
+    ///
+    /// Buffer data follows, in order. Since every field is a multiple of 8
+    /// bytes, segment tables and the first buffer's data are 8 byte aligned.
+    /// Each buffer's data is followed by zero padding up to a multiple of 8
+    /// bytes, so every buffer's data is aligned.
+    fn serialized_header(&self, py: Python) -> PyResult<Vec<u8>> {
+        let mut header = vec![];
+        header.extend_from_slice(SERIALIZED_MAGIC);
+        header.extend_from_slice(&SERIALIZED_VERSION.to_le_bytes());
+        header.extend_from_slice(&(self.buffers.len() as u32).to_le_bytes());
+
+        let mut tables = vec![];
+
+        for buffer in &self.buffers {
+            let item: &PyCell<ZstdBufferWithSegments> = buffer.extract(py)?;
+            let item = item.borrow();
+
+            header.extend_from_slice(&(item.buffer.len_bytes() as u64).to_le_bytes());
+            header.extend_from_slice(&(item.segments.len() as u64).to_le_bytes());
+
+            for segment in &item.segments {
+                tables.extend_from_slice(&segment.offset.to_le_bytes());
+                tables.extend_from_slice(&segment.length.to_le_bytes());
+            }
+        }
+
+        header.extend_from_slice(&tables);
+
+        Ok(header)
+    }
+}
+
 #[pymethods]
 impl ZstdBufferWithSegmentsCollection {
     #[new]
@@ -323,16 +817,228 @@ impl ZstdBufferWithSegmentsCollection {
 
         Ok(size)
     }
+
+    /// Copy all segments into a single, contiguous buffer.
+    fn flatten(&self, py: Python) -> PyResult<ZstdBufferWithSegments> {
+        let mut data = Vec::with_capacity(self.size(py)?);
+        let mut segments = Vec::with_capacity(self.__len__());
+
+        for buffer in &self.buffers {
+            let item: &PyCell<ZstdBufferWithSegments> = buffer.extract(py)?;
+            let item = item.borrow();
+
+            for i in 0..item.segments.len() {
+                let slice = item.get_segment_slice(py, i);
+
+                segments.push(BufferSegment {
+                    offset: data.len() as _,
+                    length: slice.len() as _,
+                });
+                data.extend_from_slice(slice);
+            }
+        }
+
+        ZstdBufferWithSegments::from_vec(py, data, segments)
+    }
+
+    /// Serialize the collection to bytes.
+    fn to_bytes<'p>(&self, py: Python<'p>) -> PyResult<&'p PyBytes> {
+        let mut data = self.serialized_header(py)?;
+
+        for buffer in &self.buffers {
+            let item: &PyCell<ZstdBufferWithSegments> = buffer.extract(py)?;
+            let item = item.borrow();
+
+            data.extend_from_slice(item.as_slice());
+            data.resize(data.len() + serialized_padding(item.as_slice().len()), 0);
+        }
+
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Serialize the collection to a file.
+    fn to_file(&self, py: Python, path: &PyAny) -> PyResult<()> {
+        let fh = py
+            .import("builtins")?
+            .getattr("open")?
+            .call1((path, "wb"))?;
+
+        let result = (|| -> PyResult<()> {
+            fh.call_method1("write", (PyBytes::new(py, &self.serialized_header(py)?),))?;
+
+            // Buffers expose their data via the buffer protocol, avoiding a
+            // copy.
+            for buffer in &self.buffers {
+                fh.call_method1("write", (buffer,))?;
+
+                let item: &PyCell<ZstdBufferWithSegments> = buffer.extract(py)?;
+                let padding = serialized_padding(item.borrow().buffer.len_bytes());
+
+                if padding > 0 {
+                    fh.call_method1(
+                        "write",
+                        (PyBytes::new(py, &[0; SERIALIZED_ALIGNMENT][0..padding]),),
+                    )?;
+                }
+            }
+
+            Ok(())
+        })();
+
+        fh.call_method0("close")?;
+
+        result
+    }
+
+    /// Construct an instance from serialized data.
+    ///
+    /// Buffers reference memory within `data` without copying it.
+    #[staticmethod]
+    fn from_bytes(py: Python, data: &PyAny) -> PyResult<Self> {
+        let data_buffer = PyBuffer::<u8>::get(data)?;
+
+        if !data_buffer.is_c_contiguous() {
+            return Err(PyValueError::new_err("data buffer must be contiguous"));
+        }
+
+        let raw = unsafe {
+            std::slice::from_raw_parts(data_buffer.buf_ptr() as *const u8, data_buffer.len_bytes())
+        };
+
+        if raw.len() < 16 || &raw[0..8] != SERIALIZED_MAGIC {
+            return Err(PyValueError::new_err(
+                "data is not a serialized BufferWithSegmentsCollection",
+            ));
+        }
+
+        let version = u32::from_le_bytes(raw[8..12].try_into().unwrap());
+        if version != SERIALIZED_VERSION {
+            return Err(PyValueError::new_err(format!(
+                "unsupported serialization version: {}",
+                version
+            )));
+        }
+
+        let count = u32::from_le_bytes(raw[12..16].try_into().unwrap()) as usize;
+        let mut pos = 16;
+
+        // (data size, segment count) of each buffer.
+        let mut sizes = vec![];
+        for _ in 0..count {
+            sizes.push((read_u64(raw, pos)?, read_u64(raw, pos + 8)?));
+            pos += 16;
+        }
+
+        let mut segments = vec![];
+        for (data_size, segment_count) in &sizes {
+            let mut buffer_segments = vec![];
+
+            for _ in 0..*segment_count {
+                let segment = BufferSegment {
+                    offset: read_u64(raw, pos)?,
+                    length: read_u64(raw, pos + 8)?,
+                };
+                pos += 16;
+
+                if !matches!(
+                    segment.offset.checked_add(segment.length),
+                    Some(end) if end <= *data_size
+                ) {
+                    return Err(PyValueError::new_err(
+                        "offset within segments array references memory outside buffer",
+                    ));
+                }
+
+                buffer_segments.push(segment);
+            }
+
+            segments.push(buffer_segments);
+        }
+
+        let mut buffers = Vec::with_capacity(count);
+        for ((data_size, _), segments) in sizes.into_iter().zip(segments) {
+            // Data is followed by padding.
+            let padding = serialized_padding(data_size as usize);
+
+            let end = match (pos as u64)
+                .checked_add(data_size)
+                .and_then(|end| end.checked_add(padding as u64))
+            {
+                Some(padded_end) if padded_end <= raw.len() as u64 => padded_end as usize - padding,
+                _ => return Err(PyValueError::new_err("serialized data is truncated")),
+            };
+
+            let view = memory_slice(py, data, pos as _, end as _)?;
+
+            buffers.push(Py::new(
+                py,
+                ZstdBufferWithSegments {
+                    source: view.into_py(py),
+                    buffer: PyBuffer::get(view)?,
+                    segments,
+                },
+            )?);
+
+            pos = end + padding;
+        }
+
+        if pos != raw.len() {
+            return Err(PyValueError::new_err("serialized data has trailing bytes"));
+        }
+
+        Ok(Self::from_buffers(py, buffers))
+    }
+
+    /// Construct an instance from a serialized file.
+    ///
+    /// The file is memory mapped and buffers reference the mapping.
+    #[staticmethod]
+    fn from_file(py: Python, path: &PyAny) -> PyResult<Self> {
+        Self::from_bytes(py, map_file(py, path)?)
+    }
+
+    /// Append the buffers of another collection to this one.
+    fn extend(
+        slf: &PyCell<ZstdBufferWithSegmentsCollection>,
+        py: Python,
+        other: &PyCell<ZstdBufferWithSegmentsCollection>,
+    ) -> PyResult<()> {
+        // Copy first so extending a collection with itself works.
+        let buffers = other
+            .borrow()
+            .buffers
+            .iter()
+            .map(|buffer| buffer.clone_ref(py))
+            .collect::<Vec<_>>();
+
+        let mut slf = slf.borrow_mut();
+        let mut offset = slf.__len__();
+
+        for buffer in buffers {
+            let item: &PyCell<ZstdBufferWithSegments> = buffer.extract(py)?;
+            offset += item.borrow().segments.len();
+
+            slf.buffers.push(buffer);
+            slf.first_elements.push(offset);
+        }
+
+        Ok(())
+    }
 }
 
 #[pyproto]
-impl PySequenceProtocol for ZstdBufferWithSegmentsCollection {
+impl PyMappingProtocol for ZstdBufferWithSegmentsCollection {
     fn __len__(&self) -> usize {
-        self.first_elements.last().unwrap().clone()
+        self.first_elements.last().copied().unwrap_or(0)
     }
 
-    fn __getitem__(&self, key: isize) -> PyResult<ZstdBufferSegment> {
-        let py = unsafe { Python::assume_gil_acquired() };
+    fn __getitem__(&self, key: &PyAny) -> PyResult<ZstdBufferSegment> {
+        let py = key.py();
+        let mut key: isize = key.extract()?;
+
+        if key < 0 {
+            key += self.__len__() as isize;
+        }
 
         if key < 0 {
             return Err(PyIndexError::new_err("offset must be non-negative"));
@@ -356,7 +1062,7 @@ impl PySequenceProtocol for ZstdBufferWithSegmentsCollection {
 
                 let item: &PyCell<ZstdBufferWithSegments> = segment.extract(py)?;
 
-                return item.borrow().__getitem__((key - offset) as isize);
+                return item.borrow().segment(py, key - offset);
             }
         }
 
@@ -366,11 +1072,84 @@ impl PySequenceProtocol for ZstdBufferWithSegmentsCollection {
     }
 }
 
+#[pyproto]
+impl PyIterProtocol for ZstdBufferWithSegmentsCollection {
+    fn __iter__(slf: PyRef<Self>) -> ZstdBufferSegmentIterator {
+        let py = slf.py();
+
+        ZstdBufferSegmentIterator {
+            buffers: slf
+                .buffers
+                .iter()
+                .map(|buffer| buffer.clone_ref(py))
+                .collect(),
+            buffer_index: 0,
+            segment_index: 0,
+        }
+    }
+}
+
+#[pyproto]
+impl PyNumberProtocol for ZstdBufferWithSegmentsCollection {
+    fn __add__(lhs: PyRef<Self>, rhs: PyRef<Self>) -> PyResult<Self> {
+        let py = lhs.py();
+
+        let buffers = lhs
+            .buffers
+            .iter()
+            .chain(rhs.buffers.iter())
+            .map(|buffer| buffer.extract(py))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        Ok(Self::from_buffers(py, buffers))
+    }
+}
+
+/// Iterates over the segments of one or more `BufferWithSegments`.
+#[pyclass(module = "zstandard.backend_rust", name = "BufferSegmentIterator")]
+pub struct ZstdBufferSegmentIterator {
+    // Py<ZstdBufferWithSegments>.
+    buffers: Vec<PyObject>,
+    buffer_index: usize,
+    segment_index: usize,
+}
+
+#[pyproto]
+impl PyIterProtocol for ZstdBufferSegmentIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(slf: &PyCell<Self>) -> PyResult<Option<ZstdBufferSegment>> {
+        let py = slf.py();
+        let mut slf = slf.try_borrow_mut()?;
+
+        while slf.buffer_index < slf.buffers.len() {
+            let buffer = slf.buffers[slf.buffer_index].clone_ref(py);
+            let buffer: &PyCell<ZstdBufferWithSegments> = buffer.extract(py)?;
+            let buffer = buffer.borrow();
+
+            if slf.segment_index < buffer.segments.len() {
+                let segment = buffer.segment(py, slf.segment_index)?;
+                slf.segment_index += 1;
+
+                return Ok(Some(segment));
+            }
+
+            slf.buffer_index += 1;
+            slf.segment_index = 0;
+        }
+
+        Ok(None)
+    }
+}
+
 pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
     module.add_class::<ZstdBufferSegment>()?;
     module.add_class::<ZstdBufferSegments>()?;
     module.add_class::<ZstdBufferWithSegments>()?;
     module.add_class::<ZstdBufferWithSegmentsCollection>()?;
+    module.add_class::<ZstdBufferSegmentIterator>()?;
 
     Ok(())
 }
@@ -0,0 +1,86 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    crate::exceptions::ZstdCancelledError,
+    pyo3::prelude::*,
+    std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+};
+
+/// Bytes of input compressed between checks for cancellation.
+pub(crate) const CANCELLATION_CHUNK_SIZE: usize = 1 << 20;
+
+/// How often threads waiting on workers check for cancellation.
+pub(crate) const CANCELLATION_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+#[pyclass(module = "zstandard.backend_rust")]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+#[pymethods]
+impl CancellationToken {
+    #[new]
+    fn new() -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Decides whether a long running operation should stop early.
+///
+/// Operations stop when their `CancellationToken` is cancelled or when a
+/// Python signal handler raises, such as on Ctrl-C. Can be cloned into
+/// threads not holding the GIL, which can only observe the token.
+#[derive(Clone)]
+pub struct Cancellation(Option<Arc<AtomicBool>>);
+
+impl Cancellation {
+    pub fn new(py: Python, token: Option<Py<CancellationToken>>) -> Self {
+        Self(token.map(|token| token.borrow(py).cancelled.clone()))
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+    }
+
+    /// Raise if the operation should stop.
+    ///
+    /// Runs pending signal handlers, propagating their exceptions.
+    pub fn check(&self, py: Python) -> PyResult<()> {
+        py.check_signals()?;
+
+        if self.is_cancelled() {
+            Err(ZstdCancelledError::new_err("operation cancelled"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
+    module.add_class::<CancellationToken>()?;
+
+    Ok(())
+}
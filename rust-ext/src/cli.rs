@@ -0,0 +1,651 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    crate::{
+        frame_parameters::FramesSummary,
+        frames::{walk_frames, FileFrameSource},
+    },
+    pyo3::{
+        prelude::*,
+        types::{IntoPyDict, PyDict},
+        wrap_pyfunction,
+    },
+};
+
+const USAGE: &str = "\
+usage: python -m zstandard <command> [options] [file ...]
+
+commands:
+  compress      compress files
+  decompress    decompress files
+  test          verify that files decompress
+  list          show frames within files
+  train         train a dictionary from sample files
+  bench         measure compression and decompression speed
+
+options:
+  -l, --level N         compression level (default: 3)
+  -T, --threads N       compression threads; negative uses all CPUs (default: 0)
+  -D, --dict FILE       dictionary to compress or decompress with
+  -o, --output FILE     output file; - for stdout
+  -f, --force           overwrite existing output files
+      --checksum        write content checksums
+      --maxdict N       size of trained dictionaries (default: 112640)
+  -i, --iterations N    maximum benchmark iterations
+  -h, --help            show this help
+  -V, --version         show version
+
+A file of - reads from stdin.";
+
+/// Suffix of compressed files.
+const SUFFIX: &str = ".zst";
+
+enum CliError {
+    /// Command line arguments are invalid.
+    Usage(String),
+    /// An operation failed.
+    Failed(String),
+}
+
+impl From<PyErr> for CliError {
+    fn from(err: PyErr) -> Self {
+        Self::Failed(err.to_string())
+    }
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+/// Write a line of text to `sys.stdout` or `sys.stderr`.
+///
+/// Going through Python keeps output ordered with output written by Python
+/// code and honors any redirection of the streams.
+fn write_line(py: Python, stream: &str, text: &str) -> PyResult<()> {
+    py.import("sys")?
+        .getattr(stream)?
+        .call_method1("write", (format!("{}\n", text),))?;
+
+    Ok(())
+}
+
+/// A file object used by a command.
+struct Handle<'p> {
+    fh: &'p PyAny,
+    /// Whether the CLI opened the file. Standard streams are borrowed and
+    /// must stay open.
+    owned: bool,
+}
+
+impl<'p> Handle<'p> {
+    /// Close the file if we opened it. Otherwise just flush it.
+    fn close(&self) -> PyResult<()> {
+        if self.owned {
+            self.fh.call_method0("close")?;
+        } else {
+            self.fh.call_method0("flush")?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Options {
+    command: Option<String>,
+    files: Vec<String>,
+    level: i32,
+    threads: i32,
+    dict: Option<String>,
+    output: Option<String>,
+    force: bool,
+    checksum: bool,
+    max_dict_size: usize,
+    iterations: Option<usize>,
+    help: bool,
+    version: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            command: None,
+            files: vec![],
+            level: 3,
+            threads: 0,
+            dict: None,
+            output: None,
+            force: false,
+            checksum: false,
+            max_dict_size: 112640,
+            iterations: None,
+            help: false,
+            version: false,
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> CliResult<T> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("invalid value for {}: {}", name, value)))
+}
+
+fn parse_args(args: &[String]) -> CliResult<Options> {
+    let mut options = Options::default();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| CliError::Usage(format!("{} requires a value", arg)))
+        };
+
+        match arg.as_str() {
+            "-l" | "--level" => options.level = parse_number(arg, value()?)?,
+            "-T" | "--threads" => options.threads = parse_number(arg, value()?)?,
+            "-D" | "--dict" => options.dict = Some(value()?.clone()),
+            "-o" | "--output" => options.output = Some(value()?.clone()),
+            "-f" | "--force" => options.force = true,
+            "--checksum" => options.checksum = true,
+            "--maxdict" => options.max_dict_size = parse_number(arg, value()?)?,
+            "-i" | "--iterations" => options.iterations = Some(parse_number(arg, value()?)?),
+            "-h" | "--help" => options.help = true,
+            "-V" | "--version" => options.version = true,
+            "-" => options.files.push(arg.clone()),
+            _ if arg.starts_with('-') => {
+                return Err(CliError::Usage(format!("unknown option: {}", arg)))
+            }
+            _ if options.command.is_none() => options.command = Some(arg.clone()),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Performs operations by calling the Python API of this module.
+///
+/// This guarantees files are processed exactly as they would be by Python
+/// code using the same parameters.
+struct Cli<'p> {
+    py: Python<'p>,
+    module: &'p PyModule,
+    builtins: &'p PyModule,
+    options: Options,
+}
+
+impl<'p> Cli<'p> {
+    fn out(&self, text: &str) -> PyResult<()> {
+        write_line(self.py, "stdout", text)
+    }
+
+    fn err(&self, text: &str) -> PyResult<()> {
+        write_line(self.py, "stderr", text)
+    }
+
+    fn open_input(&self, path: &str) -> CliResult<Handle<'p>> {
+        if path == "-" {
+            Ok(Handle {
+                fh: self.py.import("sys")?.getattr("stdin")?.getattr("buffer")?,
+                owned: false,
+            })
+        } else {
+            Ok(Handle {
+                fh: self.builtins.getattr("open")?.call1((path, "rb"))?,
+                owned: true,
+            })
+        }
+    }
+
+    fn open_output(&self, path: &str) -> CliResult<Handle<'p>> {
+        if path == "-" {
+            return Ok(Handle {
+                fh: self
+                    .py
+                    .import("sys")?
+                    .getattr("stdout")?
+                    .getattr("buffer")?,
+                owned: false,
+            });
+        }
+
+        let exists: bool = self
+            .py
+            .import("os.path")?
+            .call_method1("exists", (path,))?
+            .extract()?;
+
+        if exists && !self.options.force {
+            return Err(CliError::Failed(format!(
+                "{} already exists; use -f to overwrite",
+                path
+            )));
+        }
+
+        Ok(Handle {
+            fh: self.builtins.getattr("open")?.call1((path, "wb"))?,
+            owned: true,
+        })
+    }
+
+    fn read_file(&self, path: &str) -> CliResult<&'p PyAny> {
+        let handle = self.open_input(path)?;
+        let data = handle.fh.call_method0("read");
+        handle.close()?;
+
+        Ok(data?)
+    }
+
+    fn dict_data(&self) -> CliResult<Option<&'p PyAny>> {
+        match &self.options.dict {
+            Some(path) => {
+                let data = self.read_file(path)?;
+
+                Ok(Some(
+                    self.module.getattr("ZstdCompressionDict")?.call1((data,))?,
+                ))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn compressor(&self) -> CliResult<&'p PyAny> {
+        let kwargs = PyDict::new(self.py);
+        kwargs.set_item("level", self.options.level)?;
+        kwargs.set_item("threads", self.options.threads)?;
+        kwargs.set_item("write_checksum", self.options.checksum)?;
+        kwargs.set_item("dict_data", self.dict_data()?)?;
+
+        Ok(self
+            .module
+            .getattr("ZstdCompressor")?
+            .call((), Some(kwargs))?)
+    }
+
+    fn decompressor(&self) -> CliResult<&'p PyAny> {
+        let kwargs = [("dict_data", self.dict_data()?)].into_py_dict(self.py);
+
+        Ok(self
+            .module
+            .getattr("ZstdDecompressor")?
+            .call((), Some(kwargs))?)
+    }
+
+    /// Resolve the output path for each input file.
+    fn output_paths(&self, name: impl Fn(&str) -> CliResult<String>) -> CliResult<Vec<String>> {
+        if let Some(output) = &self.options.output {
+            if self.options.files.len() > 1 {
+                return Err(CliError::Usage(
+                    "-o cannot be used with multiple input files".to_string(),
+                ));
+            }
+
+            return Ok(vec![output.clone()]);
+        }
+
+        self.options
+            .files
+            .iter()
+            .map(|path| {
+                if path == "-" {
+                    Ok("-".to_string())
+                } else {
+                    name(path)
+                }
+            })
+            .collect()
+    }
+
+    /// Run `copy_stream()` on `operator` for every input file.
+    ///
+    /// If `pass_size` is set, the size of regular input files is passed to
+    /// `copy_stream()` so it can be written into frame headers.
+    fn transform(
+        &self,
+        operator: &PyAny,
+        pass_size: bool,
+        name: impl Fn(&str) -> CliResult<String>,
+    ) -> CliResult<()> {
+        for (input, output) in self.options.files.iter().zip(self.output_paths(name)?) {
+            let kwargs = PyDict::new(self.py);
+            if pass_size && input != "-" {
+                let size: u64 = self
+                    .py
+                    .import("os.path")?
+                    .call_method1("getsize", (input,))?
+                    .extract()?;
+                kwargs.set_item("size", size)?;
+            }
+
+            let ifh = self.open_input(input)?;
+
+            let ofh = match self.open_output(&output) {
+                Ok(ofh) => ofh,
+                Err(err) => {
+                    ifh.close()?;
+                    return Err(err);
+                }
+            };
+
+            let result = operator.call_method("copy_stream", (ifh.fh, ofh.fh), Some(kwargs));
+
+            ifh.close()?;
+            ofh.close()?;
+
+            // Don't leave partial output behind.
+            if result.is_err() && ofh.owned {
+                self.py.import("os")?.call_method1("unlink", (&output,))?;
+            }
+
+            let (read, written): (u64, u64) = result
+                .map_err(|err| CliError::Failed(format!("{}: {}", input, err)))?
+                .extract()?;
+
+            self.err(&format!(
+                "{}: {} -> {} bytes ({})",
+                input, read, written, output
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    fn compress(&self) -> CliResult<()> {
+        self.transform(self.compressor()?, true, |path| {
+            Ok(format!("{}{}", path, SUFFIX))
+        })
+    }
+
+    fn decompress(&self) -> CliResult<()> {
+        self.transform(self.decompressor()?, false, |path| {
+            path.strip_suffix(SUFFIX)
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .ok_or_else(|| {
+                    CliError::Failed(format!("{}: unknown suffix; use -o to name output", path))
+                })
+        })
+    }
+
+    fn test(&self) -> CliResult<()> {
+        let dctx = self.decompressor()?;
+        let mut failures = 0;
+
+        for path in &self.options.files {
+            // Files that can't be opened fail without stopping the run.
+            let ifh = match self.open_input(path) {
+                Ok(ifh) => ifh,
+                Err(CliError::Usage(msg)) | Err(CliError::Failed(msg)) => {
+                    self.out(&format!("{}: {}", path, msg))?;
+                    failures += 1;
+                    continue;
+                }
+            };
+
+            let ofh = self
+                .builtins
+                .getattr("open")?
+                .call1((self.py.import("os")?.getattr("devnull")?, "wb"))?;
+
+            let result = dctx.call_method1("copy_stream", (ifh.fh, ofh));
+
+            ofh.call_method0("close")?;
+            ifh.close()?;
+
+            match result {
+                Ok(_) => self.out(&format!("{}: OK", path))?,
+                Err(err) => {
+                    self.out(&format!("{}: {}", path, err))?;
+                    failures += 1;
+                }
+            }
+        }
+
+        if failures > 0 {
+            Err(CliError::Failed(format!(
+                "{} of {} files failed",
+                failures,
+                self.options.files.len()
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn list(&self) -> CliResult<()> {
+        for path in &self.options.files {
+            let handle = self.open_input(path)?;
+
+            // Finding frames requires seeking, which stdin may not support.
+            let fh = if handle.owned {
+                handle.fh
+            } else {
+                self.py
+                    .import("io")?
+                    .getattr("BytesIO")?
+                    .call1((handle.fh.call_method0("read")?,))?
+            };
+
+            let frames = walk_frames(self.py, &mut FileFrameSource::new(fh, 0));
+            handle.close()?;
+
+            let frames = frames.map_err(|err| CliError::Failed(format!("{}: {}", path, err)))?;
+
+            self.out(path)?;
+            self.out(&format!(
+                "  {:>6}  {:>12}  {:>12}  {:>12}  type",
+                "frame", "offset", "compressed", "decompressed"
+            ))?;
+
+            for (i, frame) in frames.iter().enumerate() {
+                self.out(&format!(
+                    "  {:>6}  {:>12}  {:>12}  {:>12}  {}",
+                    i,
+                    frame.offset,
+                    frame.compressed_size,
+                    frame
+                        .content_size()
+                        .map_or_else(|| "unknown".to_string(), |size| size.to_string()),
+                    if frame.is_skippable() {
+                        "skippable".to_string()
+                    } else if frame.is_legacy() {
+                        format!("zstd v0.{}", frame.legacy_version)
+                    } else {
+                        "zstd".to_string()
+                    }
+                ))?;
+            }
+
+            let summary = FramesSummary::from_frames(&frames);
+            let unknown = || "unknown".to_string();
+
+            self.out(&format!("  zstd frames: {}", summary.frame_count()))?;
+            self.out(&format!(
+                "  skippable frames: {}",
+                summary.skippable_frame_count()
+            ))?;
+            self.out(&format!("  compressed size: {}", summary.compressed_size()))?;
+            self.out(&format!(
+                "  decompressed size: {}",
+                summary
+                    .decompressed_size()
+                    .map_or_else(unknown, |size| size.to_string())
+            ))?;
+            self.out(&format!(
+                "  ratio: {}",
+                summary
+                    .ratio()
+                    .map_or_else(unknown, |ratio| format!("{:.3}", ratio))
+            ))?;
+            self.out(&format!("  window size: {}", summary.window_size()))?;
+            self.out(&format!(
+                "  check: {}",
+                summary.checksum().unwrap_or("none")
+            ))?;
+            self.out(&format!(
+                "  dict IDs: {}",
+                if summary.dict_ids().is_empty() {
+                    "none".to_string()
+                } else {
+                    summary
+                        .dict_ids()
+                        .iter()
+                        .map(|id| id.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    fn train(&self) -> CliResult<()> {
+        let samples = self
+            .options
+            .files
+            .iter()
+            .map(|path| self.read_file(path))
+            .collect::<CliResult<Vec<_>>>()?;
+
+        let dict = self
+            .module
+            .getattr("train_dictionary")?
+            .call1((self.options.max_dict_size, samples))?;
+
+        let output = self.options.output.as_deref().unwrap_or("dictionary");
+        let fh = self.open_output(output)?;
+        let written = fh
+            .fh
+            .call_method1("write", (dict.call_method0("as_bytes")?,));
+        fh.close()?;
+
+        let written: usize = written?.extract()?;
+        let dict_id: u32 = dict.call_method0("dict_id")?.extract()?;
+
+        self.err(&format!(
+            "dictionary written to {} ({} bytes, id {})",
+            output, written, dict_id
+        ))?;
+
+        Ok(())
+    }
+
+    fn bench(&self) -> CliResult<()> {
+        for path in &self.options.files {
+            let data = self.read_file(path)?;
+
+            let kwargs = PyDict::new(self.py);
+            kwargs.set_item("levels", vec![self.options.level])?;
+            kwargs.set_item("threads", self.options.threads)?;
+            kwargs.set_item("dict_data", self.dict_data()?)?;
+            kwargs.set_item("max_iterations", self.options.iterations)?;
+
+            let results = self
+                .module
+                .getattr("benchmark")?
+                .call((data,), Some(kwargs))
+                .map_err(|err| CliError::Failed(format!("{}: {}", path, err)))?;
+
+            for result in results.iter()? {
+                let result = result?;
+                let attr = |name: &str| result.getattr(name);
+
+                self.out(&format!(
+                    "{}: level {}, {} -> {} bytes ({:.3}), {:.1} MB/s compress, {:.1} MB/s decompress",
+                    path,
+                    attr("level")?,
+                    attr("size")?,
+                    attr("compressed_size")?,
+                    attr("ratio")?.extract::<f64>()?,
+                    attr("compress_speed")?.extract::<f64>()?,
+                    attr("decompress_speed")?.extract::<f64>()?
+                ))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run(&self) -> CliResult<()> {
+        let command = match &self.options.command {
+            Some(command) => command.as_str(),
+            None => return Err(CliError::Usage("no command specified".to_string())),
+        };
+
+        if self.options.files.is_empty() {
+            return Err(CliError::Usage(format!("{} requires input files", command)));
+        }
+
+        match command {
+            "compress" => self.compress(),
+            "decompress" => self.decompress(),
+            "test" => self.test(),
+            "list" => self.list(),
+            "train" => self.train(),
+            "bench" => self.bench(),
+            _ => Err(CliError::Usage(format!("unknown command: {}", command))),
+        }
+    }
+}
+
+/// Run the command line interface with arguments `args`.
+///
+/// Returns the process exit code.
+#[pyfunction]
+fn cli_main(py: Python, args: Vec<String>) -> PyResult<i32> {
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(CliError::Usage(msg)) | Err(CliError::Failed(msg)) => {
+            write_line(py, "stderr", &format!("zstandard: {}\n\n{}", msg, USAGE))?;
+            return Ok(2);
+        }
+    };
+
+    if options.help {
+        write_line(py, "stdout", USAGE)?;
+        return Ok(0);
+    }
+
+    if options.version {
+        write_line(
+            py,
+            "stdout",
+            &format!(
+                "zstandard {} (zstd {}.{}.{})",
+                crate::VERSION,
+                zstd_safe::VERSION_MAJOR,
+                zstd_safe::VERSION_MINOR,
+                zstd_safe::VERSION_RELEASE
+            ),
+        )?;
+        return Ok(0);
+    }
+
+    let cli = Cli {
+        py,
+        module: py.import("zstandard.backend_rust")?,
+        builtins: py.import("builtins")?,
+        options,
+    };
+
+    match cli.run() {
+        Ok(()) => Ok(0),
+        Err(CliError::Usage(msg)) => {
+            write_line(py, "stderr", &format!("zstandard: {}\n\n{}", msg, USAGE))?;
+            Ok(2)
+        }
+        Err(CliError::Failed(msg)) => {
+            write_line(py, "stderr", &format!("zstandard: {}", msg))?;
+            Ok(1)
+        }
+    }
+}
+
+pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(cli_main, module)?)?;
+
+    Ok(())
+}
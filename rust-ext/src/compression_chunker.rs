@@ -6,7 +6,7 @@
 
 use {
     crate::{
-        exceptions::ZstdError,
+        exceptions::{zstd_error, ZstdError},
         stream::{make_in_buffer_source, InBufferSource},
         zstd_safe::CCtx,
     },
@@ -210,7 +210,7 @@ impl PyIterProtocol for ZstdCompressionChunkerIterator {
                     &mut in_buffer,
                     zstd_sys::ZSTD_EndDirective::ZSTD_e_continue,
                 )
-                .map_err(|msg| ZstdError::new_err(format!("zstd compress error: {}", msg)))?;
+                .map_err(|msg| zstd_error(msg, format!("zstd compress error: {}", msg)))?;
 
             slf.source.record_bytes_read(in_buffer.pos - old_pos);
 
@@ -249,7 +249,7 @@ impl PyIterProtocol for ZstdCompressionChunkerIterator {
             .cctx
             .clone()
             .compress_into_vec(&mut slf.dest_buffer, &mut in_buffer, flush_mode)
-            .map_err(|msg| ZstdError::new_err(format!("zstd compress error: {}", msg)))?;
+            .map_err(|msg| zstd_error(msg, format!("zstd compress error: {}", msg)))?;
 
         // When flushing or finishing, we always emit data in the output
         // buffer. But the operation could fill the output buffer and not be
@@ -7,6 +7,8 @@
 use {
     crate::{
         compression_parameters::{get_cctx_parameter, int_to_strategy, ZstdCompressionParameters},
+        exceptions::zstd_error,
+        memory::resolve_accounting,
         zstd_safe::{train_dictionary_fastcover, CCtx, CDict, DCtx, DDict},
         ZstdError,
     },
@@ -52,30 +54,38 @@ impl ZstdCompressionDict {
             cctx.load_dict_data(&self.data, self.content_type)
         }
         .map_err(|msg| {
-            ZstdError::new_err(format!("could not load compression dictionary: {}", msg))
+            zstd_error(
+                msg,
+                format!("could not load compression dictionary: {}", msg),
+            )
         })
     }
 
     /// Ensure the DDict is populated.
-    pub(crate) fn ensure_ddict(&mut self) -> PyResult<()> {
+    ///
+    /// Its memory is accounted to the process-wide memory tracker.
+    pub(crate) fn ensure_ddict(&mut self, py: Python) -> PyResult<()> {
         if self.ddict.is_some() {
             return Ok(());
         }
 
         self.ddict = Some(
-            DDict::from_data(&self.data, self.content_type)
-                .map_err(|msg| ZstdError::new_err(msg))?,
+            DDict::from_data(&self.data, self.content_type, resolve_accounting(py, &None))
+                .map_err(|msg| zstd_error(msg, msg.to_string()))?,
         );
 
         Ok(())
     }
 
-    pub(crate) fn load_into_dctx(&mut self, dctx: &DCtx) -> PyResult<()> {
-        self.ensure_ddict()?;
+    pub(crate) fn load_into_dctx(&mut self, py: Python, dctx: &DCtx) -> PyResult<()> {
+        self.ensure_ddict(py)?;
 
         dctx.load_prepared_dict(self.ddict.as_ref().unwrap())
             .map_err(|msg| {
-                ZstdError::new_err(format!("unable to reference prepared dictionary: {}", msg))
+                zstd_error(
+                    msg,
+                    format!("unable to reference prepared dictionary: {}", msg),
+                )
             })
     }
 }
@@ -176,8 +186,13 @@ impl ZstdCompressionDict {
         };
 
         self.cdict = Some(
-            CDict::from_data(&self.data, self.content_type, params)
-                .map_err(|msg| ZstdError::new_err(msg))?,
+            CDict::from_data(
+                &self.data,
+                self.content_type,
+                params,
+                resolve_accounting(py, &None),
+            )
+            .map_err(|msg| zstd_error(msg, msg.to_string()))?,
         );
 
         Ok(())
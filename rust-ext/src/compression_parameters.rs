@@ -579,6 +579,12 @@ impl ZstdCompressionParameters {
 
         Ok(size)
     }
+
+    fn estimated_compression_stream_size(&self) -> PyResult<usize> {
+        let size = unsafe { zstd_sys::ZSTD_estimateCStreamSize_usingCCtxParams(self.params) };
+
+        Ok(size)
+    }
 }
 
 pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
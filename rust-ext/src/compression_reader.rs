@@ -6,7 +6,7 @@
 
 use {
     crate::{
-        exceptions::ZstdError,
+        exceptions::{zstd_error, ZstdError},
         stream::{make_in_buffer_source, InBufferSource},
         zstd_safe::CCtx,
     },
@@ -82,7 +82,7 @@ impl ZstdCompressionReader {
                     &mut in_buffer,
                     zstd_sys::ZSTD_EndDirective::ZSTD_e_continue,
                 )
-                .map_err(|msg| ZstdError::new_err(format!("zstd compress error: {}", msg)))?;
+                .map_err(|msg| zstd_error(msg, format!("zstd compress error: {}", msg)))?;
 
             self.bytes_compressed += out_buffer.pos - old_out_pos;
             self.source.record_bytes_read(in_buffer.pos - old_in_pos);
@@ -270,9 +270,7 @@ impl ZstdCompressionReader {
                 &mut in_buffer,
                 zstd_sys::ZSTD_EndDirective::ZSTD_e_end,
             )
-            .map_err(|msg| {
-                ZstdError::new_err(format!("error ending compression stream: {}", msg))
-            })?;
+            .map_err(|msg| zstd_error(msg, format!("error ending compression stream: {}", msg)))?;
 
         self.bytes_compressed += dest_buffer.len() - old_pos;
 
@@ -348,9 +346,7 @@ impl ZstdCompressionReader {
                 &mut in_buffer,
                 zstd_sys::ZSTD_EndDirective::ZSTD_e_end,
             )
-            .map_err(|msg| {
-                ZstdError::new_err(format!("error ending compression stream: {}", msg))
-            })?;
+            .map_err(|msg| zstd_error(msg, format!("error ending compression stream: {}", msg)))?;
 
         self.bytes_compressed += dest_buffer.len() - old_pos;
 
@@ -403,9 +399,7 @@ impl ZstdCompressionReader {
                 &mut in_buffer,
                 zstd_sys::ZSTD_EndDirective::ZSTD_e_end,
             )
-            .map_err(|msg| {
-                ZstdError::new_err(format!("error ending compression stream: {}", msg))
-            })?;
+            .map_err(|msg| zstd_error(msg, format!("error ending compression stream: {}", msg)))?;
 
         self.bytes_compressed += out_buffer.pos - old_pos;
 
@@ -461,9 +455,7 @@ impl ZstdCompressionReader {
                 &mut in_buffer,
                 zstd_sys::ZSTD_EndDirective::ZSTD_e_end,
             )
-            .map_err(|msg| {
-                ZstdError::new_err(format!("error ending compression stream: {}", msg))
-            })?;
+            .map_err(|msg| zstd_error(msg, format!("error ending compression stream: {}", msg)))?;
 
         self.bytes_compressed += out_buffer.pos - old_pos;
 
@@ -0,0 +1,159 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    pyo3::prelude::*,
+    std::time::{Duration, Instant},
+};
+
+#[pyclass(module = "zstandard.backend_rust")]
+pub struct CompressionStats {
+    bytes_read: usize,
+    bytes_written: usize,
+    frames: usize,
+    flushes: usize,
+    zstd_time: Duration,
+    io_time: Duration,
+    compression_level: Option<i32>,
+    window_log: Option<u32>,
+}
+
+#[pymethods]
+impl CompressionStats {
+    #[new]
+    fn new() -> Self {
+        Self {
+            bytes_read: 0,
+            bytes_written: 0,
+            frames: 0,
+            flushes: 0,
+            zstd_time: Duration::ZERO,
+            io_time: Duration::ZERO,
+            compression_level: None,
+            window_log: None,
+        }
+    }
+
+    #[getter]
+    fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    #[getter]
+    fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    #[getter]
+    fn frames(&self) -> usize {
+        self.frames
+    }
+
+    #[getter]
+    fn flushes(&self) -> usize {
+        self.flushes
+    }
+
+    #[getter]
+    fn zstd_time(&self) -> f64 {
+        self.zstd_time.as_secs_f64()
+    }
+
+    #[getter]
+    fn io_time(&self) -> f64 {
+        self.io_time.as_secs_f64()
+    }
+
+    #[getter]
+    fn compression_level(&self) -> Option<i32> {
+        self.compression_level
+    }
+
+    #[getter]
+    fn window_log(&self) -> Option<u32> {
+        self.window_log
+    }
+}
+
+/// Records events of a compression operation into `CompressionStats`.
+///
+/// Does nothing if no statistics were requested.
+pub struct StatsRecorder(Option<Py<CompressionStats>>);
+
+impl StatsRecorder {
+    /// Start recording an operation using the given parameters.
+    pub fn new(
+        py: Python,
+        stats: Option<Py<CompressionStats>>,
+        compression_level: i32,
+        window_log: u32,
+    ) -> Self {
+        if let Some(stats) = &stats {
+            let mut stats = stats.borrow_mut(py);
+            stats.compression_level = Some(compression_level);
+            stats.window_log = Some(window_log);
+        }
+
+        Self(stats)
+    }
+
+    fn update(&self, py: Python, f: impl FnOnce(&mut CompressionStats)) {
+        if let Some(stats) = &self.0 {
+            f(&mut stats.borrow_mut(py));
+        }
+    }
+
+    /// Run `f`, accounting its duration to zstd.
+    pub fn time_zstd<T>(&self, py: Python, f: impl FnOnce() -> T) -> T {
+        self.time(py, f, |stats, elapsed| stats.zstd_time += elapsed)
+    }
+
+    /// Run `f`, accounting its duration to I/O.
+    pub fn time_io<T>(&self, py: Python, f: impl FnOnce() -> T) -> T {
+        self.time(py, f, |stats, elapsed| stats.io_time += elapsed)
+    }
+
+    fn time<T>(
+        &self,
+        py: Python,
+        f: impl FnOnce() -> T,
+        record: impl FnOnce(&mut CompressionStats, Duration),
+    ) -> T {
+        if self.0.is_none() {
+            return f();
+        }
+
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+
+        self.update(py, |stats| record(stats, elapsed));
+
+        result
+    }
+
+    pub fn record_read(&self, py: Python, size: usize) {
+        self.update(py, |stats| stats.bytes_read += size);
+    }
+
+    pub fn record_write(&self, py: Python, size: usize) {
+        self.update(py, |stats| stats.bytes_written += size);
+    }
+
+    pub fn record_frames(&self, py: Python, count: usize) {
+        self.update(py, |stats| stats.frames += count);
+    }
+
+    pub fn record_flush(&self, py: Python) {
+        self.update(py, |stats| stats.flushes += 1);
+    }
+}
+
+pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
+    module.add_class::<CompressionStats>()?;
+
+    Ok(())
+}
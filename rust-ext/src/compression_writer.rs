@@ -5,7 +5,12 @@
 // of the BSD license. See the LICENSE file for details.
 
 use {
-    crate::{exceptions::ZstdError, zstd_safe::CCtx},
+    crate::{
+        compression_stats::StatsRecorder,
+        compressor_parallel::FrameCompressor,
+        exceptions::{zstd_error, ZstdError},
+        zstd_safe::CCtx,
+    },
     pyo3::{
         buffer::PyBuffer,
         exceptions::{PyNotImplementedError, PyOSError, PyValueError},
@@ -29,9 +34,13 @@ pub struct ZstdCompressionWriter {
     closed: bool,
     bytes_compressed: usize,
     dest_buffer: Vec<u8>,
+    /// Compresses input into independent frames instead of using `cctx`.
+    frames: Option<FrameCompressor>,
+    stats: StatsRecorder,
 }
 
 impl ZstdCompressionWriter {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         py: Python,
         cctx: Arc<CCtx<'static>>,
@@ -40,6 +49,8 @@ impl ZstdCompressionWriter {
         write_size: usize,
         write_return_read: bool,
         closefd: bool,
+        frames: Option<FrameCompressor>,
+        stats: StatsRecorder,
     ) -> PyResult<Self> {
         cctx.set_pledged_source_size(source_size)
             .map_err(|msg| ZstdError::new_err(format!("error setting source size: {}", msg)))?;
@@ -54,8 +65,42 @@ impl ZstdCompressionWriter {
             closed: false,
             bytes_compressed: 0,
             dest_buffer: Vec::with_capacity(write_size),
+            frames,
+            stats,
         })
     }
+
+    /// Write output from the frame compressor to the inner writer.
+    fn write_frames(&mut self, py: Python, data: &[u8]) -> PyResult<usize> {
+        let write_size = std::cmp::max(1, self.dest_buffer.capacity());
+
+        for chunk in data.chunks(write_size) {
+            // TODO avoid buffer copy.
+            let chunk = PyBytes::new(py, chunk);
+            self.stats
+                .time_io(py, || self.writer.call_method1(py, "write", (chunk,)))?;
+        }
+
+        self.bytes_compressed += data.len();
+        self.stats.record_write(py, data.len());
+
+        Ok(data.len())
+    }
+
+    /// Write pending output in `dest_buffer` to the inner writer.
+    fn write_dest_buffer(&mut self, py: Python) -> PyResult<()> {
+        if !self.dest_buffer.is_empty() {
+            // TODO avoid buffer copy.
+            let chunk = PyBytes::new(py, &self.dest_buffer);
+            self.stats
+                .time_io(py, || self.writer.call_method1(py, "write", (chunk,)))?;
+
+            self.bytes_compressed += self.dest_buffer.len();
+            self.stats.record_write(py, self.dest_buffer.len());
+        }
+
+        Ok(())
+    }
 }
 
 #[pymethods]
@@ -87,7 +132,11 @@ impl ZstdCompressionWriter {
     }
 
     fn memory_size(&self) -> usize {
-        self.cctx.memory_size()
+        if let Some(frames) = &self.frames {
+            frames.memory_size()
+        } else {
+            self.cctx.memory_size()
+        }
     }
 
     fn fileno(&self, py: Python) -> PyResult<PyObject> {
@@ -212,36 +261,62 @@ impl ZstdCompressionWriter {
             return Err(PyValueError::new_err("stream is closed"));
         }
 
-        let mut total_write = 0;
-
-        let mut in_buffer = zstd_sys::ZSTD_inBuffer {
-            src: buffer.buf_ptr(),
-            size: buffer.len_bytes(),
-            pos: 0,
+        let data = unsafe {
+            std::slice::from_raw_parts(buffer.buf_ptr() as *const u8, buffer.len_bytes())
         };
 
-        while in_buffer.pos < in_buffer.size {
-            self.cctx
-                .compress_into_vec(
-                    &mut self.dest_buffer,
-                    &mut in_buffer,
-                    zstd_sys::ZSTD_EndDirective::ZSTD_e_continue,
-                )
-                .map_err(|msg| ZstdError::new_err(format!("zstd compress error: {}", msg)))?;
-
-            if !self.dest_buffer.is_empty() {
-                // TODO avoid buffer copy.
-                let chunk = PyBytes::new(py, &self.dest_buffer);
-                self.writer.call_method1(py, "write", (chunk,))?;
+        if let Some(frames) = &mut self.frames {
+            let frame_count = frames.frame_count();
+            let output = self.stats.time_zstd(py, || frames.compress(py, data))?;
+            self.stats.record_read(py, data.len());
+            self.stats
+                .record_frames(py, frames.frame_count() - frame_count);
+            let written = self.write_frames(py, &output)?;
+
+            return Ok(if self.write_return_read {
+                data.len()
+            } else {
+                written
+            });
+        }
 
-                total_write += self.dest_buffer.len();
-                self.bytes_compressed += self.dest_buffer.len();
-                self.dest_buffer.clear();
-            }
+        let mut total_write = 0;
+        let mut total_read = 0;
+
+        while total_read < data.len() {
+            let cctx = &self.cctx;
+            let dest_buffer = &mut self.dest_buffer;
+            let source = &data[total_read..];
+
+            total_read += self
+                .stats
+                .time_zstd(py, || {
+                    py.allow_threads(|| {
+                        let mut in_buffer = zstd_sys::ZSTD_inBuffer {
+                            src: source.as_ptr() as *const _,
+                            size: source.len(),
+                            pos: 0,
+                        };
+
+                        cctx.compress_into_vec(
+                            dest_buffer,
+                            &mut in_buffer,
+                            zstd_sys::ZSTD_EndDirective::ZSTD_e_continue,
+                        )
+                        .map(|_| in_buffer.pos)
+                    })
+                })
+                .map_err(|msg| zstd_error(msg, format!("zstd compress error: {}", msg)))?;
+
+            self.write_dest_buffer(py)?;
+            total_write += self.dest_buffer.len();
+            self.dest_buffer.clear();
         }
 
+        self.stats.record_read(py, total_read);
+
         if self.write_return_read {
-            Ok(in_buffer.pos)
+            Ok(total_read)
         } else {
             Ok(total_write)
         }
@@ -264,30 +339,49 @@ impl ZstdCompressionWriter {
 
         let mut total_write = 0;
 
-        let mut in_buffer = zstd_sys::ZSTD_inBuffer {
-            src: std::ptr::null_mut(),
-            size: 0,
-            pos: 0,
-        };
-
-        loop {
-            let zresult = self
-                .cctx
-                .compress_into_vec(&mut self.dest_buffer, &mut in_buffer, flush)
-                .map_err(|msg| ZstdError::new_err(format!("zstd compress error: {}", msg)))?;
-
-            if !self.dest_buffer.is_empty() {
-                // TODO avoid buffer copy.
-                let chunk = PyBytes::new(py, &self.dest_buffer);
-                self.writer.call_method1(py, "write", (chunk,))?;
+        if !self.closing {
+            self.stats.record_flush(py);
+        }
 
+        // Independent frames are always ended when flushing.
+        if let Some(frames) = &mut self.frames {
+            let frame_count = frames.frame_count();
+            let output = self.stats.time_zstd(py, || frames.flush(py))?;
+            self.stats
+                .record_frames(py, frames.frame_count() - frame_count);
+            total_write = self.write_frames(py, &output)?;
+        } else {
+            loop {
+                let cctx = &self.cctx;
+                let dest_buffer = &mut self.dest_buffer;
+
+                let zresult = self
+                    .stats
+                    .time_zstd(py, || {
+                        py.allow_threads(|| {
+                            let mut in_buffer = zstd_sys::ZSTD_inBuffer {
+                                src: std::ptr::null_mut(),
+                                size: 0,
+                                pos: 0,
+                            };
+
+                            cctx.compress_into_vec(dest_buffer, &mut in_buffer, flush)
+                        })
+                    })
+                    .map_err(|msg| zstd_error(msg, format!("zstd compress error: {}", msg)))?;
+
+                self.write_dest_buffer(py)?;
                 total_write += self.dest_buffer.len();
-                self.bytes_compressed += self.dest_buffer.len();
                 self.dest_buffer.clear();
+
+                if zresult == 0 {
+                    break;
+                }
             }
 
-            if zresult == 0 {
-                break;
+            // Ending a frame always emits data, unless no frame was started.
+            if flush == zstd_sys::ZSTD_EndDirective::ZSTD_e_end && total_write > 0 {
+                self.stats.record_frames(py, 1);
             }
         }
 
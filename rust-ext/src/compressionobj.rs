@@ -7,6 +7,7 @@
 use {
     crate::{
         constants::{COMPRESSOBJ_FLUSH_BLOCK, COMPRESSOBJ_FLUSH_FINISH},
+        exceptions::zstd_error,
         zstd_safe::CCtx,
         ZstdError,
     },
@@ -57,7 +58,7 @@ impl ZstdCompressionObj {
                         write_size,
                     )
                 })
-                .or_else(|msg| Err(ZstdError::new_err(format!("zstd compress error: {}", msg))))?;
+                .map_err(|msg| zstd_error(msg, format!("zstd compress error: {}", msg)))?;
 
             compressed.extend(result.0);
             source = result.1;
@@ -94,11 +95,8 @@ impl ZstdCompressionObj {
         loop {
             let (chunk, _, call_again) = py
                 .allow_threads(|| cctx.compress_chunk(&[], flush_mode, write_size))
-                .or_else(|msg| {
-                    Err(ZstdError::new_err(format!(
-                        "error ending compression stream: {}",
-                        msg
-                    )))
+                .map_err(|msg| {
+                    zstd_error(msg, format!("error ending compression stream: {}", msg))
                 })?;
 
             result.extend(&chunk);
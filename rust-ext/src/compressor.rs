@@ -7,19 +7,31 @@
 use {
     crate::{
         buffers::ZstdBufferWithSegmentsCollection,
+        cancellation::{Cancellation, CancellationToken, CANCELLATION_CHUNK_SIZE},
         compression_chunker::ZstdCompressionChunker,
         compression_dict::ZstdCompressionDict,
-        compression_parameters::{CCtxParams, ZstdCompressionParameters},
+        compression_parameters::{get_cctx_parameter, CCtxParams, ZstdCompressionParameters},
         compression_reader::ZstdCompressionReader,
+        compression_stats::{CompressionStats, StatsRecorder},
         compression_writer::ZstdCompressionWriter,
         compressionobj::ZstdCompressionObj,
         compressor_iterator::ZstdCompressorIterator,
-        compressor_multi::multi_compress_to_buffer,
+        compressor_multi::{multi_compress_to_buffer, ZstdMultiCompressorIterator},
+        compressor_parallel::FrameCompressor,
+        exceptions::zstd_error,
+        memory::{resolve_accounting, MemoryAccounting, MemoryTracker},
+        progress::ProgressReporter,
+        threads::ThreadPolicy,
         zstd_safe::CCtx,
         ZstdError,
     },
-    pyo3::{buffer::PyBuffer, exceptions::PyValueError, prelude::*, types::PyBytes},
-    std::sync::Arc,
+    pyo3::{
+        buffer::PyBuffer,
+        exceptions::{PyMemoryError, PyValueError},
+        prelude::*,
+        types::PyBytes,
+    },
+    std::sync::{Arc, Mutex},
 };
 
 #[pyclass(module = "zstandard.backend_rust")]
@@ -27,6 +39,8 @@ struct ZstdCompressor {
     _threads: i32,
     dict: Option<Py<ZstdCompressionDict>>,
     params: CCtxParams<'static>,
+    /// Accounting for memory allocated by contexts of this compressor.
+    accounting: Option<Arc<MemoryAccounting>>,
     cctx: Arc<CCtx<'static>>,
 }
 
@@ -42,6 +56,84 @@ impl ZstdCompressor {
 
         Ok(())
     }
+
+    /// Construct a `FrameCompressor` if independent frames are requested.
+    fn frame_compressor(
+        &self,
+        py: Python,
+        size: Option<u64>,
+        frame_size: Option<usize>,
+        threads: Option<isize>,
+    ) -> PyResult<Option<FrameCompressor>> {
+        let frame_size = match frame_size {
+            Some(frame_size) => frame_size,
+            None if threads.is_some() => {
+                return Err(PyValueError::new_err("threads requires frame_size"));
+            }
+            None => return Ok(None),
+        };
+
+        self.ensure_dynamic_context("frame_size")?;
+
+        if size.is_some() {
+            return Err(PyValueError::new_err(
+                "size cannot be specified with frame_size",
+            ));
+        }
+
+        let threads = match threads {
+            None => num_cpus::get(),
+            Some(threads) if threads < 0 => num_cpus::get(),
+            Some(threads) if threads < 2 => 1,
+            Some(threads) => threads as _,
+        };
+
+        Ok(Some(FrameCompressor::new(
+            py,
+            &self.params,
+            &self.dict,
+            &self.accounting,
+            frame_size,
+            threads,
+        )?))
+    }
+
+    /// Start recording statistics for an operation on `source_size` bytes.
+    fn stats_recorder(
+        &self,
+        py: Python,
+        stats: Option<Py<CompressionStats>>,
+        source_size: u64,
+    ) -> PyResult<StatsRecorder> {
+        if stats.is_none() {
+            return Ok(StatsRecorder::new(py, None, 0, 0));
+        }
+
+        let params = unsafe { self.params.get_raw_ptr() };
+
+        let level = get_cctx_parameter(params, zstd_sys::ZSTD_cParameter::ZSTD_c_compressionLevel)?;
+
+        // zstd derives the window from the level and source size unless set.
+        let window_log =
+            match get_cctx_parameter(params, zstd_sys::ZSTD_cParameter::ZSTD_c_windowLog)? {
+                0 => unsafe { zstd_sys::ZSTD_getCParams(level, source_size, 0) }.windowLog,
+                window_log => window_log as u32,
+            };
+
+        Ok(StatsRecorder::new(py, stats, level, window_log))
+    }
+
+    /// Ensure a feature creating additional contexts can be used.
+    fn ensure_dynamic_context(&self, feature: &str) -> PyResult<()> {
+        if self.cctx.is_static() {
+            Err(PyValueError::new_err(format!(
+                "{} cannot be used with a static context",
+                feature
+            )))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 #[pymethods]
@@ -54,8 +146,11 @@ impl ZstdCompressor {
         write_checksum = "None",
         write_content_size = "None",
         write_dict_id = "None",
-        threads = "0"
+        threads = "0",
+        memory_tracker = "None",
+        static_context_size = "None"
     )]
+    #[allow(clippy::too_many_arguments)]
     fn new(
         py: Python,
         level: i32,
@@ -65,6 +160,8 @@ impl ZstdCompressor {
         write_content_size: Option<bool>,
         write_dict_id: Option<bool>,
         threads: i32,
+        memory_tracker: Option<Py<MemoryTracker>>,
+        static_context_size: Option<usize>,
     ) -> PyResult<Self> {
         if level > zstd_safe::max_c_level() {
             return Err(PyValueError::new_err(format!(
@@ -79,7 +176,26 @@ impl ZstdCompressor {
             threads
         };
 
-        let cctx = Arc::new(CCtx::new().or_else(|msg| Err(PyErr::new::<ZstdError, _>(msg)))?);
+        let (accounting, cctx) = if let Some(size) = static_context_size {
+            if threads != 0 {
+                return Err(PyValueError::new_err(
+                    "cannot define static_context_size and threads",
+                ));
+            }
+            if memory_tracker.is_some() {
+                return Err(PyValueError::new_err(
+                    "cannot define static_context_size and memory_tracker",
+                ));
+            }
+
+            (None, CCtx::new_static(size).map_err(PyValueError::new_err)?)
+        } else {
+            let accounting = resolve_accounting(py, &memory_tracker);
+            let cctx = CCtx::new(accounting.clone()).map_err(PyMemoryError::new_err)?;
+
+            (accounting, cctx)
+        };
+        let cctx = Arc::new(cctx);
         let params = CCtxParams::create()?;
 
         if let Some(compression_params) = &compression_params {
@@ -138,6 +254,7 @@ impl ZstdCompressor {
             _threads: threads,
             dict: dict_data,
             params,
+            accounting,
             cctx,
         };
 
@@ -160,16 +277,46 @@ impl ZstdCompressor {
         ))
     }
 
-    fn compress<'p>(&self, py: Python<'p>, buffer: PyBuffer<u8>) -> PyResult<&'p PyBytes> {
+    #[args(buffer, cancellation_token = "None")]
+    fn compress<'p>(
+        &self,
+        py: Python<'p>,
+        buffer: PyBuffer<u8>,
+        cancellation_token: Option<Py<CancellationToken>>,
+    ) -> PyResult<&'p PyBytes> {
         let source: &[u8] =
             unsafe { std::slice::from_raw_parts(buffer.buf_ptr() as *const _, buffer.len_bytes()) };
 
         let cctx = &self.cctx;
 
+        let cancellation = Cancellation::new(py, cancellation_token);
+        cancellation.check(py)?;
+
+        // Large inputs are fed to zstd in chunks so signal handlers and the
+        // cancellation token can stop compression in between.
+        let error = Mutex::new(None);
+        let stop = || {
+            Python::with_gil(|py| match cancellation.check(py) {
+                Ok(()) => false,
+                Err(err) => {
+                    *error.lock().unwrap() = Some(err);
+                    true
+                }
+            })
+        };
+
         // TODO implement 0 copy via Py_SIZE().
-        let data = py
-            .allow_threads(|| cctx.compress(source))
-            .or_else(|msg| Err(ZstdError::new_err(format!("cannot compress: {}", msg))))?;
+        let mut data = vec![];
+
+        let completed = py
+            .allow_threads(|| {
+                cctx.compress_append_chunked(source, &mut data, CANCELLATION_CHUNK_SIZE, stop)
+            })
+            .map_err(|msg| zstd_error(msg, format!("cannot compress: {}", msg)))?;
+
+        if !completed {
+            return Err(error.into_inner().unwrap().unwrap());
+        }
 
         Ok(PyBytes::new(py, &data))
     }
@@ -215,7 +362,19 @@ impl ZstdCompressor {
         ZstdCompressionObj::new(self.cctx.clone())
     }
 
-    #[args(ifh, ofh, size = "None", read_size = "None", write_size = "None")]
+    #[args(
+        ifh,
+        ofh,
+        size = "None",
+        read_size = "None",
+        write_size = "None",
+        frame_size = "None",
+        threads = "None",
+        stats = "None",
+        progress = "None",
+        progress_interval = "None"
+    )]
+    #[allow(clippy::too_many_arguments)]
     fn copy_stream(
         &self,
         py: Python,
@@ -224,6 +383,11 @@ impl ZstdCompressor {
         size: Option<u64>,
         read_size: Option<usize>,
         write_size: Option<usize>,
+        frame_size: Option<usize>,
+        threads: Option<isize>,
+        stats: Option<Py<CompressionStats>>,
+        progress: Option<PyObject>,
+        progress_interval: Option<usize>,
     ) -> PyResult<(usize, usize)> {
         let source_size = if let Some(source_size) = size {
             source_size
@@ -245,6 +409,25 @@ impl ZstdCompressor {
             ));
         }
 
+        let mut progress = ProgressReporter::new(py, progress, progress_interval)?;
+
+        if let Some(frames) = self.frame_compressor(py, size, frame_size, threads)? {
+            let stats = self.stats_recorder(py, stats, frame_size.unwrap_or(0) as u64)?;
+
+            return copy_stream_frames(
+                py,
+                frames,
+                ifh,
+                ofh,
+                read_size,
+                write_size,
+                &stats,
+                &mut progress,
+            );
+        }
+
+        let stats = self.stats_recorder(py, stats, source_size)?;
+
         self.cctx.reset();
         self.cctx
             .set_pledged_source_size(source_size)
@@ -260,7 +443,7 @@ impl ZstdCompressor {
 
         loop {
             // Try to read from source stream.
-            let read_object = ifh.call_method("read", (read_size,), None)?;
+            let read_object = stats.time_io(py, || ifh.call_method("read", (read_size,), None))?;
 
             let read_bytes: &PyBytes = read_object.downcast()?;
             let read_data = read_bytes.as_bytes();
@@ -271,6 +454,7 @@ impl ZstdCompressor {
             }
 
             total_read += read_data.len();
+            stats.record_read(py, read_data.len());
 
             // Send data to compressor.
 
@@ -278,17 +462,17 @@ impl ZstdCompressor {
             let cctx = &self.cctx;
 
             while !source.is_empty() {
-                let result = py
-                    .allow_threads(|| {
-                        cctx.compress_chunk(
-                            source,
-                            zstd_sys::ZSTD_EndDirective::ZSTD_e_continue,
-                            write_size,
-                        )
+                let result = stats
+                    .time_zstd(py, || {
+                        py.allow_threads(|| {
+                            cctx.compress_chunk(
+                                source,
+                                zstd_sys::ZSTD_EndDirective::ZSTD_e_continue,
+                                write_size,
+                            )
+                        })
                     })
-                    .or_else(|msg| {
-                        Err(ZstdError::new_err(format!("zstd compress error: {}", msg)))
-                    })?;
+                    .map_err(|msg| zstd_error(msg, format!("zstd compress error: {}", msg)))?;
 
                 source = result.1;
 
@@ -297,22 +481,29 @@ impl ZstdCompressor {
                 if !chunk.is_empty() {
                     // TODO avoid buffer copy.
                     let data = PyBytes::new(py, chunk);
-                    ofh.call_method("write", (data,), None)?;
+                    stats.time_io(py, || ofh.call_method("write", (data,), None))?;
                     total_write += chunk.len();
+                    stats.record_write(py, chunk.len());
                 }
             }
+
+            progress.update(py, total_read, total_read, total_write)?;
         }
 
         // We've finished reading. Now flush the compressor stream.
         loop {
-            let result = self
-                .cctx
-                .compress_chunk(&[], zstd_sys::ZSTD_EndDirective::ZSTD_e_end, write_size)
-                .or_else(|msg| {
-                    Err(ZstdError::new_err(format!(
-                        "error ending compression stream: {}",
-                        msg
-                    )))
+            let result = stats
+                .time_zstd(py, || {
+                    py.allow_threads(|| {
+                        self.cctx.compress_chunk(
+                            &[],
+                            zstd_sys::ZSTD_EndDirective::ZSTD_e_end,
+                            write_size,
+                        )
+                    })
+                })
+                .map_err(|msg| {
+                    zstd_error(msg, format!("error ending compression stream: {}", msg))
                 })?;
 
             let chunk = &result.0;
@@ -320,8 +511,9 @@ impl ZstdCompressor {
             if !chunk.is_empty() {
                 // TODO avoid buffer copy.
                 let data = PyBytes::new(py, &chunk);
-                ofh.call_method("write", (data,), None)?;
+                stats.time_io(py, || ofh.call_method("write", (data,), None))?;
                 total_write += chunk.len();
+                stats.record_write(py, chunk.len());
             }
 
             if !result.2 {
@@ -329,17 +521,71 @@ impl ZstdCompressor {
             }
         }
 
+        stats.record_frames(py, 1);
+        progress.finish(py, total_read, total_read, total_write)?;
+
         Ok((total_read, total_write))
     }
 
-    #[args(data, threads = "0")]
+    #[args(
+        data,
+        threads = "ThreadPolicy::Count(1)",
+        progress = "None",
+        progress_interval = "None",
+        cancellation_token = "None"
+    )]
     fn multi_compress_to_buffer(
         &self,
         py: Python,
         data: &PyAny,
-        threads: isize,
+        threads: ThreadPolicy,
+        progress: Option<PyObject>,
+        progress_interval: Option<usize>,
+        cancellation_token: Option<Py<CancellationToken>>,
     ) -> PyResult<ZstdBufferWithSegmentsCollection> {
-        multi_compress_to_buffer(py, &self.params, &self.dict, data, threads)
+        self.ensure_dynamic_context("multi_compress_to_buffer()")?;
+
+        let mut progress = ProgressReporter::new(py, progress, progress_interval)?;
+        let cancellation = Cancellation::new(py, cancellation_token);
+
+        multi_compress_to_buffer(
+            py,
+            &self.params,
+            &self.dict,
+            &self.accounting,
+            data,
+            threads,
+            &mut progress,
+            cancellation,
+        )
+    }
+
+    #[args(
+        data,
+        threads = "ThreadPolicy::Count(1)",
+        max_in_flight = "None",
+        ordered = "true"
+    )]
+    fn multi_compress_to_iter(
+        &self,
+        py: Python,
+        data: &PyAny,
+        threads: ThreadPolicy,
+        max_in_flight: Option<usize>,
+        ordered: bool,
+    ) -> PyResult<ZstdMultiCompressorIterator> {
+        self.ensure_dynamic_context("multi_compress_to_iter()")?;
+
+        ZstdMultiCompressorIterator::new(
+            py,
+            &self.params,
+            &self.dict,
+            &self.accounting,
+            data,
+            threads,
+            max_in_flight,
+            ordered,
+        )
     }
 
     #[args(reader, size = "None", read_size = "None", write_size = "None")]
@@ -382,8 +628,12 @@ impl ZstdCompressor {
         size = "None",
         write_size = "None",
         write_return_read = "true",
-        closefd = "true"
+        closefd = "true",
+        frame_size = "None",
+        threads = "None",
+        stats = "None"
     )]
+    #[allow(clippy::too_many_arguments)]
     fn stream_writer(
         &self,
         py: Python,
@@ -392,6 +642,9 @@ impl ZstdCompressor {
         write_size: Option<usize>,
         write_return_read: bool,
         closefd: bool,
+        frame_size: Option<usize>,
+        threads: Option<isize>,
+        stats: Option<Py<CompressionStats>>,
     ) -> PyResult<ZstdCompressionWriter> {
         if !writer.hasattr("write")? {
             return Err(PyValueError::new_err(
@@ -399,11 +652,18 @@ impl ZstdCompressor {
             ));
         }
 
+        let frames = self.frame_compressor(py, size, frame_size, threads)?;
+
         self.cctx.reset();
 
         let size = size.unwrap_or(zstd_sys::ZSTD_CONTENTSIZE_UNKNOWN as _);
         let write_size = write_size.unwrap_or_else(|| unsafe { zstd_sys::ZSTD_CStreamOutSize() });
 
+        let stats = match frame_size {
+            Some(frame_size) => self.stats_recorder(py, stats, frame_size as u64)?,
+            None => self.stats_recorder(py, stats, size)?,
+        };
+
         ZstdCompressionWriter::new(
             py,
             self.cctx.clone(),
@@ -412,10 +672,60 @@ impl ZstdCompressor {
             write_size,
             write_return_read,
             closefd,
+            frames,
+            stats,
         )
     }
 }
 
+/// Compress a stream into independent frames in the pzstd format.
+#[allow(clippy::too_many_arguments)]
+fn copy_stream_frames(
+    py: Python,
+    mut frames: FrameCompressor,
+    ifh: &PyAny,
+    ofh: &PyAny,
+    read_size: usize,
+    write_size: usize,
+    stats: &StatsRecorder,
+    progress: &mut ProgressReporter,
+) -> PyResult<(usize, usize)> {
+    let mut total_read = 0;
+    let mut total_write = 0;
+
+    loop {
+        let read_object = stats.time_io(py, || ifh.call_method1("read", (read_size,)))?;
+        let read_bytes: &PyBytes = read_object.downcast()?;
+        let read_data = read_bytes.as_bytes();
+
+        let output = if read_data.is_empty() {
+            stats.time_zstd(py, || frames.flush(py))?
+        } else {
+            total_read += read_data.len();
+            stats.record_read(py, read_data.len());
+            stats.time_zstd(py, || frames.compress(py, read_data))?
+        };
+
+        for chunk in output.chunks(write_size) {
+            // TODO avoid buffer copy.
+            stats.time_io(py, || ofh.call_method1("write", (PyBytes::new(py, chunk),)))?;
+            total_write += chunk.len();
+            stats.record_write(py, chunk.len());
+        }
+
+        if read_data.is_empty() {
+            break;
+        }
+
+        progress.update(py, total_read, total_read, total_write)?;
+    }
+
+    stats.record_frames(py, frames.frame_count());
+    progress.finish(py, total_read, total_read, total_write)?;
+
+    Ok((total_read, total_write))
+}
+
 pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
     module.add_class::<ZstdCompressor>()?;
 
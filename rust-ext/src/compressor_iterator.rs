@@ -6,7 +6,7 @@
 
 use {
     crate::{
-        exceptions::ZstdError,
+        exceptions::{zstd_error, ZstdError},
         stream::{make_in_buffer_source, InBufferSource},
         zstd_safe::CCtx,
     },
@@ -75,7 +75,7 @@ impl PyIterProtocol for ZstdCompressorIterator {
                     &mut in_buffer,
                     zstd_sys::ZSTD_EndDirective::ZSTD_e_continue,
                 )
-                .map_err(|msg| ZstdError::new_err(format!("zstd compress error: {}", msg)))?;
+                .map_err(|msg| zstd_error(msg, format!("zstd compress error: {}", msg)))?;
 
             slf.source.record_bytes_read(in_buffer.pos - old_pos);
 
@@ -106,9 +106,7 @@ impl PyIterProtocol for ZstdCompressorIterator {
                 &mut in_buffer,
                 zstd_sys::ZSTD_EndDirective::ZSTD_e_end,
             )
-            .map_err(|msg| {
-                ZstdError::new_err(format!("error ending compression stream: {}", msg))
-            })?;
+            .map_err(|msg| zstd_error(msg, format!("error ending compression stream: {}", msg)))?;
 
         if zresult == 0 {
             slf.finished_output = true;
@@ -6,41 +6,55 @@
 
 use {
     crate::{
-        buffers::{BufferSegment, ZstdBufferWithSegments, ZstdBufferWithSegmentsCollection},
+        buffers::{
+            extract_offsets, segments_from_offsets, BufferSegment, ZstdBufferWithSegments,
+            ZstdBufferWithSegmentsCollection,
+        },
+        cancellation::{Cancellation, CANCELLATION_CHECK_INTERVAL, CANCELLATION_CHUNK_SIZE},
         compression_dict::ZstdCompressionDict,
         compression_parameters::CCtxParams,
-        exceptions::ZstdError,
-        zstd_safe::CCtx,
+        exceptions::{zstd_error, ZstdError},
+        memory::MemoryAccounting,
+        progress::ProgressReporter,
+        threads::{process_batches, split_batches, ThreadPolicy},
+        zstd_safe::{CCtx, ZstdFailure},
     },
     pyo3::{
         buffer::PyBuffer,
-        exceptions::{PyTypeError, PyValueError},
+        exceptions::{PyMemoryError, PyTypeError, PyValueError},
         prelude::*,
-        types::{PyBytes, PyList, PyTuple},
-        PySequenceProtocol,
+        types::{PyBytes, PyIterator, PyTuple},
+        PyIterProtocol, PyMappingProtocol, PyNativeType,
+    },
+    std::{
+        collections::{BTreeMap, VecDeque},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Mutex,
+        },
     },
-    rayon::prelude::*,
 };
 
+/// Number of items to keep in flight per thread by default.
+const ITEMS_PER_THREAD: usize = 4;
+
 struct DataSource<'a> {
     data: &'a [u8],
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn multi_compress_to_buffer(
     py: Python,
     params: &CCtxParams,
     dict: &Option<Py<ZstdCompressionDict>>,
+    accounting: &Option<Arc<MemoryAccounting>>,
     data: &PyAny,
-    threads: isize,
+    threads: ThreadPolicy,
+    progress: &mut ProgressReporter,
+    cancellation: Cancellation,
 ) -> PyResult<ZstdBufferWithSegmentsCollection> {
-    let threads = if threads < 0 {
-        num_cpus::get()
-    } else if threads < 2 {
-        1
-    } else {
-        threads as _
-    };
-
+    // Buffers backing `sources`. Must outlive compression.
+    let mut buffers: Vec<PyBuffer<u8>> = vec![];
     let mut sources = vec![];
     let mut total_source_size = 0;
 
@@ -69,11 +83,41 @@ pub fn multi_compress_to_buffer(
                 total_source_size += slice.len();
             }
         }
-    } else if let Ok(list) = data.extract::<&PyList>() {
-        sources.reserve_exact(list.len());
+    } else if let Some(offsets) = columnar_offsets(py, data) {
+        let offsets = offsets?;
+        let data_obj = data.downcast::<PyTuple>()?.get_item(0);
+
+        let buffer: PyBuffer<u8> = PyBuffer::get(data_obj)
+            .map_err(|_| PyTypeError::new_err("data buffer not a bytes like object"))?;
+
+        if !buffer.is_c_contiguous() {
+            return Err(PyValueError::new_err("data buffer must be contiguous"));
+        }
+
+        let data = unsafe {
+            std::slice::from_raw_parts(buffer.buf_ptr() as *const u8, buffer.len_bytes())
+        };
+
+        let segments = segments_from_offsets(&offsets, data.len())?;
+
+        sources.reserve_exact(segments.len());
+
+        for segment in segments {
+            let slice = &data[segment.offset as usize..(segment.offset + segment.length) as usize];
+
+            sources.push(DataSource { data: slice });
+            total_source_size += slice.len();
+        }
 
-        for (i, item) in list.iter().enumerate() {
-            let buffer: PyBuffer<u8> = PyBuffer::get(item)
+        buffers.push(buffer);
+    } else if let Ok(iter) = PyIterator::from_object(py, data) {
+        if let Ok(len) = data.len() {
+            sources.reserve_exact(len);
+            buffers.reserve_exact(len);
+        }
+
+        for (i, item) in iter.enumerate() {
+            let buffer: PyBuffer<u8> = PyBuffer::get(item?)
                 .map_err(|_| PyTypeError::new_err(format!("item {} not a bytes like object", i)))?;
 
             let slice = unsafe {
@@ -82,10 +126,15 @@ pub fn multi_compress_to_buffer(
 
             sources.push(DataSource { data: slice });
             total_source_size += slice.len();
+
+            // Items produced by the iterator may not be referenced anywhere
+            // else. Holding the buffer keeps them alive.
+            buffers.push(buffer);
         }
     } else {
         return Err(PyTypeError::new_err(
-            "argument must be list of BufferWithSegments",
+            "argument must be a BufferWithSegments, BufferWithSegmentsCollection, \
+             (data, offsets) tuple, or iterable of bytes like objects",
         ));
     }
 
@@ -97,37 +146,47 @@ pub fn multi_compress_to_buffer(
         return Err(PyValueError::new_err("source elements are empty"));
     }
 
-    compress_from_datasources(py, params, dict, sources, threads)
+    let threads = threads.thread_count(sources.len(), total_source_size);
+
+    compress_from_datasources(
+        py,
+        params,
+        dict,
+        accounting,
+        sources,
+        threads,
+        progress,
+        cancellation,
+    )
 }
 
-/// Holds results of an individual compression operation.
-struct WorkerResult {
-    source_offset: usize,
-    error: Option<&'static str>,
-    data: Option<Vec<u8>>,
+/// Obtain item boundaries from a `(data, offsets)` tuple.
+///
+/// Returns `None` if `obj` doesn't have this shape.
+fn columnar_offsets(py: Python, obj: &PyAny) -> Option<PyResult<Vec<i64>>> {
+    let tuple = obj.downcast::<PyTuple>().ok()?;
+
+    if tuple.len() != 2 {
+        return None;
+    }
+
+    extract_offsets(py, tuple.get_item(1))
 }
 
-fn compress_from_datasources(
+/// Create compression contexts for use by worker threads.
+///
+/// Contexts reference `dict`, which must outlive them.
+pub(crate) fn create_cctxs(
     py: Python,
     params: &CCtxParams,
     dict: &Option<Py<ZstdCompressionDict>>,
-    sources: Vec<DataSource>,
-    thread_count: usize,
-) -> PyResult<ZstdBufferWithSegmentsCollection> {
-    // More threads than inputs makes no sense.
-    let thread_count = std::cmp::min(thread_count, sources.len());
+    accounting: &Option<Arc<MemoryAccounting>>,
+    count: usize,
+) -> PyResult<Vec<CCtx<'static>>> {
+    let mut cctxs = Vec::with_capacity(count);
 
-    // TODO lower thread count when input size is too small and threads
-    // would add overhead.
-
-    let mut cctxs = Vec::with_capacity(thread_count);
-    let results = std::sync::Mutex::new(Vec::with_capacity(sources.len()));
-
-    // TODO there are tons of inefficiencies in this implementation compared
-    // to the C backend.
-
-    for _ in 0..thread_count {
-        let cctx = CCtx::new().map_err(|msg| ZstdError::new_err(msg))?;
+    for _ in 0..count {
+        let cctx = CCtx::new(accounting.clone()).map_err(PyMemoryError::new_err)?;
 
         cctx.set_parameters(params).map_err(|msg| {
             ZstdError::new_err(format!("could not set compression parameters: {}", msg))
@@ -140,79 +199,412 @@ fn compress_from_datasources(
         cctxs.push(cctx);
     }
 
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(thread_count)
-        .build()
-        .map_err(|err| ZstdError::new_err(format!("error initializing thread pool: {}", err)))?;
+    Ok(cctxs)
+}
+
+/// Compressed frames for a contiguous range of sources.
+///
+/// Frames are appended to a single allocation, which backs a
+/// `BufferWithSegments` without being copied.
+#[derive(Default)]
+struct Arena {
+    data: Vec<u8>,
+    segments: Vec<BufferSegment>,
+    /// Index and message of the item that failed to compress.
+    /// No further items are compressed into the arena after an error.
+    error: Option<(usize, ZstdFailure)>,
+}
 
-    pool.install(|| {
-        sources.par_iter().enumerate().for_each(|(index, source)| {
-            let thread_index = pool.current_thread_index().unwrap();
+#[allow(clippy::too_many_arguments)]
+fn compress_from_datasources(
+    py: Python,
+    params: &CCtxParams,
+    dict: &Option<Py<ZstdCompressionDict>>,
+    accounting: &Option<Arc<MemoryAccounting>>,
+    sources: Vec<DataSource>,
+    thread_count: usize,
+    progress: &mut ProgressReporter,
+    cancellation: Cancellation,
+) -> PyResult<ZstdBufferWithSegmentsCollection> {
+    let cctxs = create_cctxs(py, params, dict, accounting, thread_count)?;
+
+    // Each batch is a contiguous range of sources compressed into its own
+    // arena. Results are collected in order, so frames in the arenas are in
+    // input order.
+    let sizes = sources
+        .iter()
+        .map(|source| source.data.len())
+        .collect::<Vec<_>>();
+    let batches = split_batches(&sizes, thread_count);
+
+    // Set to stop compressing.
+    let cancelled = AtomicBool::new(false);
+    let stop = || cancelled.load(Ordering::Relaxed) || cancellation.is_cancelled();
+
+    let report_items = progress.is_enabled();
+
+    // Workers send the input and output sizes of completed items if progress
+    // is reported. The sender is dropped once all items were compressed.
+    let compress = |sender: mpsc::Sender<(usize, usize)>| -> Vec<Arena> {
+        process_batches(&cctxs, &batches, |cctx, batch| {
+            let mut arena = Arena::default();
+
+            for (i, source) in sources[batch.clone()].iter().enumerate() {
+                if stop() {
+                    break;
+                }
 
-            let cctx = &cctxs[thread_index];
+                let offset = arena.data.len();
+
+                match cctx.compress_append_chunked(
+                    source.data,
+                    &mut arena.data,
+                    CANCELLATION_CHUNK_SIZE,
+                    stop,
+                ) {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(msg) => {
+                        arena.error = Some((batch.start + i, msg));
+                        break;
+                    }
+                }
 
-            let mut result = WorkerResult {
-                source_offset: index,
-                error: None,
-                data: None,
-            };
+                arena.segments.push(BufferSegment {
+                    offset: offset as _,
+                    length: (arena.data.len() - offset) as _,
+                });
 
-            match cctx.compress(source.data) {
-                Ok(chunk) => {
-                    result.data = Some(chunk);
+                if report_items {
+                    // The receiver outlives the workers.
+                    let _ = sender.send((source.data.len(), arena.data.len() - offset));
                 }
-                Err(msg) => {
-                    result.error = Some(msg);
+            }
+
+            arena
+        })
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let receiver = Mutex::new(receiver);
+
+    // Compression runs on another thread so this one can regularly acquire
+    // the GIL to report progress and check for cancellation.
+    let arenas = std::thread::scope(|scope| -> PyResult<Vec<Arena>> {
+        let worker = scope.spawn(|| compress(sender));
+
+        let mut result = Ok(());
+        let (mut items, mut read, mut written) = (0, 0, 0);
+        let mut finished = false;
+
+        // Workers stopped early by the token leave items uncompressed, so it
+        // is checked once more after they finished.
+        while !finished {
+            match py.allow_threads(|| {
+                receiver
+                    .lock()
+                    .unwrap()
+                    .recv_timeout(CANCELLATION_CHECK_INTERVAL)
+            }) {
+                Ok((input_size, output_size)) => {
+                    items += 1;
+                    read += input_size;
+                    written += output_size;
+
+                    if result.is_ok() {
+                        result = progress.update(py, items, read, written);
+                    }
                 }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => finished = true,
             }
 
-            // TODO we can do better than a shared lock.
-            results.lock().unwrap().push(result);
-        });
-    });
+            if result.is_ok() {
+                result = cancellation.check(py);
+            }
+
+            if result.is_err() {
+                cancelled.store(true, Ordering::Relaxed);
+            }
+        }
+
+        let arenas = py.allow_threads(|| worker.join()).unwrap();
+
+        result?;
+        progress.finish(py, items, read, written)?;
+
+        Ok(arenas)
+    })?;
 
-    // Need to sort results by their input order or else results aren't
-    // deterministic.
-    results
-        .lock()
-        .unwrap()
-        .sort_by(|a, b| a.source_offset.cmp(&b.source_offset));
+    arenas_to_collection(py, arenas)
+}
+
+/// Collect the frames compressed into arenas.
+///
+/// Raises the error of the first failed item, if any.
+fn arenas_to_collection(
+    py: Python,
+    arenas: Vec<Arena>,
+) -> PyResult<ZstdBufferWithSegmentsCollection> {
+    if let Some((index, msg)) = arenas.iter().find_map(|arena| arena.error) {
+        return Err(zstd_error(
+            msg,
+            format!("error compressing item {}: {}", index, msg),
+        ));
+    }
 
-    // TODO this is horribly inefficient due to memory copies.
     let els = PyTuple::new(
         py,
-        results
-            .lock()
-            .unwrap()
-            .iter()
-            .map(|result| {
-                if let Some(msg) = result.error {
-                    return Err(ZstdError::new_err(format!(
-                        "error compressing item {}: {}",
-                        result.source_offset, msg
-                    )));
-                }
-
-                let data = result.data.as_ref().unwrap();
-                let chunk = PyBytes::new(py, data);
-                let segments = vec![BufferSegment {
-                    offset: 0,
-                    length: data.len() as _,
-                }];
-
-                let segments = unsafe {
-                    PyBytes::from_ptr(
-                        py,
-                        segments.as_ptr() as *const _,
-                        segments.len() * std::mem::size_of::<BufferSegment>(),
-                    )
-                };
-                let segments_buffer = PyBuffer::get(segments)?;
-
-                Py::new(py, ZstdBufferWithSegments::new(py, chunk, segments_buffer)?)
+        arenas
+            .into_iter()
+            .filter(|arena| !arena.segments.is_empty())
+            .map(|arena| {
+                Py::new(
+                    py,
+                    ZstdBufferWithSegments::from_vec(py, arena.data, arena.segments)?,
+                )
             })
             .collect::<PyResult<Vec<_>>>()?,
     );
 
     ZstdBufferWithSegmentsCollection::new(py, els)
 }
+
+/// Result of compressing an item, keyed by its index in the source.
+type ItemResult = (usize, Result<Vec<u8>, ZstdFailure>);
+
+/// Work shared between an iterator and its workers.
+#[derive(Default)]
+struct IteratorQueue {
+    /// Items waiting for a worker, with their index in the source.
+    items: VecDeque<(usize, Vec<u8>)>,
+    /// Contexts not in use by a worker.
+    idle: Vec<CCtx<'static>>,
+}
+
+/// Compress queued items with `cctx` until the queue is empty.
+///
+/// Runs on the global thread pool. The context is returned to the queue
+/// when there is no more work.
+fn compress_queued(
+    queue: Arc<Mutex<IteratorQueue>>,
+    cctx: CCtx<'static>,
+    sender: mpsc::Sender<ItemResult>,
+) {
+    loop {
+        let (index, data) = {
+            let mut queue = queue.lock().unwrap();
+
+            match queue.items.pop_front() {
+                Some(item) => item,
+                None => {
+                    queue.idle.push(cctx);
+                    return;
+                }
+            }
+        };
+
+        // The receiver is gone if the iterator was dropped.
+        let _ = sender.send((index, cctx.compress(&data)));
+    }
+}
+
+#[pyclass(module = "zstandard.backend_rust")]
+pub struct ZstdMultiCompressorIterator {
+    /// Iterator of items to compress. `None` once exhausted.
+    source: Option<PyObject>,
+    /// Dictionary referenced by `cctxs`. Held to keep it alive.
+    _dict: Option<Py<ZstdCompressionDict>>,
+    /// Items waiting to be compressed and contexts to compress them with.
+    ///
+    /// There is a context for each thread allowed to run concurrently.
+    queue: Arc<Mutex<IteratorQueue>>,
+    sender: mpsc::Sender<ItemResult>,
+    receiver: Mutex<mpsc::Receiver<ItemResult>>,
+    /// Maximum number of items read from the source but not yet emitted.
+    max_in_flight: usize,
+    /// Whether to emit results in source order.
+    ordered: bool,
+    /// Number of items read from the source.
+    submitted: usize,
+    /// Number of items emitted.
+    emitted: usize,
+    /// Completed results waiting for earlier items to complete.
+    completed: BTreeMap<usize, Result<Vec<u8>, ZstdFailure>>,
+}
+
+impl ZstdMultiCompressorIterator {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        py: Python,
+        params: &CCtxParams,
+        dict: &Option<Py<ZstdCompressionDict>>,
+        accounting: &Option<Arc<MemoryAccounting>>,
+        data: &PyAny,
+        threads: ThreadPolicy,
+        max_in_flight: Option<usize>,
+        ordered: bool,
+    ) -> PyResult<Self> {
+        // Items are read lazily, so their number and size aren't known.
+        let threads = threads.thread_count(usize::MAX, usize::MAX);
+
+        let max_in_flight = max_in_flight.unwrap_or(threads * ITEMS_PER_THREAD);
+        if max_in_flight == 0 {
+            return Err(PyValueError::new_err("max_in_flight must be positive"));
+        }
+
+        let source = PyIterator::from_object(py, data)?;
+
+        let queue = IteratorQueue {
+            items: VecDeque::new(),
+            idle: create_cctxs(py, params, dict, accounting, threads)?,
+        };
+
+        let (sender, receiver) = mpsc::channel();
+
+        Ok(Self {
+            source: Some(source.into_py(py)),
+            _dict: dict.as_ref().map(|dict| dict.clone_ref(py)),
+            queue: Arc::new(Mutex::new(queue)),
+            sender,
+            receiver: Mutex::new(receiver),
+            max_in_flight,
+            ordered,
+            submitted: 0,
+            emitted: 0,
+            completed: BTreeMap::new(),
+        })
+    }
+
+    /// Read items from the source and schedule their compression until the
+    /// in flight limit is reached or the source is exhausted.
+    fn fill(&mut self, py: Python) -> PyResult<()> {
+        while self.submitted - self.emitted < self.max_in_flight {
+            let item = match &self.source {
+                Some(source) => PyIterator::from_object(py, source.as_ref(py))?.next(),
+                None => return Ok(()),
+            };
+
+            let item = match item {
+                Some(item) => item?,
+                None => {
+                    self.source = None;
+                    return Ok(());
+                }
+            };
+
+            let index = self.submitted;
+
+            let buffer: PyBuffer<u8> = PyBuffer::get(item).map_err(|_| {
+                PyTypeError::new_err(format!("item {} not a bytes like object", index))
+            })?;
+
+            // Copy so the data can be moved to a worker thread.
+            let data = buffer.to_vec(py)?;
+
+            // Start a worker if a context is free. Otherwise a running worker
+            // picks up the item.
+            let idle = {
+                let mut queue = self.queue.lock().unwrap();
+                queue.items.push_back((index, data));
+                queue.idle.pop()
+            };
+
+            if let Some(cctx) = idle {
+                let queue = self.queue.clone();
+                let sender = self.sender.clone();
+
+                rayon::spawn(move || compress_queued(queue, cctx, sender));
+            }
+
+            self.submitted += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Obtain the next result to emit, waiting for compression if necessary.
+    fn next_result(&mut self, py: Python) -> Option<ItemResult> {
+        if self.emitted == self.submitted {
+            return None;
+        }
+
+        loop {
+            if self.ordered {
+                if let Some(result) = self.completed.remove(&self.emitted) {
+                    return Some((self.emitted, result));
+                }
+            }
+
+            let receiver = &self.receiver;
+
+            // Every submitted item sends a result and we hold a sender, so
+            // this can't fail.
+            let (index, result) = py.allow_threads(|| receiver.lock().unwrap().recv().unwrap());
+
+            if !self.ordered {
+                return Some((index, result));
+            }
+
+            self.completed.insert(index, result);
+        }
+    }
+}
+
+impl Drop for ZstdMultiCompressorIterator {
+    fn drop(&mut self) {
+        // Items not yet picked up by a worker are abandoned.
+        let abandoned = match self.queue.lock() {
+            Ok(mut queue) => queue.items.drain(..).count(),
+            Err(_) => 0,
+        };
+
+        // Wait for items being compressed since their contexts reference the
+        // dictionary. Other threads can run while we wait.
+        let outstanding = self.submitted - self.emitted - self.completed.len() - abandoned;
+        let receiver = &self.receiver;
+
+        Python::with_gil(|py| {
+            py.allow_threads(|| {
+                if let Ok(receiver) = receiver.lock() {
+                    for _ in 0..outstanding {
+                        let _ = receiver.recv();
+                    }
+                }
+            })
+        });
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for ZstdMultiCompressorIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(slf: &PyCell<Self>) -> PyResult<Option<PyObject>> {
+        let py = slf.py();
+        let mut slf = slf.try_borrow_mut()?;
+
+        slf.fill(py)?;
+
+        let (index, result) = match slf.next_result(py) {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+
+        slf.emitted += 1;
+
+        let data = result
+            .map_err(|msg| zstd_error(msg, format!("error compressing item {}: {}", index, msg)))?;
+
+        // TODO avoid buffer copy.
+        let chunk = PyBytes::new(py, &data);
+
+        if slf.ordered {
+            Ok(Some(chunk.into_py(py)))
+        } else {
+            Ok(Some((index, chunk).into_py(py)))
+        }
+    }
+}
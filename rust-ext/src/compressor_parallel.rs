@@ -0,0 +1,168 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    crate::{
+        compression_dict::ZstdCompressionDict,
+        compression_parameters::CCtxParams,
+        compressor_multi::create_cctxs,
+        exceptions::zstd_error,
+        memory::MemoryAccounting,
+        threads::process_batches,
+        zstd_safe::{CCtx, ZstdFailure},
+    },
+    pyo3::{exceptions::PyValueError, prelude::*},
+    std::sync::Arc,
+};
+
+/// Number of frames to compress per thread before emitting output.
+///
+/// Bounds memory usage of buffered input and output.
+const FRAMES_PER_THREAD: usize = 4;
+
+/// Size of the skippable frame preceding each frame in the pzstd format.
+const PZSTD_HEADER_SIZE: usize = zstd_sys::ZSTD_SKIPPABLEHEADERSIZE as usize + 4;
+
+/// Compresses input into independent, fixed-size frames using multiple threads.
+///
+/// Output is in the format produced by `pzstd`: every frame is preceded by a
+/// skippable frame holding the compressed size of the frame that follows.
+/// Since frames don't depend on each other, output can be decompressed in
+/// parallel.
+pub struct FrameCompressor {
+    /// Dictionary referenced by `cctxs`. Held to keep it alive.
+    _dict: Option<Py<ZstdCompressionDict>>,
+    cctxs: Vec<CCtx<'static>>,
+    frame_size: usize,
+    /// Input not yet compressed.
+    pending: Vec<u8>,
+    /// Whether any frames have been emitted.
+    started: bool,
+    /// Number of frames emitted.
+    frame_count: usize,
+}
+
+impl FrameCompressor {
+    pub fn new(
+        py: Python,
+        params: &CCtxParams,
+        dict: &Option<Py<ZstdCompressionDict>>,
+        accounting: &Option<Arc<MemoryAccounting>>,
+        frame_size: usize,
+        thread_count: usize,
+    ) -> PyResult<Self> {
+        if frame_size == 0 {
+            return Err(PyValueError::new_err("frame_size must be positive"));
+        }
+
+        // The size header can only describe frames smaller than 4 GiB.
+        if unsafe { zstd_sys::ZSTD_compressBound(frame_size) } > u32::MAX as usize {
+            return Err(PyValueError::new_err("frame_size is too large"));
+        }
+
+        let thread_count = std::cmp::max(1, thread_count);
+
+        let cctxs = create_cctxs(py, params, dict, accounting, thread_count)?;
+
+        Ok(Self {
+            _dict: dict.as_ref().map(|dict| dict.clone_ref(py)),
+            cctxs,
+            frame_size,
+            pending: vec![],
+            started: false,
+            frame_count: 0,
+        })
+    }
+
+    /// Amount of input to compress at once.
+    fn batch_size(&self) -> usize {
+        self.frame_size * self.cctxs.len() * FRAMES_PER_THREAD
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.cctxs
+            .iter()
+            .map(|cctx| cctx.memory_size())
+            .sum::<usize>()
+            + self.pending.capacity()
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Feed input into the compressor.
+    ///
+    /// Input is buffered until enough is available to keep all threads busy.
+    /// Returns compressed data, which may be empty.
+    pub fn compress(&mut self, py: Python, data: &[u8]) -> PyResult<Vec<u8>> {
+        self.pending.extend_from_slice(data);
+
+        let batch_size = self.batch_size();
+
+        if self.pending.len() < batch_size {
+            return Ok(vec![]);
+        }
+
+        let count = self.pending.len() - self.pending.len() % batch_size;
+
+        self.compress_pending(py, count)
+    }
+
+    /// Compress all buffered input.
+    ///
+    /// The final frame may be smaller than the frame size.
+    pub fn flush(&mut self, py: Python) -> PyResult<Vec<u8>> {
+        // Always emit a frame so empty input produces valid output.
+        if self.pending.is_empty() && self.started {
+            return Ok(vec![]);
+        }
+
+        self.compress_pending(py, self.pending.len())
+    }
+
+    /// Compress the first `count` bytes of pending input.
+    fn compress_pending(&mut self, py: Python, count: usize) -> PyResult<Vec<u8>> {
+        let input = &self.pending[0..count];
+
+        let chunks = if input.is_empty() {
+            vec![input]
+        } else {
+            input.chunks(self.frame_size).collect::<Vec<_>>()
+        };
+
+        // Frames are similarly sized, so each is its own batch.
+        let batches = (0..chunks.len()).map(|i| i..i + 1).collect::<Vec<_>>();
+        let cctxs = &self.cctxs;
+
+        let results: Vec<Result<Vec<u8>, ZstdFailure>> = py.allow_threads(|| {
+            process_batches(cctxs, &batches, |cctx, batch| {
+                cctx.compress(chunks[batch.start])
+            })
+        });
+
+        let mut output = vec![];
+        let frame_count = results.len();
+
+        for result in results {
+            let frame = result
+                .map_err(|msg| zstd_error(msg, format!("error compressing frame: {}", msg)))?;
+
+            output.reserve(PZSTD_HEADER_SIZE + frame.len());
+
+            output.extend_from_slice(&zstd_sys::ZSTD_MAGIC_SKIPPABLE_START.to_le_bytes());
+            output.extend_from_slice(&4u32.to_le_bytes());
+            output.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            output.extend_from_slice(&frame);
+        }
+
+        self.pending.drain(0..count);
+        self.started = true;
+        self.frame_count += frame_count;
+
+        Ok(output)
+    }
+}
@@ -6,7 +6,8 @@
 
 use {
     crate::{
-        exceptions::ZstdError,
+        exceptions::{zstd_error, ZstdError},
+        frames::{walk_frames, FileFrameSource, SliceFrameSource},
         stream::{make_in_buffer_source, InBufferSource},
         zstd_safe::DCtx,
     },
@@ -15,7 +16,7 @@ use {
         exceptions::{PyOSError, PyValueError},
         prelude::*,
         types::{PyBytes, PyList},
-        PyIterProtocol,
+        PyIterProtocol, PyNativeType,
     },
     std::{cmp::min, sync::Arc},
 };
@@ -30,6 +31,19 @@ pub struct ZstdDecompressionReader {
     closed: bool,
     bytes_decompressed: usize,
     finished_output: bool,
+    /// Decompressed data not yet returned to the caller.
+    ///
+    /// Populated by line-oriented reads, which need to look ahead for
+    /// newlines. Data before `line_offset` has already been consumed.
+    line_buffer: Vec<u8>,
+    line_offset: usize,
+    /// Total bytes emitted by the decompressor, including buffered data.
+    bytes_produced: usize,
+    /// `(decompressed offset, compressed offset)` of frame boundaries seen so
+    /// far. Only recorded if enabled.
+    checkpoints: Option<Vec<(usize, usize)>>,
+    /// Total decompressed size of the source, once known.
+    decompressed_size: Option<usize>,
 }
 
 impl ZstdDecompressionReader {
@@ -40,6 +54,7 @@ impl ZstdDecompressionReader {
         read_size: usize,
         read_across_frames: bool,
         closefd: bool,
+        seek_checkpoints: bool,
     ) -> PyResult<Self> {
         Ok(Self {
             dctx,
@@ -50,6 +65,11 @@ impl ZstdDecompressionReader {
             closed: false,
             bytes_decompressed: 0,
             finished_output: false,
+            line_buffer: vec![],
+            line_offset: 0,
+            bytes_produced: 0,
+            checkpoints: if seek_checkpoints { Some(vec![]) } else { None },
+            decompressed_size: None,
         })
     }
 }
@@ -70,22 +90,245 @@ impl ZstdDecompressionReader {
                 });
 
         let old_pos = in_buffer.pos;
+        let old_out_pos = out_buffer.pos;
 
         let zresult = self
             .dctx
             .decompress_buffers(out_buffer, &mut in_buffer)
-            .map_err(|msg| ZstdError::new_err(format!("zstd decompress error: {}", msg)))?;
+            .map_err(|msg| zstd_error(msg, format!("zstd decompress error: {}", msg)))?;
 
         if in_buffer.pos - old_pos > 0 {
             self.source.record_bytes_read(in_buffer.pos - old_pos);
         }
 
+        self.bytes_produced += out_buffer.pos - old_out_pos;
+
+        // A frame was fully decoded and flushed. Decompression can restart here.
+        if zresult == 0 {
+            let compressed_offset = self.source.tell();
+
+            if let Some(checkpoints) = &mut self.checkpoints {
+                if checkpoints
+                    .last()
+                    .map_or(compressed_offset > 0, |(_, last)| compressed_offset > *last)
+                {
+                    checkpoints.push((self.bytes_produced, compressed_offset));
+                }
+            }
+        }
+
         // Emit data if there is data AND either:
         // a) output buffer is full (read amount is satisfied)
         // b) we're at the end of a frame and not in frame spanning mode
         return Ok(out_buffer.pos != 0
             && (out_buffer.pos == out_buffer.size || zresult == 0 && !self.read_across_frames));
     }
+
+    /// Rewind so the next read starts at decompressed offset `pos`.
+    ///
+    /// The source is repositioned to the nearest recorded frame boundary at
+    /// or before `pos` (or its start) and decompression resumes from there.
+    fn restart(&mut self, py: Python, pos: usize) -> PyResult<()> {
+        let (decompressed_offset, compressed_offset) = self
+            .checkpoints
+            .as_ref()
+            .and_then(|checkpoints| {
+                checkpoints
+                    .iter()
+                    .rev()
+                    .find(|(decompressed, _)| *decompressed <= pos)
+                    .cloned()
+            })
+            .unwrap_or((0, 0));
+
+        self.source.seek_to(py, compressed_offset)?;
+        self.dctx.reset().map_err(|msg| {
+            ZstdError::new_err(format!("unable to reset decompression context: {}", msg))
+        })?;
+
+        self.line_buffer.clear();
+        self.line_offset = 0;
+        self.bytes_decompressed = decompressed_offset;
+        self.bytes_produced = decompressed_offset;
+
+        Ok(())
+    }
+
+    /// Sum the content sizes declared in the headers of all source frames.
+    ///
+    /// Returns `None` if any frame doesn't declare its content size.
+    fn scan_decompressed_size(&self, py: Python) -> PyResult<Option<usize>> {
+        let source = self.source.source_object().as_ref(py);
+
+        let frames = if self.source.source_size().is_some() {
+            let buffer = PyBuffer::<u8>::get(source)?;
+            let data = unsafe {
+                std::slice::from_raw_parts(buffer.buf_ptr() as *const u8, buffer.len_bytes())
+            };
+
+            walk_frames(py, &mut SliceFrameSource(data))?
+        } else {
+            if !self.source.seekable(py)? {
+                return Err(PyOSError::new_err(
+                    "cannot determine decompressed size of non-seekable source",
+                ));
+            }
+
+            let start = self.source.start_position(py)?;
+            let position = source.call_method0("tell")?;
+
+            let frames = walk_frames(py, &mut FileFrameSource::new(source, start as _));
+
+            source.call_method1("seek", (position,))?;
+
+            frames?
+        };
+
+        Ok(frames
+            .iter()
+            .map(|frame| frame.content_size())
+            .sum::<Option<u64>>()
+            .map(|size| size as usize))
+    }
+
+    /// Determine the decompressed size by decompressing all remaining data.
+    ///
+    /// The read position is restored afterwards.
+    fn measure_decompressed_size(&mut self, py: Python) -> PyResult<usize> {
+        if !self.source.seekable(py)? {
+            return Err(PyOSError::new_err(
+                "cannot determine decompressed size of non-seekable source",
+            ));
+        }
+
+        let position = self.bytes_decompressed;
+
+        loop {
+            let chunk = self.read(py, Some(zstd_safe::dstream_out_size() as _))?;
+            if chunk.len()? == 0 {
+                break;
+            }
+        }
+
+        let size = self.bytes_decompressed;
+
+        self.seek(py, position as _, None)?;
+
+        Ok(size)
+    }
+
+    /// Copy buffered line data into an output buffer.
+    ///
+    /// Returns the number of bytes copied.
+    fn take_buffered(&mut self, out_buffer: &mut zstd_sys::ZSTD_outBuffer) -> usize {
+        let available = &self.line_buffer[self.line_offset..];
+        let count = min(available.len(), out_buffer.size - out_buffer.pos);
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                available.as_ptr(),
+                (out_buffer.dst as *mut u8).add(out_buffer.pos),
+                count,
+            );
+        }
+
+        out_buffer.pos += count;
+        self.line_offset += count;
+
+        if self.line_offset == self.line_buffer.len() {
+            self.line_buffer.clear();
+            self.line_offset = 0;
+        }
+
+        count
+    }
+
+    /// Decompress more data into the line buffer.
+    ///
+    /// Returns the number of new bytes. 0 is only returned once all input
+    /// has been consumed.
+    fn fill_line_buffer(&mut self, py: Python) -> PyResult<usize> {
+        // Discard consumed data so the buffer doesn't grow unbounded.
+        if self.line_offset > 0 {
+            self.line_buffer.drain(0..self.line_offset);
+            self.line_offset = 0;
+        }
+
+        self.line_buffer.reserve(zstd_safe::dstream_out_size());
+
+        let existing = self.line_buffer.len();
+
+        let mut out_buffer = zstd_sys::ZSTD_outBuffer {
+            dst: unsafe { self.line_buffer.as_mut_ptr().add(existing) } as *mut _,
+            size: self.line_buffer.capacity() - existing,
+            pos: 0,
+        };
+
+        let mut emit = self.decompress_into_buffer(py, &mut out_buffer)?;
+
+        while !emit && !self.source.finished() {
+            emit = self.decompress_into_buffer(py, &mut out_buffer)?;
+        }
+
+        unsafe {
+            self.line_buffer.set_len(existing + out_buffer.pos);
+        }
+
+        Ok(out_buffer.pos)
+    }
+
+    /// Obtain the next line of decompressed output.
+    ///
+    /// A negative `size` means no limit on the line length.
+    fn next_line<'p>(&mut self, py: Python<'p>, size: isize) -> PyResult<&'p PyBytes> {
+        if self.closed {
+            return Err(PyValueError::new_err("stream is closed"));
+        }
+
+        if size == 0 {
+            return Ok(PyBytes::new(py, &[]));
+        }
+
+        // Bytes after `line_offset` already searched for a newline.
+        let mut scanned = 0;
+
+        let line_len = loop {
+            let available = &self.line_buffer[self.line_offset..];
+
+            let limit = if size > 0 {
+                min(size as usize, available.len())
+            } else {
+                available.len()
+            };
+
+            if let Some(pos) = available[scanned..limit].iter().position(|c| *c == b'\n') {
+                break scanned + pos + 1;
+            }
+
+            if size > 0 && limit == size as usize {
+                break limit;
+            }
+
+            scanned = limit;
+
+            if self.fill_line_buffer(py)? == 0 {
+                break self.line_buffer.len() - self.line_offset;
+            }
+        };
+
+        let start = self.line_offset;
+        let line = PyBytes::new(py, &self.line_buffer[start..start + line_len]);
+
+        self.line_offset += line_len;
+        self.bytes_decompressed += line_len;
+
+        if self.line_offset == self.line_buffer.len() {
+            self.line_buffer.clear();
+            self.line_offset = 0;
+        }
+
+        Ok(line)
+    }
 }
 
 #[pymethods]
@@ -124,26 +367,54 @@ impl ZstdDecompressionReader {
         false
     }
 
-    fn seekable(&self) -> bool {
-        false
+    fn seekable(&self, py: Python) -> PyResult<bool> {
+        self.source.seekable(py)
     }
 
-    #[args(size = "None")]
-    #[allow(unused_variables)]
-    fn readline(&self, py: Python, size: Option<&PyAny>) -> PyResult<()> {
-        let io = py.import("io")?;
-        let exc = io.getattr("UnsupportedOperation")?;
+    #[args(full_scan = "false")]
+    fn decompressed_size(&mut self, py: Python, full_scan: bool) -> PyResult<Option<usize>> {
+        if self.closed {
+            return Err(PyValueError::new_err("stream is closed"));
+        }
 
-        Err(PyErr::from_instance(exc))
+        if self.decompressed_size.is_none() {
+            self.decompressed_size = match self.scan_decompressed_size(py)? {
+                Some(size) => Some(size),
+                None if full_scan => Some(self.measure_decompressed_size(py)?),
+                None => None,
+            };
+        }
+
+        Ok(self.decompressed_size)
     }
 
     #[args(size = "None")]
-    #[allow(unused_variables)]
-    fn readlines(&self, py: Python, hint: Option<&PyAny>) -> PyResult<()> {
-        let io = py.import("io")?;
-        let exc = io.getattr("UnsupportedOperation")?;
+    fn readline<'p>(&mut self, py: Python<'p>, size: Option<isize>) -> PyResult<&'p PyBytes> {
+        self.next_line(py, size.unwrap_or(-1))
+    }
 
-        Err(PyErr::from_instance(exc))
+    #[args(hint = "None")]
+    fn readlines<'p>(&mut self, py: Python<'p>, hint: Option<isize>) -> PyResult<&'p PyList> {
+        let hint = hint.unwrap_or(-1);
+
+        let lines = PyList::empty(py);
+        let mut total = 0;
+
+        loop {
+            let line = self.next_line(py, -1)?;
+            if line.as_bytes().is_empty() {
+                break;
+            }
+
+            total += line.as_bytes().len();
+            lines.append(line)?;
+
+            if hint > 0 && total >= hint as usize {
+                break;
+            }
+        }
+
+        Ok(lines)
     }
 
     #[allow(unused_variables)]
@@ -241,7 +512,10 @@ impl ZstdDecompressionReader {
             pos: 0,
         };
 
-        if self.decompress_into_buffer(py, &mut out_buffer)? {
+        // Data buffered by line-oriented reads is returned first.
+        self.take_buffered(&mut out_buffer);
+
+        if out_buffer.pos == out_buffer.size || self.decompress_into_buffer(py, &mut out_buffer)? {
             self.bytes_decompressed += out_buffer.pos;
             unsafe {
                 dest_buffer.set_len(out_buffer.pos);
@@ -294,7 +568,9 @@ impl ZstdDecompressionReader {
             pos: 0,
         };
 
-        if self.decompress_into_buffer(py, &mut out_buffer)? {
+        self.take_buffered(&mut out_buffer);
+
+        if out_buffer.pos == out_buffer.size || self.decompress_into_buffer(py, &mut out_buffer)? {
             self.bytes_decompressed += out_buffer.pos;
 
             return Ok(out_buffer.pos);
@@ -348,12 +624,10 @@ impl ZstdDecompressionReader {
         // stream to get input. However, we can't satisfy this restriction with
         // decompression because not all input generates output. So we allow
         // multiple read(). But unlike read(), we stop once we have any output.
-        while !self.source.finished() {
-            self.decompress_into_buffer(py, &mut out_buffer)?;
+        self.take_buffered(&mut out_buffer);
 
-            if out_buffer.pos > 0 {
-                break;
-            }
+        while out_buffer.pos == 0 && !self.source.finished() {
+            self.decompress_into_buffer(py, &mut out_buffer)?;
         }
 
         unsafe {
@@ -385,12 +659,10 @@ impl ZstdDecompressionReader {
             pos: 0,
         };
 
-        while !self.source.finished() && !self.finished_output {
-            self.decompress_into_buffer(py, &mut out_buffer)?;
+        self.take_buffered(&mut out_buffer);
 
-            if out_buffer.pos > 0 {
-                break;
-            }
+        while out_buffer.pos == 0 && !self.source.finished() && !self.finished_output {
+            self.decompress_into_buffer(py, &mut out_buffer)?;
         }
 
         self.bytes_decompressed += out_buffer.pos;
@@ -412,36 +684,53 @@ impl ZstdDecompressionReader {
 
         let whence = whence.unwrap_or(seek_set);
 
-        let mut read_amount = if whence == seek_set {
+        let target = if whence == seek_set {
             if pos < 0 {
                 return Err(PyOSError::new_err(
                     "cannot seek to negative position with SEEK_SET",
                 ));
             }
 
-            if pos < self.bytes_decompressed as isize {
+            pos as usize
+        } else if whence == seek_cur {
+            if pos < 0 && (-pos) as usize > self.bytes_decompressed {
                 return Err(PyOSError::new_err(
-                    "cannot seek zstd decompression stream backwards",
+                    "cannot seek to negative position with SEEK_CUR",
                 ));
             }
 
-            pos as usize - self.bytes_decompressed
-        } else if whence == seek_cur {
-            if pos < 0 {
+            (self.bytes_decompressed as isize + pos) as usize
+        } else if whence == seek_end {
+            let size = self.decompressed_size(py, false)?.ok_or_else(|| {
+                PyOSError::new_err(
+                    "zstd decompression streams cannot be seeked with SEEK_END \
+                     unless all frames declare their content size",
+                )
+            })?;
+
+            if pos < 0 && (-pos) as usize > size {
                 return Err(PyOSError::new_err(
-                    "cannot seek zstd decompression stream backwards",
+                    "cannot seek to negative position with SEEK_END",
                 ));
             }
 
-            pos as usize
-        } else if whence == seek_end {
-            return Err(PyOSError::new_err(
-                "zstd decompression streams cannot be seeked with SEEK_END",
-            ));
+            (size as isize + pos) as usize
         } else {
-            0
+            self.bytes_decompressed
         };
 
+        if target < self.bytes_decompressed {
+            if !self.source.seekable(py)? {
+                return Err(PyOSError::new_err(
+                    "cannot seek zstd decompression stream backwards",
+                ));
+            }
+
+            self.restart(py, target)?;
+        }
+
+        let mut read_amount = target - self.bytes_decompressed;
+
         while read_amount > 0 {
             let result = self.read(
                 py,
@@ -461,19 +750,22 @@ impl ZstdDecompressionReader {
 
 #[pyproto]
 impl PyIterProtocol for ZstdDecompressionReader {
-    fn __iter__(slf: PyRef<Self>) -> PyResult<()> {
-        let py = slf.py();
-        let io = py.import("io")?;
-        let exc = io.getattr("UnsupportedOperation")?;
+    fn __iter__(slf: PyRef<Self>) -> PyResult<PyRef<Self>> {
+        if slf.closed {
+            return Err(PyValueError::new_err("stream is closed"));
+        }
 
-        Err(PyErr::from_instance(exc))
+        Ok(slf)
     }
 
-    fn __next__(slf: PyRef<Self>) -> PyResult<Option<()>> {
+    fn __next__(slf: &PyCell<Self>) -> PyResult<Option<PyObject>> {
         let py = slf.py();
-        let io = py.import("io")?;
-        let exc = io.getattr("UnsupportedOperation")?;
+        let line = slf.try_borrow_mut()?.next_line(py, -1)?;
 
-        Err(PyErr::from_instance(exc))
+        if line.as_bytes().is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(line.into_py(py)))
+        }
     }
 }
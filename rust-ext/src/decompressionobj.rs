@@ -5,7 +5,10 @@
 // of the BSD license. See the LICENSE file for details.
 
 use {
-    crate::{exceptions::ZstdError, zstd_safe::DCtx},
+    crate::{
+        exceptions::{zstd_error, ZstdError},
+        zstd_safe::DCtx,
+    },
     pyo3::{
         buffer::PyBuffer,
         prelude::*,
@@ -58,7 +61,7 @@ impl ZstdDecompressionObj {
             let zresult = self
                 .dctx
                 .decompress_into_vec(&mut dest_buffer, &mut in_buffer)
-                .map_err(|msg| ZstdError::new_err(format!("zstd decompress error: {}", msg)))?;
+                .map_err(|msg| zstd_error(msg, format!("zstd decompress error: {}", msg)))?;
 
             if zresult == 0 {
                 self.finished = true;
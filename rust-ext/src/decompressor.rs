@@ -6,11 +6,18 @@
 
 use {
     crate::{
-        buffers::ZstdBufferWithSegmentsCollection, compression_dict::ZstdCompressionDict,
+        compression_dict::ZstdCompressionDict,
         decompression_reader::ZstdDecompressionReader,
-        decompression_writer::ZstdDecompressionWriter, decompressionobj::ZstdDecompressionObj,
+        decompression_writer::ZstdDecompressionWriter,
+        decompressionobj::ZstdDecompressionObj,
         decompressor_iterator::ZstdDecompressorIterator,
-        decompressor_multi::multi_decompress_to_buffer, exceptions::ZstdError, zstd_safe::DCtx,
+        decompressor_multi::multi_decompress_to_buffer,
+        decompressor_parallel::{copy_stream_parallel, decompress_parallel},
+        exceptions::{zstd_error, ZstdError},
+        memory::{resolve_accounting, MemoryTracker},
+        progress::ProgressReporter,
+        threads::ThreadPolicy,
+        zstd_safe::DCtx,
     },
     pyo3::{
         buffer::PyBuffer,
@@ -27,6 +34,8 @@ struct ZstdDecompressor {
     dict_data: Option<Py<ZstdCompressionDict>>,
     max_window_size: usize,
     format: zstd_sys::ZSTD_format_e,
+    allow_legacy: bool,
+    max_output_size: Option<u64>,
     dctx: Arc<DCtx<'static>>,
 }
 
@@ -48,24 +57,66 @@ impl ZstdDecompressor {
             .set_format(self.format)
             .map_err(|msg| ZstdError::new_err(format!("unable to set decoding format: {}", msg)))?;
 
+        self.dctx.set_reject_legacy(!self.allow_legacy);
+        self.dctx.set_output_limit(self.max_output_size);
+
         if let Some(dict_data) = &self.dict_data {
             if load_dict {
-                dict_data.try_borrow_mut(py)?.load_into_dctx(&self.dctx)?;
+                dict_data
+                    .try_borrow_mut(py)?
+                    .load_into_dctx(py, &self.dctx)?;
             }
         }
 
         Ok(())
     }
+
+    /// Ensure a feature creating additional contexts can be used.
+    fn ensure_dynamic_context(&self, feature: &str) -> PyResult<()> {
+        if self.dctx.is_static() {
+            Err(PyValueError::new_err(format!(
+                "{} cannot be used with a static context",
+                feature
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Ensure frames can be located by parsing their headers.
+    fn ensure_frame_walkable(&self) -> PyResult<()> {
+        if self.format != zstd_sys::ZSTD_format_e::ZSTD_f_zstd1 {
+            Err(PyValueError::new_err(
+                "parallel decompression requires the FORMAT_ZSTD1 format",
+            ))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 #[pymethods]
 impl ZstdDecompressor {
     #[new]
-    #[args(dict_data = "None", max_window_size = "0", format = "0")]
+    #[args(
+        dict_data = "None",
+        max_window_size = "0",
+        format = "0",
+        allow_legacy = "true",
+        memory_tracker = "None",
+        static_context_size = "None",
+        max_output_size = "None"
+    )]
+    #[allow(clippy::too_many_arguments)]
     fn new(
+        py: Python,
         dict_data: Option<Py<ZstdCompressionDict>>,
         max_window_size: usize,
         format: u32,
+        allow_legacy: bool,
+        memory_tracker: Option<Py<MemoryTracker>>,
+        static_context_size: Option<usize>,
+        max_output_size: Option<u64>,
     ) -> PyResult<Self> {
         let format = if format == zstd_sys::ZSTD_format_e::ZSTD_f_zstd1 as _ {
             zstd_sys::ZSTD_format_e::ZSTD_f_zstd1
@@ -75,17 +126,40 @@ impl ZstdDecompressor {
             return Err(PyValueError::new_err(format!("invalid format value")));
         };
 
-        let dctx = Arc::new(DCtx::new().map_err(|_| PyMemoryError::new_err(()))?);
+        let dctx = if let Some(size) = static_context_size {
+            if memory_tracker.is_some() {
+                return Err(PyValueError::new_err(
+                    "cannot define static_context_size and memory_tracker",
+                ));
+            }
+
+            DCtx::new_static(size).map_err(PyValueError::new_err)?
+        } else {
+            DCtx::new(resolve_accounting(py, &memory_tracker))
+                .map_err(|_| PyMemoryError::new_err(()))?
+        };
+        let dctx = Arc::new(dctx);
 
         Ok(Self {
             dict_data,
             max_window_size,
             format,
+            allow_legacy,
+            max_output_size,
             dctx,
         })
     }
 
-    #[args(ifh, ofh, read_size = "None", write_size = "None")]
+    #[args(
+        ifh,
+        ofh,
+        read_size = "None",
+        write_size = "None",
+        threads = "0",
+        progress = "None",
+        progress_interval = "None"
+    )]
+    #[allow(clippy::too_many_arguments)]
     fn copy_stream(
         &self,
         py: Python,
@@ -93,6 +167,9 @@ impl ZstdDecompressor {
         ofh: &PyAny,
         read_size: Option<usize>,
         write_size: Option<usize>,
+        threads: isize,
+        progress: Option<PyObject>,
+        progress_interval: Option<usize>,
     ) -> PyResult<(usize, usize)> {
         let read_size = read_size.unwrap_or_else(|| zstd_safe::dstream_in_size());
         let write_size = write_size.unwrap_or_else(|| zstd_safe::dstream_out_size());
@@ -109,8 +186,32 @@ impl ZstdDecompressor {
             ));
         }
 
+        let mut progress = ProgressReporter::new(py, progress, progress_interval)?;
+
         self.setup_dctx(py, true)?;
 
+        if threads != 0 {
+            self.ensure_dynamic_context("threads")?;
+            self.ensure_frame_walkable()?;
+
+            let threads = if threads < 0 {
+                num_cpus::get()
+            } else {
+                threads as _
+            };
+
+            return copy_stream_parallel(
+                py,
+                &self.dctx,
+                ifh,
+                ofh,
+                read_size,
+                write_size,
+                threads,
+                &mut progress,
+            );
+        }
+
         let mut dest_buffer: Vec<u8> = Vec::with_capacity(write_size);
 
         let mut in_buffer = zstd_sys::ZSTD_inBuffer {
@@ -142,7 +243,7 @@ impl ZstdDecompressor {
             while in_buffer.pos < in_buffer.size {
                 self.dctx
                     .decompress_into_vec(&mut dest_buffer, &mut in_buffer)
-                    .map_err(|msg| ZstdError::new_err(format!("zstd decompress error: {}", msg)))?;
+                    .map_err(|msg| zstd_error(msg, format!("zstd decompress error: {}", msg)))?;
 
                 if !dest_buffer.is_empty() {
                     // TODO avoid buffer copy.
@@ -153,9 +254,13 @@ impl ZstdDecompressor {
                     dest_buffer.clear();
                 }
             }
+
+            progress.update(py, total_read, total_read, total_write)?;
             // Continue loop to keep reading.
         }
 
+        progress.finish(py, total_read, total_read, total_write)?;
+
         Ok((total_read, total_write))
     }
 
@@ -185,8 +290,18 @@ impl ZstdDecompressor {
                     ));
                 }
 
-                (max_output_size, 0)
+                // Room for one byte past the limit lets exceeding it be detected.
+                let buffer_size = match self.dctx.output_limit() {
+                    Some(limit) => std::cmp::min(max_output_size as u64, limit.saturating_add(1)),
+                    None => max_output_size as u64,
+                };
+
+                (buffer_size as _, 0)
             } else {
+                self.dctx
+                    .check_output_size(output_size)
+                    .map_err(|msg| zstd_error(msg, format!("decompression error: {}", msg)))?;
+
                 (output_size as _, output_size)
             };
 
@@ -204,7 +319,7 @@ impl ZstdDecompressor {
         let zresult = self
             .dctx
             .decompress_into_vec(&mut dest_buffer, &mut in_buffer)
-            .map_err(|msg| ZstdError::new_err(format!("decompression error: {}", msg)))?;
+            .map_err(|msg| zstd_error(msg, format!("decompression error: {}", msg)))?;
 
         if zresult != 0 {
             Err(ZstdError::new_err(
@@ -221,6 +336,31 @@ impl ZstdDecompressor {
         }
     }
 
+    #[args(data, threads = "-1")]
+    fn decompress_parallel<'p>(
+        &self,
+        py: Python<'p>,
+        data: PyBuffer<u8>,
+        threads: isize,
+    ) -> PyResult<&'p PyBytes> {
+        self.ensure_dynamic_context("decompress_parallel()")?;
+        self.ensure_frame_walkable()?;
+        self.setup_dctx(py, true)?;
+
+        let threads = if threads < 0 {
+            num_cpus::get()
+        } else if threads < 2 {
+            1
+        } else {
+            threads as _
+        };
+
+        let source =
+            unsafe { std::slice::from_raw_parts(data.buf_ptr() as *const u8, data.len_bytes()) };
+
+        decompress_parallel(py, &self.dctx, source, threads)
+    }
+
     fn decompress_content_dict_chain<'p>(
         &self,
         py: Python<'p>,
@@ -270,6 +410,10 @@ impl ZstdDecompressor {
 
         self.setup_dctx(py, false)?;
 
+        self.dctx
+            .check_output_size(params.frameContentSize)
+            .map_err(|msg| zstd_error(msg, format!("could not decompress chunk 0: {}", msg)))?;
+
         let mut last_buffer: Vec<u8> = Vec::with_capacity(params.frameContentSize as _);
 
         let mut in_buffer = zstd_sys::ZSTD_inBuffer {
@@ -281,7 +425,7 @@ impl ZstdDecompressor {
         let zresult = self
             .dctx
             .decompress_into_vec(&mut last_buffer, &mut in_buffer)
-            .map_err(|msg| ZstdError::new_err(format!("could not decompress chunk 0: {}", msg)))?;
+            .map_err(|msg| zstd_error(msg, format!("could not decompress chunk 0: {}", msg)))?;
 
         if zresult != 0 {
             return Err(ZstdError::new_err("chunk 0 did not decompress full frame"));
@@ -327,6 +471,14 @@ impl ZstdDecompressor {
                 )));
             }
 
+            // The limit applies to each chunk, as only the last one is retained.
+            self.dctx
+                .check_output_size(params.frameContentSize)
+                .map_err(|msg| {
+                    zstd_error(msg, format!("could not decompress chunk {}: {}", i, msg))
+                })?;
+            self.dctx.reset_output_size();
+
             let mut dest_buffer: Vec<u8> = Vec::with_capacity(params.frameContentSize as _);
 
             let mut in_buffer = zstd_sys::ZSTD_inBuffer {
@@ -339,7 +491,7 @@ impl ZstdDecompressor {
                 .dctx
                 .decompress_into_vec(&mut dest_buffer, &mut in_buffer)
                 .map_err(|msg| {
-                    ZstdError::new_err(format!("could not decompress chunk {}: {}", i, msg))
+                    zstd_error(msg, format!("could not decompress chunk {}: {}", i, msg))
                 })?;
 
             if zresult != 0 {
@@ -379,18 +531,25 @@ impl ZstdDecompressor {
         self.dctx.memory_size()
     }
 
-    #[args(frames, decompressed_sizes = "None", threads = "0")]
+    #[args(
+        frames,
+        decompressed_sizes = "None",
+        threads = "ThreadPolicy::Count(1)",
+        errors = "\"strict\""
+    )]
     #[allow(unused_variables)]
     fn multi_decompress_to_buffer(
         &self,
         py: Python,
         frames: &PyAny,
         decompressed_sizes: Option<&PyAny>,
-        threads: isize,
-    ) -> PyResult<ZstdBufferWithSegmentsCollection> {
+        threads: ThreadPolicy,
+        errors: &str,
+    ) -> PyResult<PyObject> {
+        self.ensure_dynamic_context("multi_decompress_to_buffer()")?;
         self.setup_dctx(py, true)?;
 
-        multi_decompress_to_buffer(py, &self.dctx, frames, decompressed_sizes, threads)
+        multi_decompress_to_buffer(py, &self.dctx, frames, decompressed_sizes, threads, errors)
     }
 
     #[args(reader, read_size = "None", write_size = "None", skip_bytes = "None")]
@@ -434,7 +593,8 @@ impl ZstdDecompressor {
         source,
         read_size = "None",
         read_across_frames = "false",
-        closefd = "true"
+        closefd = "true",
+        seek_checkpoints = "false"
     )]
     fn stream_reader(
         &self,
@@ -443,6 +603,7 @@ impl ZstdDecompressor {
         read_size: Option<usize>,
         read_across_frames: bool,
         closefd: bool,
+        seek_checkpoints: bool,
     ) -> PyResult<ZstdDecompressionReader> {
         let read_size = read_size.unwrap_or_else(|| zstd_safe::dstream_in_size());
 
@@ -455,6 +616,7 @@ impl ZstdDecompressor {
             read_size,
             read_across_frames,
             closefd,
+            seek_checkpoints,
         )
     }
 
@@ -6,7 +6,7 @@
 
 use {
     crate::{
-        exceptions::ZstdError,
+        exceptions::zstd_error,
         stream::{make_in_buffer_source, InBufferSource},
         zstd_safe::DCtx,
     },
@@ -75,7 +75,7 @@ impl PyIterProtocol for ZstdDecompressorIterator {
             let zresult = slf
                 .dctx
                 .decompress_into_vec(&mut dest_buffer, &mut in_buffer)
-                .map_err(|msg| ZstdError::new_err(format!("zstd decompress error: {}", msg)))?;
+                .map_err(|msg| zstd_error(msg, format!("zstd decompress error: {}", msg)))?;
 
             slf.source.record_bytes_read(in_buffer.pos - old_pos);
 
@@ -7,17 +7,17 @@
 use {
     crate::{
         buffers::{BufferSegment, ZstdBufferWithSegments, ZstdBufferWithSegmentsCollection},
-        exceptions::ZstdError,
-        zstd_safe::DCtx,
+        exceptions::zstd_error,
+        threads::{process_batches, split_batches, ThreadPolicy},
+        zstd_safe::{DCtx, ZstdFailure},
     },
     pyo3::{
         buffer::PyBuffer,
-        exceptions::{PyTypeError, PyValueError},
+        exceptions::{PyMemoryError, PyTypeError, PyValueError},
         prelude::*,
         types::{PyBytes, PyList, PyTuple},
-        PySequenceProtocol,
+        IntoPy, PyMappingProtocol,
     },
-    rayon::prelude::*,
 };
 
 struct DataSource<'a> {
@@ -30,14 +30,17 @@ pub fn multi_decompress_to_buffer(
     dctx: &DCtx,
     frames: &PyAny,
     decompressed_sizes: Option<&PyAny>,
-    threads: isize,
-) -> PyResult<ZstdBufferWithSegmentsCollection> {
-    let threads = if threads < 0 {
-        num_cpus::get()
-    } else if threads < 2 {
-        1
-    } else {
-        threads as _
+    threads: ThreadPolicy,
+    errors: &str,
+) -> PyResult<PyObject> {
+    let collect_errors = match errors {
+        "strict" => false,
+        "collect" => true,
+        _ => {
+            return Err(PyValueError::new_err(
+                "errors must be one of 'strict' or 'collect'",
+            ))
+        }
     };
 
     let frame_sizes: &[u64] = if let Some(frames_sizes) = decompressed_sizes {
@@ -129,14 +132,29 @@ pub fn multi_decompress_to_buffer(
         ));
     }
 
-    decompress_from_datasources(py, dctx, sources, threads)
+    let total_size = sources.iter().map(|source| source.data.len()).sum();
+    let threads = threads.thread_count(sources.len(), total_size);
+
+    let (collection, errors) =
+        decompress_from_datasources(py, dctx, sources, threads, collect_errors)?;
+
+    if collect_errors {
+        let errors = errors
+            .into_iter()
+            .map(|(index, err)| (index, err.into_py(py)))
+            .collect::<Vec<_>>();
+
+        Ok((collection, PyList::new(py, errors)).into_py(py))
+    } else {
+        Ok(collection.into_py(py))
+    }
 }
 
 #[derive(Debug, PartialEq)]
 enum WorkerError {
     None,
     NoSize,
-    Zstd(&'static str),
+    Zstd(ZstdFailure),
 }
 
 /// Holds results of an individual compression operation.
@@ -146,132 +164,154 @@ struct WorkerResult {
     data: Option<Vec<u8>>,
 }
 
+/// Decompress a single source.
+fn decompress_source(dctx: &DCtx, index: usize, source: &DataSource) -> WorkerResult {
+    let mut result = WorkerResult {
+        source_offset: index,
+        error: WorkerError::None,
+        data: None,
+    };
+
+    let decompressed_size = if source.decompressed_size == 0 {
+        let frame_size = zstd_safe::get_frame_content_size(source.data);
+
+        if frame_size == zstd_safe::CONTENTSIZE_ERROR
+            || frame_size == zstd_safe::CONTENTSIZE_UNKNOWN
+        {
+            result.error = WorkerError::NoSize;
+        }
+
+        frame_size as _
+    } else {
+        source.decompressed_size
+    };
+
+    if result.error == WorkerError::None {
+        // Contexts are reused across items. A failed item must not leave
+        // state behind for the next one.
+        if let Err(msg) = dctx.reset() {
+            result.error = WorkerError::Zstd(msg);
+            return result;
+        }
+
+        // The output limit applies to each item. Items decompress into
+        // buffers of their declared size, so reject large declarations before
+        // allocating.
+        if let Err(msg) = dctx.check_output_size(decompressed_size as u64) {
+            result.error = WorkerError::Zstd(msg);
+            return result;
+        }
+
+        let mut dest_buffer = Vec::with_capacity(decompressed_size);
+        let mut in_buffer = zstd_sys::ZSTD_inBuffer {
+            src: source.data.as_ptr() as *const _,
+            size: source.data.len(),
+            pos: 0,
+        };
+
+        match dctx.decompress_into_vec(&mut dest_buffer, &mut in_buffer) {
+            Ok(zresult) if zresult != 0 || in_buffer.pos < in_buffer.size => {
+                result.error = WorkerError::Zstd("frame is incomplete".into());
+            }
+            Ok(_) => {
+                result.data = Some(dest_buffer);
+            }
+            Err(msg) => {
+                result.error = WorkerError::Zstd(msg);
+            }
+        }
+    }
+
+    result
+}
+
 fn decompress_from_datasources(
     py: Python,
     dctx: &DCtx,
     sources: Vec<DataSource>,
     thread_count: usize,
-) -> PyResult<ZstdBufferWithSegmentsCollection> {
-    // More threads than inputs makes no sense.
-    let thread_count = std::cmp::min(thread_count, sources.len());
-
-    // TODO lower thread count when input size is too small and threads
-    // would add overhead.
-
+    collect_errors: bool,
+) -> PyResult<(ZstdBufferWithSegmentsCollection, Vec<(usize, PyErr)>)> {
     let mut dctxs = Vec::with_capacity(thread_count);
-    let results = std::sync::Mutex::new(Vec::with_capacity(sources.len()));
 
     // TODO there are tons of inefficiencies in this implementation compared
     // to the C backend.
 
     for _ in 0..thread_count {
-        let dctx = dctx.try_clone().map_err(ZstdError::new_err)?;
+        let dctx = dctx.try_clone().map_err(PyMemoryError::new_err)?;
         dctxs.push(dctx);
     }
 
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(thread_count)
-        .build()
-        .map_err(|err| ZstdError::new_err(format!("error initializing thread pool: {}", err)))?;
-
-    pool.install(|| {
-        sources
-            .par_iter()
-            .enumerate()
-            .for_each(|(index, source): (usize, &DataSource)| {
-                let thread_index = pool.current_thread_index().unwrap();
-
-                let dctx = &dctxs[thread_index];
-
-                let mut result = WorkerResult {
-                    source_offset: index,
-                    error: WorkerError::None,
-                    data: None,
-                };
-
-                let decompressed_size = if source.decompressed_size == 0 {
-                    let frame_size = zstd_safe::get_frame_content_size(source.data);
-
-                    if frame_size == zstd_safe::CONTENTSIZE_ERROR
-                        || frame_size == zstd_safe::CONTENTSIZE_UNKNOWN
-                    {
-                        result.error = WorkerError::NoSize;
-                    }
-
-                    frame_size as _
-                } else {
-                    source.decompressed_size
-                };
-
-                if result.error == WorkerError::None {
-                    let mut dest_buffer = Vec::with_capacity(decompressed_size);
-                    let mut in_buffer = zstd_sys::ZSTD_inBuffer {
-                        src: source.data.as_ptr() as *const _,
-                        size: source.data.len(),
-                        pos: 0,
-                    };
-
-                    match dctx.decompress_into_vec(&mut dest_buffer, &mut in_buffer) {
-                        Ok(_) => {
-                            result.data = Some(dest_buffer);
-                        }
-                        Err(msg) => {
-                            result.error = WorkerError::Zstd(msg);
-                        }
-                    }
-                }
-
-                results.lock().unwrap().push(result);
-            });
+    // Each batch is a contiguous range of sources. Results are collected in
+    // order.
+    let sizes = sources
+        .iter()
+        .map(|source| source.data.len())
+        .collect::<Vec<_>>();
+    let batches = split_batches(&sizes, thread_count);
+
+    let results: Vec<Vec<WorkerResult>> = py.allow_threads(|| {
+        process_batches(&dctxs, &batches, |dctx, batch| {
+            sources[batch.clone()]
+                .iter()
+                .enumerate()
+                .map(|(i, source)| decompress_source(dctx, batch.start + i, source))
+                .collect()
+        })
     });
 
-    // Need to sort results by their input order or else results aren't
-    // deterministic.
-    results
-        .lock()
-        .unwrap()
-        .sort_by(|a, b| a.source_offset.cmp(&b.source_offset));
+    let mut buffers = Vec::with_capacity(sources.len());
+    let mut errors = vec![];
 
     // TODO this is horribly inefficient due to memory copies.
-    let els = PyTuple::new(
-        py,
-        results
-            .lock()
-            .unwrap()
-            .iter()
-            .map(|result| {
-                match result.error {
-                    WorkerError::None => Ok(()),
-                    WorkerError::Zstd(msg) => Err(ZstdError::new_err(format!(
-                        "error decompressing item {}: {}",
-                        result.source_offset, msg
-                    ))),
-                    WorkerError::NoSize => Err(PyValueError::new_err(format!(
-                        "could not determine decompressed size of item {}",
-                        result.source_offset
-                    ))),
-                }?;
-
-                let data = result.data.as_ref().unwrap();
-                let chunk = PyBytes::new(py, data);
-                let segments = vec![BufferSegment {
-                    offset: 0,
-                    length: data.len() as _,
-                }];
-
-                let segments = unsafe {
-                    PyBytes::from_ptr(
-                        py,
-                        segments.as_ptr() as *const _,
-                        segments.len() * std::mem::size_of::<BufferSegment>(),
-                    )
-                };
-                let segments_buffer = PyBuffer::get(segments)?;
-
-                Py::new(py, ZstdBufferWithSegments::new(py, chunk, segments_buffer)?)
-            })
-            .collect::<PyResult<Vec<_>>>()?,
-    );
-
-    ZstdBufferWithSegmentsCollection::new(py, els)
+    for result in results.iter().flatten() {
+        let error = match result.error {
+            WorkerError::None => None,
+            WorkerError::Zstd(msg) => Some(zstd_error(
+                msg,
+                format!("error decompressing item {}: {}", result.source_offset, msg),
+            )),
+            WorkerError::NoSize => Some(PyValueError::new_err(format!(
+                "could not determine decompressed size of item {}",
+                result.source_offset
+            ))),
+        };
+
+        // Failed items get an empty segment so indices line up with inputs.
+        let data: &[u8] = match error {
+            Some(err) if collect_errors => {
+                errors.push((result.source_offset, err));
+                &[]
+            }
+            Some(err) => return Err(err),
+            None => result.data.as_ref().unwrap(),
+        };
+
+        let chunk = PyBytes::new(py, data);
+        let segments = vec![BufferSegment {
+            offset: 0,
+            length: data.len() as _,
+        }];
+
+        let segments = unsafe {
+            PyBytes::from_ptr(
+                py,
+                segments.as_ptr() as *const _,
+                segments.len() * std::mem::size_of::<BufferSegment>(),
+            )
+        };
+
+        buffers.push(Py::new(
+            py,
+            ZstdBufferWithSegments::new(py, chunk, segments)?,
+        )?);
+    }
+
+    let collection = if collect_errors {
+        ZstdBufferWithSegmentsCollection::from_buffers(py, buffers)
+    } else {
+        ZstdBufferWithSegmentsCollection::new(py, PyTuple::new(py, buffers))?
+    };
+
+    Ok((collection, errors))
 }
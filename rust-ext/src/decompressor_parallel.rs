@@ -0,0 +1,376 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    crate::{
+        exceptions::{zstd_error, ZstdError},
+        frames::{walk_frames, FrameInfo, FrameScanner, SliceFrameSource},
+        progress::ProgressReporter,
+        threads::process_batches,
+        zstd_safe::{DCtx, ZstdFailure},
+    },
+    pyo3::{exceptions::PyMemoryError, prelude::*, types::PyBytes},
+};
+
+/// Number of frames to decompress per thread before writing output.
+///
+/// Bounds memory usage of `copy_stream_parallel()`.
+const FRAMES_PER_THREAD: usize = 4;
+
+/// Bytes of input per thread `copy_stream_parallel()` buffers at most.
+///
+/// Complete frames are decompressed once this much input is buffered, even
+/// if they don't fill a batch. Frames larger than this are decompressed
+/// serially as they are read.
+const PENDING_SIZE_PER_THREAD: usize = 4 * 1024 * 1024;
+
+/// A zstd frame to be decompressed.
+struct FrameInput<'a> {
+    /// Offset of the frame within the source, for error reporting.
+    offset: u64,
+    data: &'a [u8],
+    content_size: Option<u64>,
+}
+
+/// Obtain the decompressible frames within `data`.
+///
+/// Skippable frames are ignored since they don't produce output.
+fn frame_inputs<'a>(data: &'a [u8], frames: &[FrameInfo], base_offset: u64) -> Vec<FrameInput<'a>> {
+    frames
+        .iter()
+        .filter(|frame| !frame.is_skippable())
+        .map(|frame| FrameInput {
+            offset: base_offset + frame.offset,
+            data: &data[frame.offset as usize..(frame.offset + frame.compressed_size) as usize],
+            content_size: frame.content_size(),
+        })
+        .collect()
+}
+
+/// Sum the content sizes the inputs declare.
+fn declared_size(inputs: &[FrameInput]) -> u64 {
+    inputs
+        .iter()
+        .filter_map(|input| input.content_size)
+        .fold(0, u64::saturating_add)
+}
+
+/// Ensure `size` total bytes of output are allowed by the context's limit.
+///
+/// Each frame is limited by its context. This enforces the limit across frames.
+fn check_output_size(dctx: &DCtx, size: u64) -> PyResult<()> {
+    dctx.check_output_size(size)
+        .map_err(|msg| zstd_error(msg, format!("error decompressing frames: {}", msg)))
+}
+
+/// Decompress a single, complete frame.
+fn decompress_frame(dctx: &DCtx, input: &FrameInput) -> Result<Vec<u8>, ZstdFailure> {
+    dctx.reset()?;
+
+    let mut dest_buffer: Vec<u8> = Vec::new();
+
+    if let Some(size) = input.content_size {
+        dest_buffer
+            .try_reserve_exact(size as usize)
+            .map_err(|_| "unable to allocate output buffer")?;
+    }
+
+    let mut in_buffer = zstd_sys::ZSTD_inBuffer {
+        src: input.data.as_ptr() as *const _,
+        size: input.data.len(),
+        pos: 0,
+    };
+
+    loop {
+        if dest_buffer.len() == dest_buffer.capacity() {
+            dest_buffer
+                .try_reserve(zstd_safe::dstream_out_size())
+                .map_err(|_| "unable to allocate output buffer")?;
+        }
+
+        let zresult = dctx.decompress_into_vec(&mut dest_buffer, &mut in_buffer)?;
+
+        if zresult == 0 {
+            return Ok(dest_buffer);
+        } else if in_buffer.pos == in_buffer.size && dest_buffer.len() < dest_buffer.capacity() {
+            return Err("frame is incomplete".into());
+        }
+    }
+}
+
+/// Create a context for each of `thread_count` threads.
+fn create_dctxs<'a>(dctx: &DCtx<'a>, thread_count: usize) -> PyResult<Vec<DCtx<'a>>> {
+    (0..std::cmp::max(1, thread_count))
+        .map(|_| dctx.try_clone().map_err(PyMemoryError::new_err))
+        .collect()
+}
+
+/// Decompress frames on the global thread pool, one thread per context.
+///
+/// Results are returned in input order.
+fn decompress_frames(py: Python, dctxs: &[DCtx], inputs: &[FrameInput]) -> PyResult<Vec<Vec<u8>>> {
+    // Frames vary in size, so each is its own batch.
+    let batches = (0..inputs.len()).map(|i| i..i + 1).collect::<Vec<_>>();
+
+    let results: Vec<Result<Vec<u8>, ZstdFailure>> = py.allow_threads(|| {
+        process_batches(dctxs, &batches, |dctx, batch| {
+            decompress_frame(dctx, &inputs[batch.start])
+        })
+    });
+
+    results
+        .into_iter()
+        .zip(inputs)
+        .map(|(result, input)| {
+            result.map_err(|msg| {
+                zstd_error(
+                    msg,
+                    format!(
+                        "error decompressing frame at offset {}: {}",
+                        input.offset, msg
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Decompress all frames in `data` in parallel.
+pub fn decompress_parallel<'p>(
+    py: Python<'p>,
+    dctx: &DCtx,
+    data: &[u8],
+    thread_count: usize,
+) -> PyResult<&'p PyBytes> {
+    let frames = walk_frames(py, &mut SliceFrameSource(data))?;
+    let inputs = frame_inputs(data, &frames, 0);
+
+    check_output_size(dctx, declared_size(&inputs))?;
+
+    // More threads than inputs makes no sense.
+    let dctxs = create_dctxs(dctx, std::cmp::min(thread_count, inputs.len()))?;
+    let chunks = decompress_frames(py, &dctxs, &inputs)?;
+
+    let total_size = chunks.iter().map(|chunk| chunk.len()).sum();
+    check_output_size(dctx, total_size as u64)?;
+
+    PyBytes::new_with(py, total_size, |dest| {
+        let mut offset = 0;
+
+        for chunk in &chunks {
+            dest[offset..offset + chunk.len()].copy_from_slice(chunk);
+            offset += chunk.len();
+        }
+
+        Ok(())
+    })
+}
+
+/// Write decompressed `data` to `ofh` in chunks of at most `write_size`.
+fn write_output(py: Python, ofh: &PyAny, data: &[u8], write_size: usize) -> PyResult<()> {
+    for chunk in data.chunks(write_size) {
+        // TODO avoid buffer copy.
+        ofh.call_method1("write", (PyBytes::new(py, chunk),))?;
+    }
+
+    Ok(())
+}
+
+/// Decompress the frame at the start of `pending` as more input is read.
+///
+/// Used for frames too large to buffer. Input following the frame is left in
+/// `pending`.
+#[allow(clippy::too_many_arguments)]
+fn stream_frame(
+    py: Python,
+    dctx: &DCtx,
+    ifh: &PyAny,
+    ofh: &PyAny,
+    read_size: usize,
+    write_size: usize,
+    pending: &mut Vec<u8>,
+    pending_offset: usize,
+    total_read: &mut usize,
+    total_write: &mut usize,
+    progress: &mut ProgressReporter,
+) -> PyResult<()> {
+    dctx.reset().map_err(|msg| {
+        zstd_error(
+            msg,
+            format!("unable to reset decompression context: {}", msg),
+        )
+    })?;
+
+    let mut input = std::mem::take(pending);
+    let mut dest_buffer: Vec<u8> = Vec::with_capacity(write_size);
+
+    loop {
+        let mut in_buffer = zstd_sys::ZSTD_inBuffer {
+            src: input.as_ptr() as *const _,
+            size: input.len(),
+            pos: 0,
+        };
+
+        loop {
+            let zresult = dctx
+                .decompress_into_vec(&mut dest_buffer, &mut in_buffer)
+                .map_err(|msg| {
+                    zstd_error(
+                        msg,
+                        format!(
+                            "error decompressing frame at offset {}: {}",
+                            pending_offset, msg
+                        ),
+                    )
+                })?;
+
+            // A full output buffer may leave output for the next call.
+            let output_full = dest_buffer.len() == dest_buffer.capacity();
+
+            if !dest_buffer.is_empty() {
+                check_output_size(dctx, (*total_write + dest_buffer.len()) as u64)?;
+                write_output(py, ofh, &dest_buffer, write_size)?;
+                *total_write += dest_buffer.len();
+                dest_buffer.clear();
+            }
+
+            if zresult == 0 {
+                pending.extend_from_slice(&input[in_buffer.pos..]);
+                return Ok(());
+            } else if in_buffer.pos == in_buffer.size && !output_full {
+                break;
+            }
+        }
+
+        progress.update(py, *total_read, *total_read, *total_write)?;
+
+        let read_object = ifh.call_method1("read", (read_size,))?;
+        let read_bytes: &PyBytes = read_object.downcast()?;
+        let read_data = read_bytes.as_bytes();
+
+        if read_data.is_empty() {
+            return Err(ZstdError::new_err(format!(
+                "truncated frame at offset {}",
+                pending_offset
+            )));
+        }
+
+        *total_read += read_data.len();
+        input.clear();
+        input.extend_from_slice(read_data);
+    }
+}
+
+/// Decompress frames read from `ifh` in parallel and write output to `ofh`.
+///
+/// Input is read until a batch of complete frames is available or a bounded
+/// amount of input is buffered. Frames in a batch are decompressed
+/// concurrently and their output is written in order. A frame too large to
+/// buffer is decompressed serially as it is read.
+#[allow(clippy::too_many_arguments)]
+pub fn copy_stream_parallel(
+    py: Python,
+    dctx: &DCtx,
+    ifh: &PyAny,
+    ofh: &PyAny,
+    read_size: usize,
+    write_size: usize,
+    thread_count: usize,
+    progress: &mut ProgressReporter,
+) -> PyResult<(usize, usize)> {
+    let batch_size = thread_count * FRAMES_PER_THREAD;
+    let max_pending = thread_count * PENDING_SIZE_PER_THREAD;
+    let dctxs = create_dctxs(dctx, thread_count)?;
+
+    // Input data not yet decompressed and its offset within the stream.
+    let mut pending: Vec<u8> = vec![];
+    let mut pending_offset = 0;
+
+    // Complete frames found in `pending` and the state of looking for more.
+    let mut frames: Vec<FrameInfo> = vec![];
+    let mut scanner = FrameScanner::default();
+
+    let mut total_read = 0;
+    let mut total_write = 0;
+    let mut finished = false;
+
+    while !finished {
+        let read_object = ifh.call_method1("read", (read_size,))?;
+        let read_bytes: &PyBytes = read_object.downcast()?;
+        let read_data = read_bytes.as_bytes();
+
+        if read_data.is_empty() {
+            finished = true;
+        } else {
+            total_read += read_data.len();
+            pending.extend_from_slice(read_data);
+        }
+
+        frames.extend(scanner.scan(py, &pending)?);
+
+        if frames.len() >= batch_size
+            || ((finished || pending.len() >= max_pending) && !frames.is_empty())
+        {
+            for batch in frames.chunks(batch_size) {
+                let inputs = frame_inputs(&pending, batch, pending_offset as u64);
+
+                check_output_size(
+                    dctx,
+                    (total_write as u64).saturating_add(declared_size(&inputs)),
+                )?;
+
+                let chunks = decompress_frames(py, &dctxs, &inputs)?;
+                let output_size: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+                check_output_size(dctx, (total_write + output_size) as u64)?;
+
+                for chunk in chunks {
+                    write_output(py, ofh, &chunk, write_size)?;
+                    total_write += chunk.len();
+                }
+            }
+
+            let consumed = scanner.offset();
+            pending.drain(0..consumed as usize);
+            pending_offset += consumed as usize;
+            scanner.consume(consumed);
+            frames.clear();
+
+            progress.update(py, total_read, total_read, total_write)?;
+        }
+
+        // No frame ends within the buffered input, so stream the frame
+        // instead of buffering more of it.
+        if !finished && frames.is_empty() && pending.len() >= max_pending {
+            stream_frame(
+                py,
+                &dctxs[0],
+                ifh,
+                ofh,
+                read_size,
+                write_size,
+                &mut pending,
+                pending_offset,
+                &mut total_read,
+                &mut total_write,
+                progress,
+            )?;
+
+            pending_offset = total_read - pending.len();
+            scanner = FrameScanner::default();
+        }
+    }
+
+    if !pending.is_empty() {
+        return Err(ZstdError::new_err(format!(
+            "truncated frame at offset {}",
+            pending_offset
+        )));
+    }
+
+    progress.finish(py, total_read, total_read, total_write)?;
+
+    Ok((total_read, total_write))
+}
@@ -4,12 +4,47 @@
 // This software may be modified and distributed under the terms
 // of the BSD license. See the LICENSE file for details.
 
-use pyo3::{create_exception, exceptions::PyException, prelude::*};
+use {
+    crate::zstd_safe::ZstdFailure,
+    pyo3::{
+        create_exception,
+        exceptions::{PyException, PyMemoryError},
+        prelude::*,
+    },
+};
 
 create_exception!(module, ZstdError, PyException);
+create_exception!(module, ZstdOutputLimitError, ZstdError);
+create_exception!(module, ZstdCancelledError, ZstdError);
+
+/// Create the exception for an error reported by zstd.
+///
+/// `message` describes the error and is usually derived from `failure`, which
+/// determines the exception type. Allocation failures, including those caused
+/// by a `MemoryTracker` limit, raise `MemoryError` instead of `ZstdError`.
+/// Exceeding a decompressor's `max_output_size` raises `ZstdOutputLimitError`.
+pub(crate) fn zstd_error(failure: ZstdFailure, message: String) -> PyErr {
+    match failure {
+        ZstdFailure::Alloc => PyMemoryError::new_err(message),
+        ZstdFailure::OutputLimit => ZstdOutputLimitError::new_err(message),
+        ZstdFailure::Zstd(_) => ZstdError::new_err(message),
+    }
+}
+
+// Allows raising any exception type with the failure's description.
+impl IntoPy<PyObject> for ZstdFailure {
+    fn into_py(self, py: Python) -> PyObject {
+        self.to_string().into_py(py)
+    }
+}
 
 pub(crate) fn init_module(py: Python, module: &PyModule) -> PyResult<()> {
     module.add("ZstdError", py.get_type::<ZstdError>())?;
+    module.add(
+        "ZstdOutputLimitError",
+        py.get_type::<ZstdOutputLimitError>(),
+    )?;
+    module.add("ZstdCancelledError", py.get_type::<ZstdCancelledError>())?;
 
     Ok(())
 }
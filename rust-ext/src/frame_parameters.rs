@@ -5,13 +5,17 @@
 // of the BSD license. See the LICENSE file for details.
 
 use {
-    crate::ZstdError,
-    pyo3::{buffer::PyBuffer, prelude::*, wrap_pyfunction},
+    crate::{
+        frames::{walk_frames, FileFrameSource, FrameInfo, SliceFrameSource},
+        ZstdError,
+    },
+    pyo3::{buffer::PyBuffer, prelude::*, types::PyString, wrap_pyfunction},
 };
 
 #[pyclass(module = "zstandard.backend_rust")]
 struct FrameParameters {
     header: zstd_sys::ZSTD_frameHeader,
+    legacy_version: u32,
 }
 
 #[pymethods]
@@ -38,6 +42,156 @@ impl FrameParameters {
             _ => true,
         })
     }
+
+    #[getter]
+    fn legacy_version(&self) -> u32 {
+        self.legacy_version
+    }
+}
+
+#[pyclass(module = "zstandard.backend_rust")]
+pub(crate) struct FramesSummary {
+    frame_count: usize,
+    skippable_frame_count: usize,
+    checksum_frame_count: usize,
+    compressed_size: u64,
+    decompressed_size: Option<u64>,
+    window_size: u64,
+    dict_ids: Vec<u32>,
+}
+
+impl FramesSummary {
+    pub fn from_frames(frames: &[FrameInfo]) -> Self {
+        let mut summary = Self {
+            frame_count: 0,
+            skippable_frame_count: 0,
+            checksum_frame_count: 0,
+            compressed_size: 0,
+            decompressed_size: Some(0),
+            window_size: 0,
+            dict_ids: vec![],
+        };
+
+        for frame in frames {
+            summary.compressed_size += frame.compressed_size;
+
+            if frame.is_skippable() {
+                summary.skippable_frame_count += 1;
+                continue;
+            }
+
+            summary.frame_count += 1;
+
+            if frame.header.checksumFlag != 0 {
+                summary.checksum_frame_count += 1;
+            }
+
+            summary.decompressed_size = summary
+                .decompressed_size
+                .and_then(|total| frame.content_size().map(|size| total + size));
+            summary.window_size = std::cmp::max(summary.window_size, frame.header.windowSize);
+
+            if frame.header.dictID != 0 && !summary.dict_ids.contains(&frame.header.dictID) {
+                summary.dict_ids.push(frame.header.dictID);
+            }
+        }
+
+        summary.dict_ids.sort_unstable();
+
+        summary
+    }
+
+    /// Describe the checksums of zstd frames the way `zstd -l` does.
+    fn checksum_name(&self) -> Option<&'static str> {
+        if self.checksum_frame_count == 0 {
+            None
+        } else if self.checksum_frame_count == self.frame_count {
+            Some("XXH64")
+        } else {
+            Some("mixed")
+        }
+    }
+}
+
+#[pymethods]
+impl FramesSummary {
+    #[getter]
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    #[getter]
+    pub fn skippable_frame_count(&self) -> usize {
+        self.skippable_frame_count
+    }
+
+    #[getter]
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    #[getter]
+    pub fn decompressed_size(&self) -> Option<u64> {
+        self.decompressed_size
+    }
+
+    #[getter]
+    pub fn ratio(&self) -> Option<f64> {
+        match self.decompressed_size {
+            Some(size) if self.compressed_size > 0 => {
+                Some(size as f64 / self.compressed_size as f64)
+            }
+            _ => None,
+        }
+    }
+
+    #[getter]
+    pub fn checksum(&self) -> Option<&'static str> {
+        self.checksum_name()
+    }
+
+    #[getter]
+    pub fn window_size(&self) -> u64 {
+        self.window_size
+    }
+
+    #[getter]
+    pub fn dict_ids(&self) -> Vec<u32> {
+        self.dict_ids.clone()
+    }
+}
+
+/// Find all frames in a path, file object or bytes-like object.
+///
+/// File objects are read from their current position.
+pub(crate) fn source_frames(py: Python, source: &PyAny) -> PyResult<Vec<FrameInfo>> {
+    if source.is_instance::<PyString>()? || source.hasattr("__fspath__")? {
+        let fh = py
+            .import("builtins")?
+            .getattr("open")?
+            .call1((source, "rb"))?;
+
+        let frames = walk_frames(py, &mut FileFrameSource::new(fh, 0));
+        fh.call_method0("close")?;
+
+        frames
+    } else if source.hasattr("read")? {
+        let origin: u64 = source.call_method0("tell")?.extract()?;
+
+        walk_frames(py, &mut FileFrameSource::new(source, origin))
+    } else {
+        let buffer = PyBuffer::<u8>::get(source)?;
+        let data = unsafe {
+            std::slice::from_raw_parts::<u8>(buffer.buf_ptr() as *const _, buffer.len_bytes())
+        };
+
+        walk_frames(py, &mut SliceFrameSource(data))
+    }
+}
+
+#[pyfunction]
+fn summarize_frames(py: Python, source: &PyAny) -> PyResult<FramesSummary> {
+    Ok(FramesSummary::from_frames(&source_frames(py, source)?))
 }
 
 #[pyfunction]
@@ -66,6 +220,14 @@ fn frame_header_size(data: PyBuffer<u8>) -> PyResult<usize> {
     Ok(zresult)
 }
 
+#[pyfunction]
+fn frame_legacy_version(data: PyBuffer<u8>) -> u32 {
+    let data =
+        unsafe { std::slice::from_raw_parts::<u8>(data.buf_ptr() as *const _, data.len_bytes()) };
+
+    crate::zstd_safe::legacy_version(data)
+}
+
 #[pyfunction]
 fn get_frame_parameters(py: Python, buffer: PyBuffer<u8>) -> PyResult<Py<FrameParameters>> {
     let raw_data = unsafe {
@@ -81,6 +243,24 @@ fn get_frame_parameters(py: Python, buffer: PyBuffer<u8>) -> PyResult<Py<FramePa
         dictID: 0,
         checksumFlag: 0,
     };
+
+    // Legacy frame headers can't be parsed. But their content size can be
+    // obtained.
+    let legacy_version = crate::zstd_safe::legacy_version(raw_data);
+    if legacy_version != 0 {
+        header.frameContentSize = unsafe {
+            zstd_sys::ZSTD_getFrameContentSize(raw_data.as_ptr() as *const _, raw_data.len())
+        };
+
+        return Py::new(
+            py,
+            FrameParameters {
+                header,
+                legacy_version,
+            },
+        );
+    }
+
     let zresult = unsafe {
         zstd_sys::ZSTD_getFrameHeader(&mut header, raw_data.as_ptr() as *const _, raw_data.len())
     };
@@ -96,15 +276,24 @@ fn get_frame_parameters(py: Python, buffer: PyBuffer<u8>) -> PyResult<Py<FramePa
             zresult
         )))
     } else {
-        Py::new(py, FrameParameters { header })
+        Py::new(
+            py,
+            FrameParameters {
+                header,
+                legacy_version: 0,
+            },
+        )
     }
 }
 
 pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
     module.add_class::<FrameParameters>()?;
+    module.add_class::<FramesSummary>()?;
     module.add_function(wrap_pyfunction!(frame_content_size, module)?)?;
     module.add_function(wrap_pyfunction!(frame_header_size, module)?)?;
+    module.add_function(wrap_pyfunction!(frame_legacy_version, module)?)?;
     module.add_function(wrap_pyfunction!(get_frame_parameters, module)?)?;
+    module.add_function(wrap_pyfunction!(summarize_frames, module)?)?;
 
     Ok(())
 }
@@ -0,0 +1,440 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    crate::exceptions::ZstdError,
+    pyo3::{buffer::PyBuffer, prelude::*},
+};
+
+/// Size of a block header within a zstd frame.
+const BLOCK_HEADER_SIZE: usize = 3;
+
+/// Size of the content checksum at the end of a zstd frame.
+const CHECKSUM_SIZE: u64 = 4;
+
+/// Amount of data initially read to find the end of a legacy frame.
+const LEGACY_PROBE_SIZE: usize = 128 * 1024;
+
+/// Describes a frame found by walking a stream of zstd frames.
+pub(crate) struct FrameInfo {
+    /// Offset of the start of this frame within the stream.
+    pub offset: u64,
+    /// Total size of the frame, including header and checksum.
+    pub compressed_size: u64,
+    /// The parsed frame header.
+    ///
+    /// Only the content size is populated for legacy frames.
+    pub header: zstd_sys::ZSTD_frameHeader,
+    /// Legacy zstd format version of the frame. `0` if not a legacy frame.
+    pub legacy_version: u32,
+}
+
+impl FrameInfo {
+    pub fn is_skippable(&self) -> bool {
+        self.header.frameType == zstd_sys::ZSTD_frameType_e::ZSTD_skippableFrame
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.legacy_version != 0
+    }
+
+    /// Decompressed size of this frame, if known.
+    ///
+    /// Skippable frames don't decompress to anything.
+    pub fn content_size(&self) -> Option<u64> {
+        if self.is_skippable() {
+            Some(0)
+        } else if self.header.frameContentSize == zstd_safe::CONTENTSIZE_UNKNOWN {
+            None
+        } else {
+            Some(self.header.frameContentSize)
+        }
+    }
+}
+
+/// Provides random access to compressed data.
+pub(crate) trait FrameSource {
+    /// Read up to `dest.len()` bytes at `offset`, returning the number read.
+    ///
+    /// Fewer bytes are returned only when the end of data is reached.
+    fn read_at(&mut self, py: Python, offset: u64, dest: &mut [u8]) -> PyResult<usize>;
+}
+
+/// A `FrameSource` backed by memory.
+pub(crate) struct SliceFrameSource<'a>(pub &'a [u8]);
+
+impl<'a> FrameSource for SliceFrameSource<'a> {
+    fn read_at(&mut self, _py: Python, offset: u64, dest: &mut [u8]) -> PyResult<usize> {
+        let offset = std::cmp::min(offset as usize, self.0.len());
+        let count = std::cmp::min(dest.len(), self.0.len() - offset);
+
+        dest[0..count].copy_from_slice(&self.0[offset..offset + count]);
+
+        Ok(count)
+    }
+}
+
+/// A `FrameSource` backed by a Python object with `seek()` and `read()`.
+///
+/// Offsets are relative to `origin` within the object. The object's
+/// position is not restored.
+pub(crate) struct FileFrameSource<'a> {
+    fh: &'a PyAny,
+    origin: u64,
+}
+
+impl<'a> FileFrameSource<'a> {
+    pub fn new(fh: &'a PyAny, origin: u64) -> Self {
+        Self { fh, origin }
+    }
+}
+
+impl<'a> FrameSource for FileFrameSource<'a> {
+    fn read_at(&mut self, _py: Python, offset: u64, dest: &mut [u8]) -> PyResult<usize> {
+        self.fh.call_method1("seek", (self.origin + offset,))?;
+
+        let mut count = 0;
+
+        while count < dest.len() {
+            let data = self.fh.call_method1("read", (dest.len() - count,))?;
+            let buffer = PyBuffer::<u8>::get(data)?;
+
+            if buffer.len_bytes() == 0 {
+                break;
+            }
+
+            let data = unsafe {
+                std::slice::from_raw_parts(buffer.buf_ptr() as *const u8, buffer.len_bytes())
+            };
+            let chunk = std::cmp::min(data.len(), dest.len() - count);
+
+            dest[count..count + chunk].copy_from_slice(&data[0..chunk]);
+            count += chunk;
+        }
+
+        Ok(count)
+    }
+}
+
+/// Result of attempting to parse a frame.
+pub(crate) enum FrameProbe {
+    /// A complete frame was found.
+    Frame(FrameInfo),
+    /// The frame extends beyond the end of available data.
+    Truncated,
+    /// There is no more data.
+    End,
+}
+
+/// A frame whose header was parsed but whose end wasn't found yet.
+#[derive(Clone, Copy)]
+pub(crate) struct PartialFrame {
+    header: zstd_sys::ZSTD_frameHeader,
+    /// Position of the next block header to parse.
+    position: u64,
+}
+
+/// Result of parsing a frame that may be resumed once more data is available.
+enum FrameParse {
+    Probe(FrameProbe),
+    Partial(PartialFrame),
+}
+
+/// Parse the frame starting at `offset`.
+pub(crate) fn probe_frame(
+    py: Python,
+    source: &mut dyn FrameSource,
+    offset: u64,
+) -> PyResult<FrameProbe> {
+    Ok(match parse_frame(py, source, offset)? {
+        FrameParse::Probe(probe) => probe,
+        FrameParse::Partial(_) => FrameProbe::Truncated,
+    })
+}
+
+/// Parse the frame starting at `offset`, remembering progress through its
+/// blocks if it is truncated.
+fn parse_frame(py: Python, source: &mut dyn FrameSource, offset: u64) -> PyResult<FrameParse> {
+    let mut header_data = [0u8; zstd_sys::ZSTD_FRAMEHEADERSIZE_MAX as usize];
+    let header_len = source.read_at(py, offset, &mut header_data)?;
+
+    if header_len == 0 {
+        return Ok(FrameParse::Probe(FrameProbe::End));
+    }
+
+    let mut header = zstd_sys::ZSTD_frameHeader {
+        frameContentSize: 0,
+        windowSize: 0,
+        blockSizeMax: 0,
+        frameType: zstd_sys::ZSTD_frameType_e::ZSTD_frame,
+        headerSize: 0,
+        dictID: 0,
+        checksumFlag: 0,
+    };
+    let zresult = unsafe {
+        zstd_sys::ZSTD_getFrameHeader(&mut header, header_data.as_ptr() as *const _, header_len)
+    };
+
+    if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
+        let legacy_version = crate::zstd_safe::legacy_version(&header_data[0..header_len]);
+        if legacy_version != 0 {
+            return Ok(FrameParse::Probe(probe_legacy_frame(
+                py,
+                source,
+                offset,
+                legacy_version,
+            )?));
+        }
+
+        return Err(ZstdError::new_err(format!(
+            "error parsing frame header at offset {}: {}",
+            offset,
+            zstd_safe::get_error_name(zresult)
+        )));
+    } else if zresult != 0 {
+        return Ok(FrameParse::Probe(FrameProbe::Truncated));
+    }
+
+    if header.frameType == zstd_sys::ZSTD_frameType_e::ZSTD_skippableFrame {
+        // frameContentSize holds the size of the skippable content. headerSize
+        // isn't populated for skippable frames.
+        let end = offset + zstd_sys::ZSTD_SKIPPABLEHEADERSIZE as u64 + header.frameContentSize;
+
+        // Ensure the frame's final byte is present.
+        let mut last = [0u8; 1];
+        if source.read_at(py, end - 1, &mut last)? != 1 {
+            return Ok(FrameParse::Probe(FrameProbe::Truncated));
+        }
+
+        return Ok(FrameParse::Probe(FrameProbe::Frame(FrameInfo {
+            offset,
+            compressed_size: end - offset,
+            header,
+            legacy_version: 0,
+        })));
+    }
+
+    resume_frame(
+        py,
+        source,
+        offset,
+        PartialFrame {
+            header,
+            position: offset + header.headerSize as u64,
+        },
+    )
+}
+
+/// Continue parsing the blocks of the frame starting at `offset`.
+///
+/// Block headers are walked to find the end of the frame without needing the
+/// block contents.
+fn resume_frame(
+    py: Python,
+    source: &mut dyn FrameSource,
+    offset: u64,
+    mut partial: PartialFrame,
+) -> PyResult<FrameParse> {
+    loop {
+        let mut block_header = [0u8; BLOCK_HEADER_SIZE];
+        if source.read_at(py, partial.position, &mut block_header)? != BLOCK_HEADER_SIZE {
+            return Ok(FrameParse::Partial(partial));
+        }
+
+        let value = u32::from(block_header[0])
+            | u32::from(block_header[1]) << 8
+            | u32::from(block_header[2]) << 16;
+        let last_block = value & 1 == 1;
+        let block_size = (value >> 3) as u64;
+
+        let mut end = partial.position
+            + BLOCK_HEADER_SIZE as u64
+            + match (value >> 1) & 3 {
+                // Raw and compressed blocks store block_size bytes.
+                0 | 2 => block_size,
+                // RLE blocks store a single byte.
+                1 => 1,
+                _ => {
+                    return Err(ZstdError::new_err(format!(
+                        "reserved block type in frame at offset {}",
+                        offset
+                    )));
+                }
+            };
+
+        if !last_block {
+            partial.position = end;
+            continue;
+        }
+
+        if partial.header.checksumFlag != 0 {
+            end += CHECKSUM_SIZE;
+        }
+
+        // Ensure the frame's final byte is present. Otherwise the last block
+        // header is parsed again once there is more data.
+        let mut last = [0u8; 1];
+        if source.read_at(py, end - 1, &mut last)? != 1 {
+            return Ok(FrameParse::Partial(partial));
+        }
+
+        return Ok(FrameParse::Probe(FrameProbe::Frame(FrameInfo {
+            offset,
+            compressed_size: end - offset,
+            header: partial.header,
+            legacy_version: 0,
+        })));
+    }
+}
+
+/// Parse the legacy format frame starting at `offset`.
+///
+/// Legacy frame headers can't be parsed. Instead, increasingly large amounts
+/// of data are read until zstd can find the end of the frame.
+fn probe_legacy_frame(
+    py: Python,
+    source: &mut dyn FrameSource,
+    offset: u64,
+    legacy_version: u32,
+) -> PyResult<FrameProbe> {
+    let mut data = vec![0u8; LEGACY_PROBE_SIZE];
+
+    loop {
+        let len = source.read_at(py, offset, &mut data)?;
+
+        let zresult =
+            unsafe { zstd_sys::ZSTD_findFrameCompressedSize(data.as_ptr() as *const _, len) };
+
+        if unsafe { zstd_sys::ZSTD_isError(zresult) } == 0 {
+            let content_size =
+                match unsafe { zstd_sys::ZSTD_getFrameContentSize(data.as_ptr() as *const _, len) }
+                {
+                    zstd_safe::CONTENTSIZE_ERROR => zstd_safe::CONTENTSIZE_UNKNOWN,
+                    size => size,
+                };
+
+            return Ok(FrameProbe::Frame(FrameInfo {
+                offset,
+                compressed_size: zresult as u64,
+                header: zstd_sys::ZSTD_frameHeader {
+                    frameContentSize: content_size,
+                    windowSize: 0,
+                    blockSizeMax: 0,
+                    frameType: zstd_sys::ZSTD_frameType_e::ZSTD_frame,
+                    headerSize: 0,
+                    dictID: 0,
+                    checksumFlag: 0,
+                },
+                legacy_version,
+            }));
+        }
+
+        if !crate::zstd_safe::is_src_size_error(zresult) {
+            return Err(ZstdError::new_err(format!(
+                "error parsing legacy zstd v0.{} frame at offset {}: {}",
+                legacy_version,
+                offset,
+                zstd_safe::get_error_name(zresult)
+            )));
+        }
+
+        // More data won't help once all of it was read.
+        if len < data.len() {
+            return Ok(FrameProbe::Truncated);
+        }
+
+        data.resize(data.len() * 2, 0);
+    }
+}
+
+/// Parse the frame starting at `offset`.
+///
+/// Returns `None` if there is no more data at `offset`.
+pub(crate) fn read_frame(
+    py: Python,
+    source: &mut dyn FrameSource,
+    offset: u64,
+) -> PyResult<Option<FrameInfo>> {
+    match probe_frame(py, source, offset)? {
+        FrameProbe::Frame(frame) => Ok(Some(frame)),
+        FrameProbe::End => Ok(None),
+        FrameProbe::Truncated => Err(ZstdError::new_err(format!(
+            "truncated frame at offset {}",
+            offset
+        ))),
+    }
+}
+
+/// Find all frames in a source.
+pub(crate) fn walk_frames(py: Python, source: &mut dyn FrameSource) -> PyResult<Vec<FrameInfo>> {
+    let mut frames = vec![];
+    let mut offset = 0;
+
+    while let Some(frame) = read_frame(py, source, offset)? {
+        offset += frame.compressed_size;
+        frames.push(frame);
+    }
+
+    Ok(frames)
+}
+
+/// Finds complete frames in a buffer as data is appended to it.
+///
+/// Unlike `walk_frames()`, a trailing partial frame is not an error. How far
+/// its blocks were parsed is remembered, so each block header is only parsed
+/// once no matter how often more data is appended.
+#[derive(Default)]
+pub(crate) struct FrameScanner {
+    /// Offset of the frame following those already found.
+    offset: u64,
+    /// Progress parsing the frame at `offset`.
+    partial: Option<PartialFrame>,
+}
+
+impl FrameScanner {
+    /// Offset of the frame following those already found.
+    ///
+    /// Data before it consists of complete frames.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Find complete frames in `data` following those already found.
+    pub fn scan(&mut self, py: Python, data: &[u8]) -> PyResult<Vec<FrameInfo>> {
+        let mut source = SliceFrameSource(data);
+        let mut frames = vec![];
+
+        loop {
+            let parse = match self.partial.take() {
+                Some(partial) => resume_frame(py, &mut source, self.offset, partial)?,
+                None => parse_frame(py, &mut source, self.offset)?,
+            };
+
+            match parse {
+                FrameParse::Probe(FrameProbe::Frame(frame)) => {
+                    self.offset += frame.compressed_size;
+                    frames.push(frame);
+                }
+                FrameParse::Probe(_) => return Ok(frames),
+                FrameParse::Partial(partial) => {
+                    self.partial = Some(partial);
+                    return Ok(frames);
+                }
+            }
+        }
+    }
+
+    /// Account for `count` bytes being removed from the start of the data.
+    ///
+    /// `count` must not exceed `offset()`.
+    pub fn consume(&mut self, count: u64) {
+        self.offset -= count;
+
+        if let Some(partial) = &mut self.partial {
+            partial.position -= count;
+        }
+    }
+}
@@ -8,16 +8,21 @@
 
 use pyo3::{prelude::*, types::PySet};
 
+mod benchmark;
 mod buffers;
+mod cancellation;
+mod cli;
 mod compression_chunker;
 mod compression_dict;
 mod compression_parameters;
 mod compression_reader;
+mod compression_stats;
 mod compression_writer;
 mod compressionobj;
 mod compressor;
 mod compressor_iterator;
 mod compressor_multi;
+mod compressor_parallel;
 mod constants;
 mod decompression_reader;
 mod decompression_writer;
@@ -25,9 +30,14 @@ mod decompressionobj;
 mod decompressor;
 mod decompressor_iterator;
 mod decompressor_multi;
+mod decompressor_parallel;
 mod exceptions;
 mod frame_parameters;
+mod frames;
+mod memory;
+mod progress;
 mod stream;
+mod threads;
 mod zstd_safe;
 
 use exceptions::ZstdError;
@@ -41,21 +51,51 @@ fn backend_rust(py: Python, module: &PyModule) -> PyResult<()> {
     let features = PySet::new(
         py,
         &[
+            "benchmark",
+            "buffer_from_file",
+            "buffer_offsets",
+            "buffer_segments_array",
+            "buffer_sequence_ops",
+            "buffer_serialization",
             "buffer_types",
+            "cancellation",
+            "cli",
+            "compress_parallel_frames",
+            "compression_stats",
+            "decompress_parallel",
+            "decompression_reader_backward_seek",
+            "decompression_reader_readline",
+            "decompression_reader_seek_end",
+            "legacy_frames",
+            "max_output_size",
+            "memory_tracker",
             "multi_compress_to_buffer",
+            "multi_compress_to_buffer_sequences",
+            "multi_compress_to_iter",
             "multi_decompress_to_buffer",
+            "multi_decompress_to_buffer_errors",
+            "multi_threads_auto",
+            "progress_callbacks",
+            "static_contexts",
+            "summarize_frames",
         ],
     )?;
     module.add("backend_features", features)?;
 
+    crate::benchmark::init_module(module)?;
     crate::buffers::init_module(module)?;
+    crate::cancellation::init_module(module)?;
+    crate::cli::init_module(module)?;
     crate::compression_dict::init_module(module)?;
     crate::compression_parameters::init_module(module)?;
+    crate::compression_stats::init_module(module)?;
     crate::compressor::init_module(module)?;
     crate::constants::init_module(py, module)?;
     crate::decompressor::init_module(module)?;
     crate::exceptions::init_module(py, module)?;
     crate::frame_parameters::init_module(module)?;
+    crate::memory::init_module(module)?;
+    crate::progress::init_module(py, module)?;
 
     Ok(())
 }
@@ -0,0 +1,254 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    pyo3::{prelude::*, wrap_pyfunction},
+    std::{
+        alloc::Layout,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    },
+};
+
+/// Error message zstd uses for `ZSTD_error_memory_allocation`.
+pub(crate) const ALLOCATION_ERROR: &str = "Allocation error : not enough memory";
+
+/// Bytes reserved in front of each allocation to record its size.
+///
+/// This is also the alignment of allocations, matching what `malloc()`
+/// guarantees on 64-bit platforms.
+const HEADER_SIZE: usize = 16;
+
+/// Process-wide tracker, used by objects that aren't given one explicitly.
+static DEFAULT_TRACKER: Mutex<Option<Py<MemoryTracker>>> = Mutex::new(None);
+
+/// Counters for memory allocated by zstd.
+///
+/// Instances are shared with the zstd objects allocating through them and
+/// must outlive those objects.
+pub struct MemoryAccounting {
+    live_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+    allocation_count: AtomicUsize,
+    failed_allocation_count: AtomicUsize,
+    /// Maximum value of `live_bytes`. `usize::MAX` if unlimited.
+    limit: AtomicUsize,
+}
+
+impl MemoryAccounting {
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            live_bytes: AtomicUsize::new(0),
+            peak_bytes: AtomicUsize::new(0),
+            allocation_count: AtomicUsize::new(0),
+            failed_allocation_count: AtomicUsize::new(0),
+            limit: AtomicUsize::new(limit.unwrap_or(usize::MAX)),
+        }
+    }
+
+    pub fn failed_allocation_count(&self) -> usize {
+        self.failed_allocation_count.load(Ordering::Relaxed)
+    }
+
+    /// Account for an allocation of `size` bytes, if the limit allows it.
+    fn reserve(&self, size: usize) -> bool {
+        let limit = self.limit.load(Ordering::Relaxed);
+
+        let reserved = self
+            .live_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |live| {
+                live.checked_add(size).filter(|total| *total <= limit)
+            });
+
+        match reserved {
+            Ok(live) => {
+                self.peak_bytes.fetch_max(live + size, Ordering::Relaxed);
+                self.allocation_count.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(_) => {
+                self.failed_allocation_count.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    fn release(&self, size: usize) {
+        self.live_bytes.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+fn header_layout(size: usize) -> Option<Layout> {
+    Layout::from_size_align(size.checked_add(HEADER_SIZE)?, HEADER_SIZE).ok()
+}
+
+unsafe extern "C" fn tracked_alloc(
+    opaque: *mut libc::c_void,
+    size: libc::size_t,
+) -> *mut libc::c_void {
+    let accounting = &*(opaque as *const MemoryAccounting);
+
+    let layout = match header_layout(size) {
+        Some(layout) => layout,
+        None => {
+            accounting
+                .failed_allocation_count
+                .fetch_add(1, Ordering::Relaxed);
+            return std::ptr::null_mut();
+        }
+    };
+
+    if !accounting.reserve(size) {
+        return std::ptr::null_mut();
+    }
+
+    let ptr = std::alloc::alloc(layout);
+    if ptr.is_null() {
+        accounting.release(size);
+        accounting.allocation_count.fetch_sub(1, Ordering::Relaxed);
+        accounting
+            .failed_allocation_count
+            .fetch_add(1, Ordering::Relaxed);
+        return std::ptr::null_mut();
+    }
+
+    (ptr as *mut usize).write(size);
+
+    ptr.add(HEADER_SIZE) as *mut _
+}
+
+unsafe extern "C" fn tracked_free(opaque: *mut libc::c_void, address: *mut libc::c_void) {
+    // zstd doesn't free NULL. But be defensive.
+    if address.is_null() {
+        return;
+    }
+
+    let accounting = &*(opaque as *const MemoryAccounting);
+
+    let ptr = (address as *mut u8).sub(HEADER_SIZE);
+    let size = (ptr as *const usize).read();
+
+    accounting.release(size);
+
+    std::alloc::dealloc(ptr, header_layout(size).unwrap());
+}
+
+/// Obtain the allocator zstd should use for `accounting`.
+///
+/// The returned value references `accounting` without owning it.
+pub(crate) fn custom_mem(accounting: &Option<Arc<MemoryAccounting>>) -> zstd_sys::ZSTD_customMem {
+    match accounting {
+        Some(accounting) => zstd_sys::ZSTD_customMem {
+            customAlloc: Some(tracked_alloc),
+            customFree: Some(tracked_free),
+            opaque: Arc::as_ptr(accounting) as *mut _,
+        },
+        None => zstd_sys::ZSTD_customMem {
+            customAlloc: None,
+            customFree: None,
+            opaque: std::ptr::null_mut(),
+        },
+    }
+}
+
+/// Resolve the memory accounting to use for new zstd objects.
+///
+/// Falls back to the process-wide tracker if `tracker` is `None`.
+pub(crate) fn resolve_accounting(
+    py: Python,
+    tracker: &Option<Py<MemoryTracker>>,
+) -> Option<Arc<MemoryAccounting>> {
+    match tracker {
+        Some(tracker) => Some(tracker.borrow(py).accounting.clone()),
+        None => DEFAULT_TRACKER
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|tracker| tracker.borrow(py).accounting.clone()),
+    }
+}
+
+#[pyclass(module = "zstandard.backend_rust")]
+pub(crate) struct MemoryTracker {
+    accounting: Arc<MemoryAccounting>,
+}
+
+#[pymethods]
+impl MemoryTracker {
+    #[new]
+    #[args(limit = "None")]
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            accounting: Arc::new(MemoryAccounting::new(limit)),
+        }
+    }
+
+    #[getter]
+    fn live_bytes(&self) -> usize {
+        self.accounting.live_bytes.load(Ordering::Relaxed)
+    }
+
+    #[getter]
+    fn peak_bytes(&self) -> usize {
+        self.accounting.peak_bytes.load(Ordering::Relaxed)
+    }
+
+    #[getter]
+    fn allocation_count(&self) -> usize {
+        self.accounting.allocation_count.load(Ordering::Relaxed)
+    }
+
+    #[getter]
+    fn failed_allocation_count(&self) -> usize {
+        self.accounting.failed_allocation_count()
+    }
+
+    #[getter]
+    fn limit(&self) -> Option<usize> {
+        match self.accounting.limit.load(Ordering::Relaxed) {
+            usize::MAX => None,
+            limit => Some(limit),
+        }
+    }
+
+    #[setter]
+    fn set_limit(&self, limit: Option<usize>) {
+        self.accounting
+            .limit
+            .store(limit.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    fn reset_peak(&self) {
+        self.accounting.peak_bytes.store(
+            self.accounting.live_bytes.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+#[pyfunction]
+fn set_memory_tracker(tracker: Option<Py<MemoryTracker>>) {
+    *DEFAULT_TRACKER.lock().unwrap() = tracker;
+}
+
+#[pyfunction]
+fn get_memory_tracker(py: Python) -> Option<Py<MemoryTracker>> {
+    DEFAULT_TRACKER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|tracker| tracker.clone_ref(py))
+}
+
+pub(crate) fn init_module(module: &PyModule) -> PyResult<()> {
+    module.add_class::<MemoryTracker>()?;
+    module.add_function(wrap_pyfunction!(get_memory_tracker, module)?)?;
+    module.add_function(wrap_pyfunction!(set_memory_tracker, module)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    crate::exceptions::ZstdCancelledError,
+    pyo3::{exceptions::PyTypeError, once_cell::GILOnceCell, prelude::*},
+};
+
+/// Value progress callbacks return to cancel the operation.
+static PROGRESS_CANCEL: GILOnceCell<PyObject> = GILOnceCell::new();
+
+fn progress_cancel(py: Python<'_>) -> PyResult<&PyAny> {
+    if let Some(sentinel) = PROGRESS_CANCEL.get(py) {
+        return Ok(sentinel.as_ref(py));
+    }
+
+    let sentinel = py.import("builtins")?.getattr("object")?.call0()?;
+
+    Ok(PROGRESS_CANCEL
+        .get_or_init(py, || sentinel.into_py(py))
+        .as_ref(py))
+}
+
+/// Reports progress of an operation to an optional Python callable.
+///
+/// The callable receives the totals of bytes read and written. Progress is
+/// measured in units of work, such as bytes or items, and is reported once
+/// `interval` units were completed since the last report.
+pub(crate) struct ProgressReporter<'p> {
+    callback: Option<&'p PyAny>,
+    interval: usize,
+    /// Units of work completed at the last report.
+    reported: usize,
+}
+
+impl<'p> ProgressReporter<'p> {
+    /// Create an instance reporting to `callback`.
+    ///
+    /// With no `interval`, every update is reported.
+    pub fn new(
+        py: Python<'p>,
+        callback: Option<PyObject>,
+        interval: Option<usize>,
+    ) -> PyResult<Self> {
+        let callback = callback.map(|callback| callback.into_ref(py));
+
+        if let Some(callback) = callback {
+            if !callback.is_callable() {
+                return Err(PyTypeError::new_err("progress must be callable"));
+            }
+        }
+
+        Ok(Self {
+            callback,
+            interval: interval.unwrap_or(0),
+            reported: 0,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.callback.is_some()
+    }
+
+    /// Record that `done` units of work were completed in total.
+    pub fn update(&mut self, py: Python, done: usize, read: usize, written: usize) -> PyResult<()> {
+        if done - self.reported >= std::cmp::max(1, self.interval) {
+            self.report(py, done, read, written)?;
+        }
+
+        Ok(())
+    }
+
+    /// Record that the operation completed, reporting the final totals.
+    pub fn finish(&mut self, py: Python, done: usize, read: usize, written: usize) -> PyResult<()> {
+        self.report(py, done, read, written)
+    }
+
+    fn report(&mut self, py: Python, done: usize, read: usize, written: usize) -> PyResult<()> {
+        self.reported = done;
+
+        if let Some(callback) = self.callback {
+            let result = callback.call1((read, written))?;
+
+            if std::ptr::eq(result, progress_cancel(py)?) {
+                return Err(ZstdCancelledError::new_err(
+                    "operation cancelled by progress callback",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn init_module(py: Python, module: &PyModule) -> PyResult<()> {
+    module.add("PROGRESS_CANCEL", progress_cancel(py)?)?;
+
+    Ok(())
+}
@@ -5,7 +5,12 @@
 // of the BSD license. See the LICENSE file for details.
 
 use {
-    pyo3::{buffer::PyBuffer, exceptions::PyValueError, prelude::*},
+    pyo3::{
+        buffer::PyBuffer,
+        exceptions::{PyOSError, PyValueError},
+        prelude::*,
+    },
+    std::cmp::min,
     zstd_sys::ZSTD_inBuffer,
 };
 
@@ -25,6 +30,20 @@ pub trait InBufferSource {
 
     /// Whether source data has been fully consumed.
     fn finished(&self) -> bool;
+
+    /// Number of bytes consumed since reading started.
+    fn tell(&self) -> usize;
+
+    /// Whether the source can be repositioned via `seek_to()`.
+    fn seekable(&self, py: Python) -> PyResult<bool>;
+
+    /// Position within the source object where reading started.
+    ///
+    /// Only meaningful if the source is seekable.
+    fn start_position(&self, py: Python) -> PyResult<usize>;
+
+    /// Reposition the source to `offset` bytes after where reading started.
+    fn seek_to(&mut self, py: Python, offset: usize) -> PyResult<()>;
 }
 
 /// A data source where data is obtaine by calling `read()`.
@@ -34,6 +53,8 @@ struct ReadSource {
     read_size: usize,
     finished: bool,
     offset: usize,
+    /// Total bytes returned by `read()`.
+    bytes_read: usize,
 }
 
 impl InBufferSource for ReadSource {
@@ -64,6 +85,7 @@ impl InBufferSource for ReadSource {
                 self.finished = true;
                 Ok(None)
             } else {
+                self.bytes_read += buffer.len_bytes();
                 self.buffer = Some(buffer);
                 self.offset = 0;
 
@@ -92,6 +114,49 @@ impl InBufferSource for ReadSource {
     fn finished(&self) -> bool {
         self.finished
     }
+
+    fn tell(&self) -> usize {
+        match &self.buffer {
+            Some(buffer) => self.bytes_read - (buffer.len_bytes() - self.offset),
+            None => self.bytes_read,
+        }
+    }
+
+    fn seekable(&self, py: Python) -> PyResult<bool> {
+        let source = self.source.as_ref(py);
+
+        if !source.hasattr("seek")? || !source.hasattr("tell")? {
+            return Ok(false);
+        }
+
+        if source.hasattr("seekable")? {
+            source.call_method0("seekable")?.is_true()
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn start_position(&self, py: Python) -> PyResult<usize> {
+        // Derived from the current position and how much we've read.
+        let position = self.source.call_method0(py, "tell")?.extract::<usize>(py)?;
+
+        position
+            .checked_sub(self.bytes_read)
+            .ok_or_else(|| PyOSError::new_err("source position is inconsistent with bytes read"))
+    }
+
+    fn seek_to(&mut self, py: Python, offset: usize) -> PyResult<()> {
+        let start = self.start_position(py)?;
+
+        self.source.call_method1(py, "seek", (start + offset,))?;
+
+        self.buffer = None;
+        self.offset = 0;
+        self.bytes_read = offset;
+        self.finished = false;
+
+        Ok(())
+    }
 }
 
 /// A data source where data is obtained from a `PyObject`
@@ -130,6 +195,24 @@ impl InBufferSource for BufferSource {
     fn finished(&self) -> bool {
         self.offset >= self.buffer.len_bytes()
     }
+
+    fn tell(&self) -> usize {
+        self.offset
+    }
+
+    fn seekable(&self, _py: Python) -> PyResult<bool> {
+        Ok(true)
+    }
+
+    fn start_position(&self, _py: Python) -> PyResult<usize> {
+        Ok(0)
+    }
+
+    fn seek_to(&mut self, _py: Python, offset: usize) -> PyResult<()> {
+        self.offset = min(offset, self.buffer.len_bytes());
+
+        Ok(())
+    }
 }
 
 pub(crate) fn make_in_buffer_source(
@@ -144,6 +227,7 @@ pub(crate) fn make_in_buffer_source(
             read_size,
             finished: false,
             offset: 0,
+            bytes_read: 0,
         }))
     } else {
         let buffer = PyBuffer::get(source).map_err(|_| {
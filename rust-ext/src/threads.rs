@@ -0,0 +1,154 @@
+// Copyright (c) 2021-present, Gregory Szorc
+// All rights reserved.
+//
+// This software may be modified and distributed under the terms
+// of the BSD license. See the LICENSE file for details.
+
+use {
+    pyo3::{exceptions::PyValueError, prelude::*, types::PyString},
+    std::{
+        ops::Range,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    },
+};
+
+/// Minimum amount of input each thread should receive under `auto`.
+const AUTO_MIN_BYTES_PER_THREAD: usize = 512 * 1024;
+
+/// Minimum number of items each thread should receive under `auto`.
+const AUTO_MIN_ITEMS_PER_THREAD: usize = 32;
+
+/// Number of batches to split work into per thread.
+///
+/// Threads claim batches as they become idle, so threads given cheap
+/// batches pick up work left behind by threads given expensive ones.
+const BATCHES_PER_THREAD: usize = 8;
+
+/// How many threads a batch operation should use.
+///
+/// Work is distributed on rayon's global thread pool, which is created on
+/// first use and is sized to the number of logical CPUs. So the thread count
+/// controls how many contexts are created and how many pool threads work
+/// concurrently, not how many threads are spawned.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ThreadPolicy {
+    /// Use a fixed number of threads.
+    Count(usize),
+    /// Choose a thread count from the size of the input.
+    Auto,
+}
+
+impl<'a> FromPyObject<'a> for ThreadPolicy {
+    fn extract(ob: &'a PyAny) -> PyResult<Self> {
+        if let Ok(value) = ob.downcast::<PyString>() {
+            return if value.to_str()? == "auto" {
+                Ok(Self::Auto)
+            } else {
+                Err(PyValueError::new_err(
+                    "threads must be an integer or 'auto'",
+                ))
+            };
+        }
+
+        let threads: isize = ob.extract()?;
+
+        Ok(Self::Count(if threads < 0 {
+            num_cpus::get()
+        } else if threads < 2 {
+            1
+        } else {
+            threads as _
+        }))
+    }
+}
+
+impl ThreadPolicy {
+    /// Resolve the number of threads to use for `count` items totaling
+    /// `total_size` bytes.
+    ///
+    /// The result is at least 1 and no more than `count`.
+    pub fn thread_count(&self, count: usize, total_size: usize) -> usize {
+        let threads = match self {
+            Self::Count(threads) => *threads,
+            Self::Auto => std::cmp::min(
+                num_cpus::get(),
+                std::cmp::min(
+                    total_size / AUTO_MIN_BYTES_PER_THREAD,
+                    count / AUTO_MIN_ITEMS_PER_THREAD,
+                ),
+            ),
+        };
+
+        std::cmp::max(1, std::cmp::min(threads, count))
+    }
+}
+
+/// Split items of the given sizes into contiguous batches for `thread_count`
+/// threads.
+///
+/// Batches hold similar amounts of data. Items larger than that form their
+/// own batch.
+pub(crate) fn split_batches(sizes: &[usize], thread_count: usize) -> Vec<Range<usize>> {
+    let batch_count = thread_count * BATCHES_PER_THREAD;
+    let target_size = std::cmp::max(1, sizes.iter().sum::<usize>() / batch_count);
+    let max_items = std::cmp::max(1, sizes.len().div_ceil(batch_count));
+
+    let mut batches = vec![];
+    let mut start = 0;
+    let mut batch_size = 0;
+
+    for (i, size) in sizes.iter().enumerate() {
+        batch_size += size;
+
+        if batch_size >= target_size || i + 1 - start >= max_items {
+            batches.push(start..i + 1);
+            start = i + 1;
+            batch_size = 0;
+        }
+    }
+
+    if start < sizes.len() {
+        batches.push(start..sizes.len());
+    }
+
+    batches
+}
+
+/// Process `batches` on the global thread pool with one thread per context.
+///
+/// Each thread uses its context for every batch it claims. Results are
+/// returned in batch order.
+pub(crate) fn process_batches<C: Sync, R: Send>(
+    contexts: &[C],
+    batches: &[Range<usize>],
+    f: impl Fn(&C, Range<usize>) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(batches.len()));
+
+    let (next, results_ref, f) = (&next, &results, &f);
+
+    rayon::scope(|scope| {
+        for context in contexts.iter().take(batches.len()) {
+            scope.spawn(move |_| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+
+                let batch = match batches.get(index) {
+                    Some(batch) => batch.clone(),
+                    None => break,
+                };
+
+                let result = f(context, batch);
+                results_ref.lock().unwrap().push((index, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_unstable_by_key(|(index, _)| *index);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
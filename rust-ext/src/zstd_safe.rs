@@ -4,11 +4,69 @@
 // This software may be modified and distributed under the terms
 // of the BSD license. See the LICENSE file for details.
 
-use {crate::compression_parameters::CCtxParams, std::marker::PhantomData};
+use {
+    crate::{
+        compression_parameters::CCtxParams,
+        memory::{custom_mem, MemoryAccounting, ALLOCATION_ERROR},
+    },
+    std::{
+        fmt,
+        marker::PhantomData,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// Values of zstd error codes used to classify errors. These are stable.
+const ERROR_MEMORY_ALLOCATION: usize = 64;
+const ERROR_SRC_SIZE_WRONG: usize = 72;
+
+/// Why a zstd operation failed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZstdFailure {
+    /// Memory couldn't be allocated, possibly due to a `MemoryTracker` limit.
+    Alloc,
+    /// Decompressed output exceeded the configured limit.
+    OutputLimit,
+    /// Any other error.
+    Zstd(&'static str),
+}
+
+impl ZstdFailure {
+    /// Describe an error code returned by a zstd function.
+    pub fn from_code(zresult: usize) -> Self {
+        // Errors are returned as negated error codes.
+        if zresult == ERROR_MEMORY_ALLOCATION.wrapping_neg() {
+            Self::Alloc
+        } else {
+            Self::Zstd(zstd_safe::get_error_name(zresult))
+        }
+    }
+}
+
+impl From<&'static str> for ZstdFailure {
+    fn from(msg: &'static str) -> Self {
+        Self::Zstd(msg)
+    }
+}
+
+impl fmt::Display for ZstdFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Alloc => ALLOCATION_ERROR,
+            Self::OutputLimit => "decompressed data exceeds max_output_size",
+            Self::Zstd(msg) => msg,
+        })
+    }
+}
 
 /// Safe wrapper for ZSTD_CDict instances.
 pub struct CDict<'a> {
     ptr: *mut zstd_sys::ZSTD_CDict,
+    /// Accounting for memory allocated by this instance.
+    _accounting: Option<Arc<MemoryAccounting>>,
     _phantom: PhantomData<&'a ()>,
 }
 
@@ -18,7 +76,9 @@ impl<'a> CDict<'a> {
         data: &[u8],
         content_type: zstd_sys::ZSTD_dictContentType_e,
         params: zstd_sys::ZSTD_compressionParameters,
-    ) -> Result<Self, &'static str> {
+        accounting: Option<Arc<MemoryAccounting>>,
+    ) -> Result<Self, ZstdFailure> {
+        let failures = failed_allocation_count(&accounting);
         let ptr = unsafe {
             zstd_sys::ZSTD_createCDict_advanced(
                 data.as_ptr() as *const _,
@@ -26,18 +86,20 @@ impl<'a> CDict<'a> {
                 zstd_sys::ZSTD_dictLoadMethod_e::ZSTD_dlm_byRef,
                 content_type,
                 params,
-                zstd_sys::ZSTD_customMem {
-                    customAlloc: None,
-                    customFree: None,
-                    opaque: std::ptr::null_mut(),
-                },
+                custom_mem(&accounting),
             )
         };
         if ptr.is_null() {
-            Err("unable to precompute dictionary")
+            // Dictionary loading can fail for reasons other than allocation.
+            if failed_allocation_count(&accounting) != failures {
+                Err(ZstdFailure::Alloc)
+            } else {
+                Err(ZstdFailure::Zstd("unable to precompute dictionary"))
+            }
         } else {
             Ok(Self {
                 ptr,
+                _accounting: accounting,
                 _phantom: PhantomData,
             })
         }
@@ -59,6 +121,8 @@ unsafe impl<'a> Sync for CDict<'a> {}
 /// Safe wrapper for ZSTD_DDict instances.
 pub struct DDict<'a> {
     ptr: *mut zstd_sys::ZSTD_DDict,
+    /// Accounting for memory allocated by this instance.
+    _accounting: Option<Arc<MemoryAccounting>>,
     _phantom: PhantomData<&'a ()>,
 }
 
@@ -78,37 +142,62 @@ impl<'a> DDict<'a> {
     pub fn from_data(
         data: &[u8],
         content_type: zstd_sys::ZSTD_dictContentType_e,
-    ) -> Result<Self, &'static str> {
+        accounting: Option<Arc<MemoryAccounting>>,
+    ) -> Result<Self, ZstdFailure> {
+        let failures = failed_allocation_count(&accounting);
         let ptr = unsafe {
             zstd_sys::ZSTD_createDDict_advanced(
                 data.as_ptr() as *const _,
                 data.len(),
                 zstd_sys::ZSTD_dictLoadMethod_e::ZSTD_dlm_byRef,
                 content_type,
-                zstd_sys::ZSTD_customMem {
-                    customAlloc: None,
-                    customFree: None,
-                    opaque: std::ptr::null_mut(),
-                },
+                custom_mem(&accounting),
             )
         };
         if ptr.is_null() {
-            Err("could not create compression dict")
+            // Dictionary loading can fail for reasons other than allocation.
+            if failed_allocation_count(&accounting) != failures {
+                Err(ZstdFailure::Alloc)
+            } else {
+                Err(ZstdFailure::Zstd("could not create compression dict"))
+            }
         } else {
             Ok(Self {
                 ptr,
+                _accounting: accounting,
                 _phantom: PhantomData,
             })
         }
     }
 }
 
-pub struct CCtx<'a>(*mut zstd_sys::ZSTD_CCtx, PhantomData<&'a ()>);
+fn failed_allocation_count(accounting: &Option<Arc<MemoryAccounting>>) -> usize {
+    accounting
+        .as_ref()
+        .map_or(0, |accounting| accounting.failed_allocation_count())
+}
+
+/// Allocate zeroed memory suitably aligned for a static zstd context.
+fn static_workspace(size: usize) -> Vec<u64> {
+    vec![0; size.div_ceil(std::mem::size_of::<u64>())]
+}
+
+pub struct CCtx<'a> {
+    ptr: *mut zstd_sys::ZSTD_CCtx,
+    /// Accounting for memory allocated by this instance.
+    _accounting: Option<Arc<MemoryAccounting>>,
+    /// Memory holding a static context. The context can't allocate more.
+    workspace: Option<Vec<u64>>,
+    _phantom: PhantomData<&'a ()>,
+}
 
 impl<'a> Drop for CCtx<'a> {
     fn drop(&mut self) {
-        unsafe {
-            zstd_sys::ZSTD_freeCCtx(self.0);
+        // Static contexts are freed with their workspace.
+        if self.workspace.is_none() {
+            unsafe {
+                zstd_sys::ZSTD_freeCCtx(self.ptr);
+            }
         }
     }
 }
@@ -117,22 +206,53 @@ unsafe impl<'a> Send for CCtx<'a> {}
 unsafe impl<'a> Sync for CCtx<'a> {}
 
 impl<'a> CCtx<'a> {
-    pub fn new() -> Result<Self, &'static str> {
-        let cctx = unsafe { zstd_sys::ZSTD_createCCtx() };
+    pub fn new(accounting: Option<Arc<MemoryAccounting>>) -> Result<Self, ZstdFailure> {
+        let cctx = unsafe { zstd_sys::ZSTD_createCCtx_advanced(custom_mem(&accounting)) };
+        if cctx.is_null() {
+            return Err(ZstdFailure::Zstd("could not allocate ZSTD_CCtx instance"));
+        }
+
+        Ok(Self {
+            ptr: cctx,
+            _accounting: accounting,
+            workspace: None,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Create a context confined to a workspace of `size` bytes.
+    pub fn new_static(size: usize) -> Result<Self, ZstdFailure> {
+        let mut workspace = static_workspace(size);
+
+        let cctx = unsafe {
+            zstd_sys::ZSTD_initStaticCCtx(
+                workspace.as_mut_ptr() as *mut _,
+                workspace.len() * std::mem::size_of::<u64>(),
+            )
+        };
         if cctx.is_null() {
-            return Err("could not allocate ZSTD_CCtx instance");
+            return Err(ZstdFailure::Zstd("static context size is too small"));
         }
 
-        Ok(Self(cctx, PhantomData))
+        Ok(Self {
+            ptr: cctx,
+            _accounting: None,
+            workspace: Some(workspace),
+            _phantom: PhantomData,
+        })
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.workspace.is_some()
     }
 
     pub fn cctx(&self) -> *mut zstd_sys::ZSTD_CCtx {
-        self.0
+        self.ptr
     }
 
     pub fn set_parameters(&self, params: &CCtxParams) -> Result<(), String> {
         let zresult = unsafe {
-            zstd_sys::ZSTD_CCtx_setParametersUsingCCtxParams(self.0, params.get_raw_ptr())
+            zstd_sys::ZSTD_CCtx_setParametersUsingCCtxParams(self.ptr, params.get_raw_ptr())
         };
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
             return Err(zstd_safe::get_error_name(zresult).to_string());
@@ -142,31 +262,31 @@ impl<'a> CCtx<'a> {
     }
 
     pub fn memory_size(&self) -> usize {
-        unsafe { zstd_sys::ZSTD_sizeof_CCtx(self.0 as *const _) }
+        unsafe { zstd_sys::ZSTD_sizeof_CCtx(self.ptr as *const _) }
     }
 
     pub fn reset(&self) -> usize {
         unsafe {
             zstd_sys::ZSTD_CCtx_reset(
-                self.0,
+                self.ptr,
                 zstd_sys::ZSTD_ResetDirective::ZSTD_reset_session_only,
             )
         }
     }
 
-    pub fn set_pledged_source_size(&self, size: u64) -> Result<(), &'static str> {
-        let zresult = unsafe { zstd_sys::ZSTD_CCtx_setPledgedSrcSize(self.0, size) };
+    pub fn set_pledged_source_size(&self, size: u64) -> Result<(), ZstdFailure> {
+        let zresult = unsafe { zstd_sys::ZSTD_CCtx_setPledgedSrcSize(self.ptr, size) };
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else {
             Ok(())
         }
     }
 
-    pub fn load_computed_dict<'b: 'a>(&'a self, cdict: &'b CDict) -> Result<(), &'static str> {
-        let zresult = unsafe { zstd_sys::ZSTD_CCtx_refCDict(self.0, cdict.ptr) };
+    pub fn load_computed_dict<'b: 'a>(&'a self, cdict: &'b CDict) -> Result<(), ZstdFailure> {
+        let zresult = unsafe { zstd_sys::ZSTD_CCtx_refCDict(self.ptr, cdict.ptr) };
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else {
             Ok(())
         }
@@ -176,10 +296,10 @@ impl<'a> CCtx<'a> {
         &'a self,
         data: &'b [u8],
         content_type: zstd_sys::ZSTD_dictContentType_e,
-    ) -> Result<(), &'static str> {
+    ) -> Result<(), ZstdFailure> {
         let zresult = unsafe {
             zstd_sys::ZSTD_CCtx_loadDictionary_advanced(
-                self.0,
+                self.ptr,
                 data.as_ptr() as *const _,
                 data.len(),
                 zstd_sys::ZSTD_dictLoadMethod_e::ZSTD_dlm_byRef,
@@ -187,17 +307,17 @@ impl<'a> CCtx<'a> {
             )
         };
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else {
             Ok(())
         }
     }
 
     pub fn get_frame_progression(&self) -> zstd_sys::ZSTD_frameProgression {
-        unsafe { zstd_sys::ZSTD_getFrameProgression(self.0) }
+        unsafe { zstd_sys::ZSTD_getFrameProgression(self.ptr) }
     }
 
-    pub fn compress(&self, source: &[u8]) -> Result<Vec<u8>, &'static str> {
+    pub fn compress(&self, source: &[u8]) -> Result<Vec<u8>, ZstdFailure> {
         self.reset();
 
         let dest_len = unsafe { zstd_sys::ZSTD_compressBound(source.len()) };
@@ -222,7 +342,7 @@ impl<'a> CCtx<'a> {
         // size. This means the parameters to control frame parameters are honored.
         let zresult = unsafe {
             zstd_sys::ZSTD_compressStream2(
-                self.0,
+                self.ptr,
                 &mut out_buffer as *mut _,
                 &mut in_buffer as *mut _,
                 zstd_sys::ZSTD_EndDirective::ZSTD_e_end,
@@ -230,9 +350,9 @@ impl<'a> CCtx<'a> {
         };
 
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else if zresult > 0 {
-            Err("unexpected partial frame flush")
+            Err(ZstdFailure::Zstd("unexpected partial frame flush"))
         } else {
             unsafe { dest.set_len(out_buffer.pos) }
 
@@ -249,7 +369,7 @@ impl<'a> CCtx<'a> {
         source: &'a [u8],
         end_mode: zstd_sys::ZSTD_EndDirective,
         output_size: usize,
-    ) -> Result<(Vec<u8>, &'a [u8], bool), &'static str> {
+    ) -> Result<(Vec<u8>, &'a [u8], bool), ZstdFailure> {
         let mut in_buffer = zstd_sys::ZSTD_inBuffer {
             src: source.as_ptr() as *const _,
             size: source.len() as _,
@@ -266,7 +386,7 @@ impl<'a> CCtx<'a> {
 
         let zresult = unsafe {
             zstd_sys::ZSTD_compressStream2(
-                self.0,
+                self.ptr,
                 &mut out_buffer as *mut _,
                 &mut in_buffer as *mut _,
                 end_mode,
@@ -274,7 +394,7 @@ impl<'a> CCtx<'a> {
         };
 
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            return Err(zstd_safe::get_error_name(zresult));
+            return Err(ZstdFailure::from_code(zresult));
         }
 
         unsafe {
@@ -286,15 +406,94 @@ impl<'a> CCtx<'a> {
         Ok((dest, remaining, zresult != 0))
     }
 
+    /// Compress `source` into a single frame appended to `dest`.
+    pub fn compress_append(&self, source: &[u8], dest: &mut Vec<u8>) -> Result<(), ZstdFailure> {
+        self.reset();
+
+        dest.reserve(unsafe { zstd_sys::ZSTD_compressBound(source.len()) });
+
+        let mut in_buffer = zstd_sys::ZSTD_inBuffer {
+            src: source.as_ptr() as *const _,
+            size: source.len(),
+            pos: 0,
+        };
+
+        let zresult = self.compress_into_vec(
+            dest,
+            &mut in_buffer,
+            zstd_sys::ZSTD_EndDirective::ZSTD_e_end,
+        )?;
+
+        if zresult > 0 {
+            Err(ZstdFailure::Zstd("unexpected partial frame flush"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Compress `source` into a single frame appended to `dest`.
+    ///
+    /// Input is fed to zstd in chunks of `chunk_size` bytes. Before each chunk
+    /// after the first, `stop` is consulted and compression is abandoned if it
+    /// returns true. Returns whether the frame was completed.
+    pub fn compress_append_chunked(
+        &self,
+        source: &[u8],
+        dest: &mut Vec<u8>,
+        chunk_size: usize,
+        stop: impl Fn() -> bool,
+    ) -> Result<bool, ZstdFailure> {
+        if source.len() <= chunk_size {
+            return self.compress_append(source, dest).map(|_| true);
+        }
+
+        self.reset();
+        self.set_pledged_source_size(source.len() as _)?;
+
+        dest.reserve(unsafe { zstd_sys::ZSTD_compressBound(source.len()) });
+
+        for (i, chunk) in source.chunks(chunk_size).enumerate() {
+            if i > 0 && stop() {
+                return Ok(false);
+            }
+
+            let last = (i + 1) * chunk_size >= source.len();
+            let end_mode = if last {
+                zstd_sys::ZSTD_EndDirective::ZSTD_e_end
+            } else {
+                zstd_sys::ZSTD_EndDirective::ZSTD_e_continue
+            };
+
+            let mut in_buffer = zstd_sys::ZSTD_inBuffer {
+                src: chunk.as_ptr() as *const _,
+                size: chunk.len(),
+                pos: 0,
+            };
+
+            loop {
+                let zresult = self.compress_into_vec(dest, &mut in_buffer, end_mode)?;
+
+                // The frame is complete once zstd has nothing left to flush.
+                if in_buffer.pos == in_buffer.size && (!last || zresult == 0) {
+                    break;
+                }
+
+                dest.reserve(zstd_safe::cstream_out_size());
+            }
+        }
+
+        Ok(true)
+    }
+
     pub fn compress_buffers(
         &self,
         out_buffer: &mut zstd_sys::ZSTD_outBuffer,
         in_buffer: &mut zstd_sys::ZSTD_inBuffer,
         end_mode: zstd_sys::ZSTD_EndDirective,
-    ) -> Result<usize, &'static str> {
+    ) -> Result<usize, ZstdFailure> {
         let zresult = unsafe {
             zstd_sys::ZSTD_compressStream2(
-                self.0,
+                self.ptr,
                 out_buffer as *mut _,
                 in_buffer as *mut _,
                 end_mode,
@@ -302,7 +501,7 @@ impl<'a> CCtx<'a> {
         };
 
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else {
             Ok(zresult)
         }
@@ -317,7 +516,7 @@ impl<'a> CCtx<'a> {
         dest_buffer: &mut Vec<u8>,
         in_buffer: &mut zstd_sys::ZSTD_inBuffer,
         end_mode: zstd_sys::ZSTD_EndDirective,
-    ) -> Result<usize, &'static str> {
+    ) -> Result<usize, ZstdFailure> {
         let mut out_buffer = zstd_sys::ZSTD_outBuffer {
             dst: dest_buffer.as_mut_ptr() as *mut _,
             size: dest_buffer.capacity(),
@@ -334,12 +533,97 @@ impl<'a> CCtx<'a> {
     }
 }
 
-pub struct DCtx<'a>(*mut zstd_sys::ZSTD_DCtx, PhantomData<&'a ()>);
+/// Whether `zresult` is the error zstd reports when given too little input.
+pub fn is_src_size_error(zresult: usize) -> bool {
+    // Errors are returned as negated error codes.
+    zresult == ERROR_SRC_SIZE_WRONG.wrapping_neg()
+}
+
+/// Magic numbers of legacy zstd formats, indexed by version.
+const LEGACY_MAGIC_NUMBERS: [u32; 7] = [
+    0xFD2FB51E, 0xFD2FB522, 0xFD2FB523, 0xFD2FB524, 0xFD2FB525, 0xFD2FB526, 0xFD2FB527,
+];
+
+/// Obtain the legacy format version of a frame beginning with `data`.
+///
+/// Returns `0` if the frame isn't in a legacy format, like `ZSTD_isLegacy()`.
+pub fn legacy_version(data: &[u8]) -> u32 {
+    if data.len() < 4 {
+        return 0;
+    }
+
+    let magic = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+
+    LEGACY_MAGIC_NUMBERS
+        .iter()
+        .position(|value| *value == magic)
+        .map_or(0, |index| index as u32 + 1)
+}
+
+/// Progress of checking the current frame for a legacy magic number.
+#[derive(Clone, Copy)]
+enum LegacyCheck {
+    /// The first `len` bytes of the frame have been fed to zstd.
+    Pending { magic: [u8; 4], len: usize },
+    /// The frame isn't legacy.
+    Done,
+}
+
+impl LegacyCheck {
+    const START: Self = Self::Pending {
+        magic: [0; 4],
+        len: 0,
+    };
+
+    /// Value of `Done` when packed into a `u64`.
+    const DONE: u64 = u64::MAX;
+
+    /// Pack into a `u64`, with the magic number bytes in the low 32 bits.
+    fn pack(self) -> u64 {
+        match self {
+            Self::Pending { magic, len } => (len as u64) << 32 | u32::from_le_bytes(magic) as u64,
+            Self::Done => Self::DONE,
+        }
+    }
+
+    fn unpack(value: u64) -> Self {
+        match value {
+            Self::DONE => Self::Done,
+            value => Self::Pending {
+                magic: (value as u32).to_le_bytes(),
+                len: (value >> 32) as usize,
+            },
+        }
+    }
+}
+
+pub struct DCtx<'a> {
+    ptr: *mut zstd_sys::ZSTD_DCtx,
+    /// Accounting for memory allocated by this instance.
+    accounting: Option<Arc<MemoryAccounting>>,
+    /// Memory holding a static context. The context can't allocate more.
+    workspace: Option<Vec<u64>>,
+    /// Whether `decompress_buffers()` refuses legacy frames.
+    reject_legacy: AtomicBool,
+    /// Whether frames lack magic numbers, making legacy detection impossible.
+    magicless: AtomicBool,
+    /// Packed `LegacyCheck`.
+    legacy_check: AtomicU64,
+    /// Maximum bytes `decompress_buffers()` may produce between resets.
+    /// `u64::MAX` if unlimited.
+    output_limit: AtomicU64,
+    /// Bytes produced since the last reset.
+    output_size: AtomicU64,
+    _phantom: PhantomData<&'a ()>,
+}
 
 impl<'a> Drop for DCtx<'a> {
     fn drop(&mut self) {
-        unsafe {
-            zstd_sys::ZSTD_freeDCtx(self.0);
+        // Static contexts are freed with their workspace.
+        if self.workspace.is_none() {
+            unsafe {
+                zstd_sys::ZSTD_freeDCtx(self.ptr);
+            }
         }
     }
 }
@@ -348,70 +632,172 @@ unsafe impl<'a> Send for DCtx<'a> {}
 unsafe impl<'a> Sync for DCtx<'a> {}
 
 impl<'a> DCtx<'a> {
-    pub fn new() -> Result<Self, &'static str> {
-        let dctx = unsafe { zstd_sys::ZSTD_createDCtx() };
+    pub fn new(accounting: Option<Arc<MemoryAccounting>>) -> Result<Self, ZstdFailure> {
+        let dctx = unsafe { zstd_sys::ZSTD_createDCtx_advanced(custom_mem(&accounting)) };
         if dctx.is_null() {
-            return Err("could not allocate ZSTD_DCtx instance");
+            return Err(ZstdFailure::Zstd("could not allocate ZSTD_DCtx instance"));
         }
 
-        Ok(Self(dctx, PhantomData))
+        Ok(Self {
+            ptr: dctx,
+            accounting,
+            workspace: None,
+            reject_legacy: AtomicBool::new(false),
+            magicless: AtomicBool::new(false),
+            legacy_check: AtomicU64::new(LegacyCheck::START.pack()),
+            output_limit: AtomicU64::new(u64::MAX),
+            output_size: AtomicU64::new(0),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Create a context confined to a workspace of `size` bytes.
+    pub fn new_static(size: usize) -> Result<Self, ZstdFailure> {
+        let mut workspace = static_workspace(size);
+
+        let dctx = unsafe {
+            zstd_sys::ZSTD_initStaticDCtx(
+                workspace.as_mut_ptr() as *mut _,
+                workspace.len() * std::mem::size_of::<u64>(),
+            )
+        };
+        if dctx.is_null() {
+            return Err(ZstdFailure::Zstd("static context size is too small"));
+        }
+
+        Ok(Self {
+            ptr: dctx,
+            accounting: None,
+            workspace: Some(workspace),
+            reject_legacy: AtomicBool::new(false),
+            magicless: AtomicBool::new(false),
+            legacy_check: AtomicU64::new(LegacyCheck::START.pack()),
+            output_limit: AtomicU64::new(u64::MAX),
+            output_size: AtomicU64::new(0),
+            _phantom: PhantomData,
+        })
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.workspace.is_some()
     }
 
     /// Attempt to create a copy of this instance.
-    pub fn try_clone(&self) -> Result<Self, &'static str> {
-        let dctx = Self::new()?;
+    ///
+    /// Static contexts can't be copied.
+    pub fn try_clone(&self) -> Result<Self, ZstdFailure> {
+        if self.is_static() {
+            return Err(ZstdFailure::Zstd("static contexts cannot be copied"));
+        }
+
+        let dctx = Self::new(self.accounting.clone())?;
 
         unsafe {
-            zstd_sys::ZSTD_copyDCtx(dctx.0, self.0);
+            zstd_sys::ZSTD_copyDCtx(dctx.ptr, self.ptr);
         }
 
+        dctx.set_reject_legacy(self.reject_legacy.load(Ordering::Relaxed));
+        dctx.magicless
+            .store(self.magicless.load(Ordering::Relaxed), Ordering::Relaxed);
+        dctx.set_output_limit(self.output_limit());
+
         Ok(dctx)
     }
 
     pub fn dctx(&self) -> *mut zstd_sys::ZSTD_DCtx {
-        self.0
+        self.ptr
     }
 
     pub fn memory_size(&self) -> usize {
-        unsafe { zstd_sys::ZSTD_sizeof_DCtx(self.0) }
+        unsafe { zstd_sys::ZSTD_sizeof_DCtx(self.ptr) }
+    }
+
+    /// Set whether to refuse frames in legacy zstd formats.
+    pub fn set_reject_legacy(&self, reject: bool) {
+        self.reject_legacy.store(reject, Ordering::Relaxed);
+    }
+
+    /// Set the maximum bytes decompression may produce between resets.
+    pub fn set_output_limit(&self, limit: Option<u64>) {
+        self.output_limit
+            .store(limit.unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    pub fn output_limit(&self) -> Option<u64> {
+        match self.output_limit.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            limit => Some(limit),
+        }
+    }
+
+    /// Ensure producing `size` bytes wouldn't exceed the output limit.
+    ///
+    /// Used to reject declared content sizes before allocating for them.
+    pub fn check_output_size(&self, size: u64) -> Result<(), ZstdFailure> {
+        if size > self.output_limit.load(Ordering::Relaxed) {
+            Err(ZstdFailure::OutputLimit)
+        } else {
+            Ok(())
+        }
     }
 
-    pub fn reset(&self) -> Result<(), &'static str> {
+    /// Start counting output towards the limit from zero.
+    pub fn reset_output_size(&self) {
+        self.output_size.store(0, Ordering::Relaxed);
+    }
+
+    fn legacy_check(&self) -> LegacyCheck {
+        LegacyCheck::unpack(self.legacy_check.load(Ordering::Relaxed))
+    }
+
+    fn set_legacy_check(&self, check: LegacyCheck) {
+        self.legacy_check.store(check.pack(), Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) -> Result<(), ZstdFailure> {
+        self.set_legacy_check(LegacyCheck::START);
+        self.reset_output_size();
+
         let zresult = unsafe {
             zstd_sys::ZSTD_DCtx_reset(
-                self.0,
+                self.ptr,
                 zstd_sys::ZSTD_ResetDirective::ZSTD_reset_session_only,
             )
         };
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else {
             Ok(())
         }
     }
 
-    pub fn set_max_window_size(&self, size: usize) -> Result<(), &'static str> {
-        let zresult = unsafe { zstd_sys::ZSTD_DCtx_setMaxWindowSize(self.0, size) };
+    pub fn set_max_window_size(&self, size: usize) -> Result<(), ZstdFailure> {
+        let zresult = unsafe { zstd_sys::ZSTD_DCtx_setMaxWindowSize(self.ptr, size) };
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else {
             Ok(())
         }
     }
 
-    pub fn set_format(&self, format: zstd_sys::ZSTD_format_e) -> Result<(), &'static str> {
-        let zresult = unsafe { zstd_sys::ZSTD_DCtx_setFormat(self.0, format) };
+    pub fn set_format(&self, format: zstd_sys::ZSTD_format_e) -> Result<(), ZstdFailure> {
+        let zresult = unsafe { zstd_sys::ZSTD_DCtx_setFormat(self.ptr, format) };
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else {
+            self.magicless.store(
+                format == zstd_sys::ZSTD_format_e::ZSTD_f_zstd1_magicless,
+                Ordering::Relaxed,
+            );
+
             Ok(())
         }
     }
 
-    pub fn load_prepared_dict<'b: 'a>(&'a self, dict: &'b DDict) -> Result<(), &'static str> {
-        let zresult = unsafe { zstd_sys::ZSTD_DCtx_refDDict(self.0, dict.ptr) };
+    pub fn load_prepared_dict<'b: 'a>(&'a self, dict: &'b DDict) -> Result<(), ZstdFailure> {
+        let zresult = unsafe { zstd_sys::ZSTD_DCtx_refDDict(self.ptr, dict.ptr) };
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
+            Err(ZstdFailure::from_code(zresult))
         } else {
             Ok(())
         }
@@ -421,23 +807,85 @@ impl<'a> DCtx<'a> {
         &self,
         out_buffer: &mut zstd_sys::ZSTD_outBuffer,
         in_buffer: &mut zstd_sys::ZSTD_inBuffer,
-    ) -> Result<usize, &'static str> {
+    ) -> Result<usize, ZstdFailure> {
+        let start = in_buffer.pos;
+        let out_start = out_buffer.pos;
+
+        if self.reject_legacy.load(Ordering::Relaxed) && !self.magicless.load(Ordering::Relaxed) {
+            self.check_legacy(in_buffer)?;
+        }
+
         let zresult = unsafe {
-            zstd_sys::ZSTD_decompressStream(self.0, out_buffer as *mut _, in_buffer as *mut _)
+            zstd_sys::ZSTD_decompressStream(self.ptr, out_buffer as *mut _, in_buffer as *mut _)
         };
 
         if unsafe { zstd_sys::ZSTD_isError(zresult) } != 0 {
-            Err(zstd_safe::get_error_name(zresult))
-        } else {
-            Ok(zresult)
+            return Err(ZstdFailure::from_code(zresult));
+        }
+
+        let produced = (out_buffer.pos - out_start) as u64;
+        let output_size = self.output_size.fetch_add(produced, Ordering::Relaxed) + produced;
+        self.check_output_size(output_size)?;
+
+        if zresult == 0 {
+            // The next input begins a new frame.
+            self.set_legacy_check(LegacyCheck::START);
+        } else if let LegacyCheck::Pending { mut magic, len } = self.legacy_check() {
+            // Remember magic number bytes zstd consumed.
+            let consumed = std::cmp::min(in_buffer.pos - start, magic.len() - len);
+            if consumed == 0 {
+                return Ok(zresult);
+            }
+
+            let input = unsafe {
+                std::slice::from_raw_parts((in_buffer.src as *const u8).add(start), consumed)
+            };
+            magic[len..len + consumed].copy_from_slice(input);
+
+            self.set_legacy_check(LegacyCheck::Pending {
+                magic,
+                len: len + consumed,
+            });
         }
+
+        Ok(zresult)
+    }
+
+    /// Ensure the current frame isn't in a legacy format.
+    ///
+    /// The check happens once the 4 byte magic number is available, before
+    /// zstd has seen all of it and can start decoding.
+    fn check_legacy(&self, in_buffer: &zstd_sys::ZSTD_inBuffer) -> Result<(), ZstdFailure> {
+        if let LegacyCheck::Pending { mut magic, len } = self.legacy_check() {
+            let available = std::cmp::min(in_buffer.size - in_buffer.pos, magic.len() - len);
+
+            if available == 0 || len + available < magic.len() {
+                return Ok(());
+            }
+
+            let input = unsafe {
+                std::slice::from_raw_parts(
+                    (in_buffer.src as *const u8).add(in_buffer.pos),
+                    available,
+                )
+            };
+            magic[len..].copy_from_slice(input);
+
+            if legacy_version(&magic) != 0 {
+                return Err(ZstdFailure::Zstd("legacy zstd frames are not allowed"));
+            }
+
+            self.set_legacy_check(LegacyCheck::Done);
+        }
+
+        Ok(())
     }
 
     pub fn decompress_into_vec(
         &self,
         dest_buffer: &mut Vec<u8>,
         in_buffer: &mut zstd_sys::ZSTD_inBuffer,
-    ) -> Result<usize, &'static str> {
+    ) -> Result<usize, ZstdFailure> {
         let mut out_buffer = zstd_sys::ZSTD_outBuffer {
             dst: dest_buffer.as_mut_ptr() as *mut _,
             size: dest_buffer.capacity(),
@@ -459,7 +907,7 @@ pub fn train_dictionary_fastcover(
     samples_buffer: &[u8],
     samples_sizes: &[usize],
     params: &zstd_sys::ZDICT_fastCover_params_t,
-) -> Result<(), &'static str> {
+) -> Result<(), ZstdFailure> {
     let zresult = unsafe {
         zstd_sys::ZDICT_optimizeTrainFromBuffer_fastCover(
             dict_buffer.as_mut_ptr() as *mut _,
@@ -471,7 +919,7 @@ pub fn train_dictionary_fastcover(
         )
     };
     if unsafe { zstd_sys::ZDICT_isError(zresult) } != 0 {
-        Err(zstd_safe::get_error_name(zresult))
+        Err(ZstdFailure::from_code(zresult))
     } else {
         unsafe {
             dict_buffer.set_len(zresult);
@@ -94,8 +94,11 @@ if C_BACKEND:
         )
     )
 
+entry_points = {}
+
 if RUST_BACKEND:
     extensions.append(setup_zstd.get_rust_extension())
+    entry_points["console_scripts"] = ["zstandard = zstandard.__main__:main"]
 
 if CFFI_BACKEND and cffi:
     import make_cffi
@@ -142,6 +145,7 @@ setup(
     packages=["zstandard"],
     package_data={"zstandard": ["__init__.pyi", "py.typed"]},
     ext_modules=extensions,
+    entry_points=entry_points,
     cmdclass={"build_ext": setup_zstd.RustBuildExt},
     test_suite="tests",
     install_requires=[
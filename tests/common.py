@@ -123,6 +123,26 @@ def generate_samples():
     return samples
 
 
+def split_pzstd_frames(data):
+    """Split pzstd formatted data into its zstd frames.
+
+    Asserts every frame is preceded by a skippable frame holding its size.
+    """
+    frames = []
+    offset = 0
+
+    while offset < len(data):
+        assert data[offset : offset + 8] == b"\x50\x2a\x4d\x18\x04\x00\x00\x00"
+        size = int.from_bytes(data[offset + 8 : offset + 12], "little")
+        offset += 12
+        frames.append(data[offset : offset + size])
+        offset += size
+
+    assert offset == len(data)
+
+    return frames
+
+
 if hypothesis:
     default_settings = hypothesis.settings(deadline=10000)
     hypothesis.settings.register_profile("default", default_settings)
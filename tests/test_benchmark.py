@@ -0,0 +1,161 @@
+import unittest
+
+import zstandard as zstd
+
+from .common import (
+    generate_samples,
+)
+
+
+def benchmark(data, **kwargs):
+    kwargs.setdefault("min_time", 0)
+    kwargs.setdefault("max_iterations", 2)
+
+    return zstd.benchmark(data, **kwargs)
+
+
+@unittest.skipUnless(
+    "benchmark" in zstd.backend_features, "benchmark feature not available"
+)
+class TestBenchmark(unittest.TestCase):
+    def test_invalid_inputs(self):
+        with self.assertRaisesRegex(
+            TypeError, "data must be a bytes-like object or an iterable"
+        ):
+            zstd.benchmark(42)
+
+        with self.assertRaisesRegex(
+            TypeError, "item 1 not a bytes like object"
+        ):
+            zstd.benchmark([b"foo", u"foo"])
+
+        with self.assertRaisesRegex(ValueError, "data must not be empty"):
+            zstd.benchmark([])
+
+        with self.assertRaisesRegex(ValueError, "level must be less than 23"):
+            zstd.benchmark(b"foo", levels=[23])
+
+        for min_time in (-1, float("nan"), float("inf")):
+            with self.assertRaisesRegex(
+                ValueError, "min_time must be a non-negative number"
+            ):
+                zstd.benchmark(b"foo", min_time=min_time)
+
+        with self.assertRaisesRegex(
+            ValueError, "max_iterations must be positive"
+        ):
+            zstd.benchmark(b"foo", max_iterations=0)
+
+    def test_default_level(self):
+        data = b"foobar" * 10000
+
+        results = benchmark(data)
+        self.assertEqual(len(results), 1)
+
+        result = results[0]
+        self.assertEqual(result.level, 3)
+        self.assertIsNone(result.compression_params)
+        self.assertEqual(result.size, len(data))
+        self.assertEqual(
+            result.compressed_size,
+            len(zstd.ZstdCompressor(level=3).compress(data)),
+        )
+        self.assertAlmostEqual(result.ratio, len(data) / result.compressed_size)
+        self.assertEqual(result.compress_iterations, 2)
+        self.assertEqual(result.decompress_iterations, 2)
+        self.assertGreater(result.compress_time, 0)
+        self.assertGreater(result.decompress_time, 0)
+        self.assertAlmostEqual(
+            result.compress_speed,
+            len(data) / 1000000 / result.compress_time,
+        )
+        self.assertAlmostEqual(
+            result.decompress_speed,
+            len(data) / 1000000 / result.decompress_time,
+        )
+        self.assertGreater(result.compressor_memory_size, 0)
+        self.assertGreater(result.decompressor_memory_size, 0)
+
+    def test_levels(self):
+        data = b"".join(generate_samples())
+
+        results = benchmark(data, levels=[1, 5, -3])
+        self.assertEqual([r.level for r in results], [1, 5, -3])
+
+        for result in results:
+            cctx = zstd.ZstdCompressor(level=result.level)
+            self.assertEqual(result.compressed_size, len(cctx.compress(data)))
+
+    def test_params(self):
+        data = b"foobar" * 10000
+        params = zstd.ZstdCompressionParameters.from_level(
+            5, window_log=15, write_checksum=True
+        )
+        magicless = zstd.ZstdCompressionParameters.from_level(
+            1, format=zstd.FORMAT_ZSTD1_MAGICLESS
+        )
+
+        results = benchmark(data, levels=[1], params=[params, magicless])
+        self.assertEqual(len(results), 3)
+
+        self.assertEqual(results[0].level, 1)
+        self.assertIsNone(results[1].level)
+        self.assertIs(results[1].compression_params, params)
+        self.assertIs(results[2].compression_params, magicless)
+
+        cctx = zstd.ZstdCompressor(compression_params=params)
+        self.assertEqual(results[1].compressed_size, len(cctx.compress(data)))
+        self.assertEqual(
+            results[2].compressed_size, results[0].compressed_size - 4
+        )
+
+        # Only params are benchmarked when levels isn't specified.
+        self.assertEqual(len(benchmark(data, params=[params])), 1)
+
+    def test_samples(self):
+        samples = generate_samples()
+
+        result = benchmark(samples)[0]
+        self.assertEqual(result.size, sum(len(s) for s in samples))
+
+        cctx = zstd.ZstdCompressor(level=3)
+        self.assertEqual(
+            result.compressed_size,
+            sum(len(cctx.compress(s)) for s in samples),
+        )
+
+        result = benchmark([b"", b"foo"])[0]
+        self.assertEqual(result.size, 3)
+
+    def test_dictionary(self):
+        samples = []
+        for i in range(128):
+            samples.append(b"foo" * 64)
+            samples.append(b"bar" * 64)
+            samples.append(b"foobar" * 64)
+
+        d = zstd.train_dictionary(8192, samples)
+
+        with_dict = benchmark(samples, dict_data=d)[0]
+
+        cctx = zstd.ZstdCompressor(dict_data=d)
+        self.assertEqual(
+            with_dict.compressed_size,
+            sum(len(cctx.compress(s)) for s in samples),
+        )
+
+    def test_threads(self):
+        data = b"foobar" * 100000
+
+        result = benchmark(data, threads=2)[0]
+        self.assertEqual(result.size, len(data))
+        self.assertGreater(
+            result.compressor_memory_size,
+            benchmark(data)[0].compressor_memory_size,
+        )
+
+    def test_min_time(self):
+        result = zstd.benchmark(b"foo", min_time=0.05)[0]
+
+        self.assertGreaterEqual(result.compress_iterations, 4)
+        self.assertGreaterEqual(result.decompress_iterations, 4)
@@ -1,4 +1,7 @@
+import array
+import os
 import struct
+import tempfile
 import unittest
 
 import zstandard as zstd
@@ -29,6 +32,12 @@ class TestBufferWithSegments(unittest.TestCase):
         ):
             zstd.BufferWithSegments(b"foo", ss.pack(0, 4))
 
+        # The segment end must not wrap around.
+        with self.assertRaisesRegex(
+            ValueError, "offset within segments array references memory"
+        ):
+            zstd.BufferWithSegments(b"foo", ss.pack(2**64 - 1, 2))
+
     def test_invalid_getitem(self):
         b = zstd.BufferWithSegments(b"foo", ss.pack(0, 3))
 
@@ -65,6 +74,180 @@ class TestBufferWithSegments(unittest.TestCase):
         self.assertEqual(b[2].tobytes(), b"fooxy")
 
 
+
+@unittest.skipUnless(
+    "buffer_offsets" in zstd.backend_features,
+    "buffer_offsets feature not available",
+)
+class TestBufferWithSegments_offsets(unittest.TestCase):
+    def test_from_offsets(self):
+        for typecode in ("i", "I", "q", "Q"):
+            b = zstd.BufferWithSegments.from_offsets(
+                b"foobarbaz", array.array(typecode, [0, 3, 3, 9])
+            )
+
+            self.assertEqual(len(b), 3)
+            self.assertEqual(b.size, 9)
+            self.assertEqual(b[0].tobytes(), b"foo")
+            self.assertEqual(b[1].tobytes(), b"")
+            self.assertEqual(b[2].tobytes(), b"barbaz")
+            self.assertEqual(b[2].offset, 3)
+
+    def test_from_offsets_empty(self):
+        b = zstd.BufferWithSegments.from_offsets(b"", array.array("i", [0]))
+        self.assertEqual(len(b), 0)
+
+    def test_from_offsets_invalid(self):
+        with self.assertRaisesRegex(
+            TypeError, "offsets must be an array of 32 or 64-bit integers"
+        ):
+            zstd.BufferWithSegments.from_offsets(b"foo", b"\x00\x03")
+
+        with self.assertRaisesRegex(
+            ValueError, "offsets must be non-decreasing"
+        ):
+            zstd.BufferWithSegments.from_offsets(
+                b"foo", array.array("i", [0, 2, 1])
+            )
+
+        with self.assertRaisesRegex(ValueError, "offsets must not be negative"):
+            zstd.BufferWithSegments.from_offsets(
+                b"foo", array.array("q", [-1, 1])
+            )
+
+        with self.assertRaisesRegex(
+            ValueError, "offsets reference memory outside data buffer"
+        ):
+            zstd.BufferWithSegments.from_offsets(
+                b"foo", array.array("i", [0, 4])
+            )
+
+    def test_offsets(self):
+        b = zstd.BufferWithSegments(
+            b"xfoobarbaz", ss.pack(1, 3) + ss.pack(4, 0) + ss.pack(4, 6)
+        )
+
+        offsets = array.array("i")
+        offsets.frombytes(b.offsets())
+        self.assertEqual(list(offsets), [1, 4, 4, 10])
+
+        offsets = array.array("q")
+        offsets.frombytes(b.offsets(large=True))
+        self.assertEqual(list(offsets), [1, 4, 4, 10])
+
+    def test_offsets_round_trip(self):
+        offsets = array.array("i", [0, 3, 6, 9])
+        b = zstd.BufferWithSegments.from_offsets(b"foobarbaz", offsets)
+
+        self.assertEqual(b.offsets(), offsets.tobytes())
+
+    def test_offsets_not_adjacent(self):
+        b = zstd.BufferWithSegments(b"foobarbaz", ss.pack(0, 3) + ss.pack(6, 3))
+
+        with self.assertRaisesRegex(
+            ValueError, "segment 1 is not adjacent to previous segment"
+        ):
+            b.offsets()
+
+    def test_compressed_column(self):
+        cctx = zstd.ZstdCompressor()
+        dctx = zstd.ZstdDecompressor()
+
+        original = [b"foo" * 10, b"bar" * 20, b"baz" * 30]
+        source = zstd.BufferWithSegments.from_offsets(
+            b"".join(original), array.array("i", [0, 30, 90, 180])
+        )
+
+        compressed = cctx.multi_compress_to_buffer(source)
+        frames = [compressed[i].tobytes() for i in range(len(compressed))]
+
+        offsets = array.array("q", [0])
+        for frame in frames:
+            offsets.append(offsets[-1] + len(frame))
+
+        column = zstd.BufferWithSegments.from_offsets(b"".join(frames), offsets)
+        self.assertEqual(column.offsets(large=True), offsets.tobytes())
+
+        result = dctx.multi_decompress_to_buffer(column)
+        self.assertEqual(
+            [result[i].tobytes() for i in range(len(result))], original
+        )
+
+
+@unittest.skipUnless(
+    "buffer_from_file" in zstd.backend_features,
+    "buffer_from_file feature not available",
+)
+class TestBufferWithSegments_from_file(unittest.TestCase):
+    def setUp(self):
+        self.tempdir = tempfile.TemporaryDirectory()
+        self.path = os.path.join(self.tempdir.name, "data")
+
+    def tearDown(self):
+        self.tempdir.cleanup()
+
+    def test_from_file(self):
+        with open(self.path, "wb") as fh:
+            fh.write(b"foobarbaz")
+
+        b = zstd.BufferWithSegments.from_file(
+            self.path, ss.pack(0, 3) + ss.pack(6, 3)
+        )
+
+        self.assertEqual(len(b), 2)
+        self.assertEqual(b.size, 9)
+        self.assertEqual(b[0].tobytes(), b"foo")
+        self.assertEqual(b[1].tobytes(), b"baz")
+        self.assertEqual(b.tobytes(), b"foobarbaz")
+
+    def test_empty_file(self):
+        open(self.path, "wb").close()
+
+        b = zstd.BufferWithSegments.from_file(self.path, b"")
+        self.assertEqual(len(b), 0)
+        self.assertEqual(b.size, 0)
+
+    def test_invalid(self):
+        with open(self.path, "wb") as fh:
+            fh.write(b"foo")
+
+        with self.assertRaisesRegex(
+            ValueError, "offset within segments array references memory"
+        ):
+            zstd.BufferWithSegments.from_file(self.path, ss.pack(0, 4))
+
+        with self.assertRaisesRegex(
+            ValueError, "offset within segments array references memory"
+        ):
+            zstd.BufferWithSegments.from_file(
+                self.path, ss.pack(2**64 - 1, 2)
+            )
+
+        with self.assertRaises(FileNotFoundError):
+            zstd.BufferWithSegments.from_file(
+                os.path.join(self.tempdir.name, "missing"), ss.pack(0, 0)
+            )
+
+    def test_decompress(self):
+        cctx = zstd.ZstdCompressor()
+        original = [b"%d" % i * 128 for i in range(100)]
+
+        segments = []
+        with open(self.path, "wb") as fh:
+            for chunk in original:
+                frame = cctx.compress(chunk)
+                segments.append(ss.pack(fh.tell(), len(frame)))
+                fh.write(frame)
+
+        b = zstd.BufferWithSegments.from_file(self.path, b"".join(segments))
+
+        dctx = zstd.ZstdDecompressor()
+        result = dctx.multi_decompress_to_buffer(b, threads=2)
+
+        self.assertEqual(
+            [result[i].tobytes() for i in range(len(result))], original
+        )
+
 @unittest.skipUnless(
     "buffer_types" in zstd.backend_features, "buffer types not available"
 )
@@ -128,3 +311,347 @@ class TestBufferWithSegmentsCollection(unittest.TestCase):
         self.assertEqual(c[0].tobytes(), b"foo")
         self.assertEqual(c[1].tobytes(), b"bar")
         self.assertEqual(c[2].tobytes(), b"baz")
+
+
+@unittest.skipUnless(
+    "buffer_sequence_ops" in zstd.backend_features,
+    "buffer_sequence_ops feature not available",
+)
+class TestBufferWithSegments_sequence_ops(unittest.TestCase):
+    def setUp(self):
+        self.b = zstd.BufferWithSegments(
+            b"foobarbazqux",
+            b"".join(ss.pack(i, 3) for i in range(0, 12, 3)),
+        )
+
+    def test_negative_index(self):
+        self.assertEqual(self.b[-1].tobytes(), b"qux")
+        self.assertEqual(self.b[-4].tobytes(), b"foo")
+
+        with self.assertRaisesRegex(IndexError, "offset must be non-negative"):
+            self.b[-5]
+
+        with self.assertRaises(TypeError):
+            self.b["foo"]
+
+    def test_collection_negative_index(self):
+        c = zstd.BufferWithSegmentsCollection(
+            self.b, zstd.BufferWithSegments(b"quux", ss.pack(0, 4))
+        )
+
+        self.assertEqual(c[-1].tobytes(), b"quux")
+        self.assertEqual(c[-2].tobytes(), b"qux")
+        self.assertEqual(c[-5].tobytes(), b"foo")
+        self.assertEqual(c.__getitem__(-1).tobytes(), b"quux")
+
+        with self.assertRaisesRegex(IndexError, "offset must be non-negative"):
+            c[-6]
+
+    def test_slice(self):
+        s = self.b[1:3]
+
+        self.assertIsInstance(s, zstd.BufferWithSegments)
+        self.assertEqual(len(s), 2)
+        self.assertEqual(s.size, 6)
+        self.assertEqual(s.tobytes(), b"barbaz")
+        self.assertEqual(bytes(memoryview(s)), b"barbaz")
+        self.assertEqual(s[0].offset, 0)
+        self.assertEqual(s[0].tobytes(), b"bar")
+        self.assertEqual(s[1].tobytes(), b"baz")
+
+        # Slices of slices work.
+        self.assertEqual(s[1:][0].tobytes(), b"baz")
+
+    def test_slice_step(self):
+        s = self.b[::-2]
+
+        self.assertEqual([o.tobytes() for o in s], [b"qux", b"bar"])
+        self.assertEqual(s.tobytes(), b"barbazqux")
+
+    def test_slice_empty(self):
+        s = self.b[10:]
+
+        self.assertEqual(len(s), 0)
+        self.assertEqual(s.size, 0)
+        self.assertEqual(list(s), [])
+
+    def test_slice_keeps_source_alive(self):
+        data = bytearray(b"foobar")
+        b = zstd.BufferWithSegments(data, ss.pack(0, 3) + ss.pack(3, 3))
+        s = b[1:]
+        del b
+
+        # Memory is shared with the source.
+        data[3:6] = b"BAR"
+        self.assertEqual(s[0].tobytes(), b"BAR")
+
+    def test_iter(self):
+        self.assertEqual(
+            [o.tobytes() for o in self.b], [b"foo", b"bar", b"baz", b"qux"]
+        )
+
+        it = iter(self.b)
+        self.assertIs(iter(it), it)
+        self.assertEqual(next(it).tobytes(), b"foo")
+
+
+@unittest.skipUnless(
+    "buffer_sequence_ops" in zstd.backend_features,
+    "buffer_sequence_ops feature not available",
+)
+class TestBufferWithSegmentsCollection_sequence_ops(unittest.TestCase):
+    def setUp(self):
+        self.b1 = zstd.BufferWithSegments(b"foo", ss.pack(0, 3))
+        self.b2 = zstd.BufferWithSegments(
+            b"xbarbaz", b"".join([ss.pack(1, 3), ss.pack(4, 3)])
+        )
+
+    def test_iter(self):
+        c = zstd.BufferWithSegmentsCollection(self.b1, self.b2)
+
+        self.assertEqual([o.tobytes() for o in c], [b"foo", b"bar", b"baz"])
+
+    def test_flatten(self):
+        c = zstd.BufferWithSegmentsCollection(self.b1, self.b2)
+
+        b = c.flatten()
+        self.assertIsInstance(b, zstd.BufferWithSegments)
+        self.assertEqual(len(b), 3)
+        self.assertEqual(b.size, 9)
+        self.assertEqual(b.tobytes(), b"foobarbaz")
+        self.assertEqual([o.tobytes() for o in b], [b"foo", b"bar", b"baz"])
+        self.assertEqual([o.offset for o in b], [0, 3, 6])
+
+    def test_flatten_compressed(self):
+        cctx = zstd.ZstdCompressor()
+        dctx = zstd.ZstdDecompressor()
+
+        original = [b"%d" % i * 64 for i in range(100)]
+        c = cctx.multi_compress_to_buffer(original, threads=4)
+
+        b = c.flatten()
+        self.assertEqual(len(b), 100)
+        self.assertEqual([dctx.decompress(o.tobytes()) for o in b], original)
+
+    def test_add(self):
+        c1 = zstd.BufferWithSegmentsCollection(self.b1)
+        c2 = zstd.BufferWithSegmentsCollection(self.b2)
+
+        c = c1 + c2
+        self.assertEqual(len(c), 3)
+        self.assertEqual([o.tobytes() for o in c], [b"foo", b"bar", b"baz"])
+
+        # Operands are unchanged.
+        self.assertEqual(len(c1), 1)
+        self.assertEqual(len(c2), 2)
+
+        with self.assertRaises(TypeError):
+            c1 + self.b2
+
+    def test_extend(self):
+        c = zstd.BufferWithSegmentsCollection(self.b1)
+        c.extend(zstd.BufferWithSegmentsCollection(self.b2))
+
+        self.assertEqual(len(c), 3)
+        self.assertEqual(c.size(), 9)
+        self.assertEqual(c[2].tobytes(), b"baz")
+
+        c.extend(c)
+        self.assertEqual(len(c), 6)
+        self.assertEqual([o.tobytes() for o in c], [b"foo", b"bar", b"baz"] * 2)
+
+        with self.assertRaises(TypeError):
+            c.extend(self.b1)
+
+
+@unittest.skipUnless(
+    "buffer_serialization" in zstd.backend_features,
+    "buffer_serialization feature not available",
+)
+class TestBufferWithSegmentsCollection_serialization(unittest.TestCase):
+    def setUp(self):
+        self.tempdir = tempfile.TemporaryDirectory()
+        self.path = os.path.join(self.tempdir.name, "data")
+
+        b1 = zstd.BufferWithSegments(b"foo", ss.pack(0, 3))
+        b2 = zstd.BufferWithSegments(
+            b"xbarbazy", b"".join([ss.pack(1, 3), ss.pack(4, 3)])
+        )
+        self.c = zstd.BufferWithSegmentsCollection(b1, b2)
+
+    def tearDown(self):
+        self.tempdir.cleanup()
+
+    def assertRoundTrip(self, c):
+        self.assertEqual(len(c), 3)
+        self.assertEqual(c.size(), 9)
+        self.assertEqual(
+            [c[i].tobytes() for i in range(len(c))], [b"foo", b"bar", b"baz"]
+        )
+
+    def test_bytes(self):
+        data = self.c.to_bytes()
+
+        self.assertIsInstance(data, bytes)
+        self.assertEqual(data[0:8], b"ZSTDBWSC")
+        # Header, 2 buffer entries, 3 segments, and 11 bytes of data padded
+        # to 8 byte alignment.
+        self.assertEqual(len(data), 16 + 2 * 16 + 3 * 16 + 8 + 8)
+        self.assertEqual(data[99:104], b"\x00" * 5)
+        self.assertEqual(data[104:112], b"xbarbazy")
+
+        self.assertRoundTrip(zstd.BufferWithSegmentsCollection.from_bytes(data))
+
+    def test_from_bytes_no_copy(self):
+        data = bytearray(self.c.to_bytes())
+        c = zstd.BufferWithSegmentsCollection.from_bytes(data)
+
+        data[-7:-4] = b"BAR"
+        self.assertEqual(c[1].tobytes(), b"BAR")
+
+    def test_file(self):
+        self.c.to_file(self.path)
+
+        with open(self.path, "rb") as fh:
+            self.assertEqual(fh.read(), self.c.to_bytes())
+
+        self.assertRoundTrip(
+            zstd.BufferWithSegmentsCollection.from_file(self.path)
+        )
+
+    def test_empty(self):
+        c = zstd.BufferWithSegmentsCollection.from_bytes(
+            b"ZSTDBWSC" + struct.pack("<II", 1, 0)
+        )
+
+        self.assertEqual(len(c), 0)
+        self.assertEqual(c.to_bytes(), b"ZSTDBWSC" + struct.pack("<II", 1, 0))
+
+    def test_compressed(self):
+        cctx = zstd.ZstdCompressor()
+        dctx = zstd.ZstdDecompressor()
+
+        original = [b"%d" % i * 128 for i in range(100)]
+        cctx.multi_compress_to_buffer(original, threads=4).to_file(self.path)
+
+        c = zstd.BufferWithSegmentsCollection.from_file(self.path)
+        result = dctx.multi_decompress_to_buffer(c, threads=2)
+
+        self.assertEqual(
+            [result[i].tobytes() for i in range(len(result))], original
+        )
+
+    def test_invalid(self):
+        data = self.c.to_bytes()
+
+        with self.assertRaisesRegex(
+            ValueError, "data is not a serialized BufferWithSegmentsCollection"
+        ):
+            zstd.BufferWithSegmentsCollection.from_bytes(b"foo")
+
+        with self.assertRaisesRegex(
+            ValueError, "unsupported serialization version: 2"
+        ):
+            zstd.BufferWithSegmentsCollection.from_bytes(
+                data[0:8] + struct.pack("<I", 2) + data[12:]
+            )
+
+        for size in (20, 40, 60, len(data) - 1):
+            with self.assertRaisesRegex(
+                ValueError, "serialized data is truncated"
+            ):
+                zstd.BufferWithSegmentsCollection.from_bytes(data[0:size])
+
+        with self.assertRaisesRegex(
+            ValueError, "serialized data has trailing bytes"
+        ):
+            zstd.BufferWithSegmentsCollection.from_bytes(data + b"\x00")
+
+        # Segment extending past the end of its buffer.
+        bad = bytearray(data)
+        bad[48 + 8 : 48 + 16] = struct.pack("<Q", 4)
+        with self.assertRaisesRegex(
+            ValueError, "offset within segments array references memory"
+        ):
+            zstd.BufferWithSegmentsCollection.from_bytes(bytes(bad))
+
+
+@unittest.skipUnless(
+    "buffer_segments_array" in zstd.backend_features,
+    "buffer_segments_array feature not available",
+)
+class TestBufferSegments_array(unittest.TestCase):
+    def setUp(self):
+        self.b = zstd.BufferWithSegments(
+            b"foobarbaz", ss.pack(0, 3) + ss.pack(3, 6)
+        )
+
+    def test_memoryview(self):
+        m = memoryview(self.b.segments())
+
+        self.assertEqual(m.format, "Q")
+        self.assertEqual(m.itemsize, 8)
+        self.assertEqual(m.ndim, 2)
+        self.assertEqual(m.shape, (2, 2))
+        self.assertEqual(m.strides, (16, 8))
+        self.assertEqual(m.nbytes, 32)
+        self.assertTrue(m.readonly)
+        self.assertTrue(m.c_contiguous)
+        self.assertEqual(m.tolist(), [[0, 3], [3, 6]])
+
+    def test_empty(self):
+        b = zstd.BufferWithSegments(b"", b"")
+        m = memoryview(b.segments())
+
+        self.assertEqual(m.shape, (0, 2))
+        self.assertEqual(m.tolist(), [])
+
+    def test_bytes(self):
+        raw = ss.pack(0, 3) + ss.pack(3, 6)
+
+        self.assertEqual(bytes(self.b.segments()), raw)
+        self.assertEqual(b"".join([self.b.segments()]), raw)
+
+    def test_constructor_input(self):
+        b = zstd.BufferWithSegments(b"foobarbaz", self.b.segments())
+        self.assertEqual([o.tobytes() for o in b], [b"foo", b"barbaz"])
+
+        b = zstd.BufferWithSegments(b"foobarbaz", array.array("Q", [3, 3]))
+        self.assertEqual(b[0].tobytes(), b"bar")
+
+        with self.assertRaisesRegex(
+            ValueError, "segments array size is not a multiple of 16"
+        ):
+            zstd.BufferWithSegments(b"foo", array.array("Q", [0]))
+
+    def test_segment_repr(self):
+        self.assertEqual(repr(self.b[0]), "BufferSegment(offset=0, length=3)")
+        self.assertEqual(repr(self.b[1]), "BufferSegment(offset=3, length=6)")
+
+    def test_segment_eq(self):
+        other = zstd.BufferWithSegments(b"xfoo", ss.pack(1, 3))
+
+        self.assertEqual(self.b[0], self.b[0])
+        self.assertEqual(self.b[0], other[0])
+        self.assertNotEqual(self.b[0], self.b[1])
+
+        self.assertEqual(self.b[0], b"foo")
+        self.assertEqual(self.b[1], bytearray(b"barbaz"))
+        self.assertNotEqual(self.b[0], b"bar")
+
+        self.assertFalse(self.b[0] == 1)
+        self.assertTrue(self.b[0] != u"foo")
+
+        with self.assertRaises(TypeError):
+            self.b[0] < self.b[1]
+
+    def test_segment_hash(self):
+        other = zstd.BufferWithSegments(b"xfoo", ss.pack(1, 3))
+
+        # Hashes are consistent with equality, including with bytes.
+        self.assertEqual(hash(self.b[0]), hash(other[0]))
+        self.assertEqual(hash(self.b[0]), hash(b"foo"))
+        self.assertEqual(hash(self.b[1]), hash(b"barbaz"))
+
+        self.assertEqual({self.b[0]: 1}[b"foo"], 1)
+        self.assertEqual(len({self.b[0], other[0], b"foo"}), 1)
@@ -0,0 +1,152 @@
+import _thread
+import random
+import threading
+import unittest
+
+import zstandard as zstd
+
+
+# Slow to compress at high levels, so operations are still running when
+# interrupted.
+def slow_data(size):
+    r = random.Random(0)
+    words = [
+        bytes(r.choice(b"abcdefghijklmnop") for _ in range(r.randint(2, 9)))
+        for _ in range(5000)
+    ]
+
+    chunks = []
+    while sum(map(len, chunks)) < size:
+        chunks.append(b" ".join(r.choice(words) for _ in range(10000)))
+
+    return b" ".join(chunks)[0:size]
+
+
+DATA = b"foobar" * 1048576
+
+
+@unittest.skipUnless(
+    "cancellation" in zstd.backend_features,
+    "cancellation feature not available",
+)
+class TestCancellation(unittest.TestCase):
+    def run_interrupted(self, fn, interrupt):
+        timer = threading.Timer(0.05, interrupt)
+        timer.start()
+        try:
+            fn()
+        finally:
+            timer.cancel()
+            timer.join()
+
+    def test_token(self):
+        token = zstd.CancellationToken()
+        self.assertFalse(token.cancelled)
+
+        token.cancel()
+        self.assertTrue(token.cancelled)
+
+    def test_compress_chunks(self):
+        dctx = zstd.ZstdDecompressor()
+
+        for kwargs in ({}, {"write_content_size": False}, {"threads": 2}):
+            cctx = zstd.ZstdCompressor(**kwargs)
+
+            frame = cctx.compress(DATA)
+            self.assertEqual(
+                dctx.decompress(frame, max_output_size=len(DATA)), DATA
+            )
+
+            params = zstd.get_frame_parameters(frame)
+            if kwargs.get("write_content_size", True):
+                self.assertEqual(params.content_size, len(DATA))
+            else:
+                self.assertEqual(
+                    params.content_size, zstd.CONTENTSIZE_UNKNOWN
+                )
+
+    def test_compress_cancelled(self):
+        token = zstd.CancellationToken()
+        token.cancel()
+
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaisesRegex(
+            zstd.ZstdCancelledError, "operation cancelled"
+        ):
+            cctx.compress(DATA, cancellation_token=token)
+
+        # The token is checked before compressing, even for small inputs.
+        with self.assertRaisesRegex(
+            zstd.ZstdCancelledError, "operation cancelled"
+        ):
+            cctx.compress(b"foobar", cancellation_token=token)
+
+        # The compressor is usable after cancellation.
+        frame = cctx.compress(DATA)
+        self.assertEqual(zstd.ZstdDecompressor().decompress(frame), DATA)
+
+    def test_compress_cancel_from_thread(self):
+        data = slow_data(4 * 1048576)
+        token = zstd.CancellationToken()
+        cctx = zstd.ZstdCompressor(level=19)
+
+        with self.assertRaises(zstd.ZstdCancelledError):
+            self.run_interrupted(
+                lambda: cctx.compress(data, cancellation_token=token),
+                token.cancel,
+            )
+
+    def test_compress_keyboard_interrupt(self):
+        data = slow_data(4 * 1048576)
+        cctx = zstd.ZstdCompressor(level=19)
+
+        with self.assertRaises(KeyboardInterrupt):
+            self.run_interrupted(
+                lambda: cctx.compress(data), _thread.interrupt_main
+            )
+
+    def test_multi_compress_to_buffer(self):
+        items = [DATA[0:i] for i in (1, 1048576, 3 * 1048576)]
+        cctx = zstd.ZstdCompressor()
+
+        result = cctx.multi_compress_to_buffer(
+            items, threads=2, cancellation_token=zstd.CancellationToken()
+        )
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual([dctx.decompress(s) for s in result], items)
+
+        token = zstd.CancellationToken()
+        token.cancel()
+
+        with self.assertRaisesRegex(
+            zstd.ZstdCancelledError, "operation cancelled"
+        ):
+            cctx.multi_compress_to_buffer(items, cancellation_token=token)
+
+    def test_multi_compress_to_buffer_cancel_from_thread(self):
+        data = slow_data(4 * 1048576)
+        items = [data[i : i + 1048576] for i in range(0, len(data), 1048576)]
+        cctx = zstd.ZstdCompressor(level=19)
+
+        for threads in (1, 2):
+            token = zstd.CancellationToken()
+
+            with self.assertRaises(zstd.ZstdCancelledError):
+                self.run_interrupted(
+                    lambda: cctx.multi_compress_to_buffer(
+                        items, threads=threads, cancellation_token=token
+                    ),
+                    token.cancel,
+                )
+
+    def test_multi_compress_to_buffer_keyboard_interrupt(self):
+        data = slow_data(4 * 1048576)
+        cctx = zstd.ZstdCompressor(level=19)
+
+        with self.assertRaises(KeyboardInterrupt):
+            self.run_interrupted(
+                lambda: cctx.multi_compress_to_buffer([data], threads=2),
+                _thread.interrupt_main,
+            )
@@ -0,0 +1,254 @@
+import contextlib
+import io
+import os
+import subprocess
+import sys
+import tempfile
+import unittest
+
+import zstandard as zstd
+
+from .common import (
+    generate_samples,
+)
+
+
+def run(*args, input=None, cwd=None):
+    env = dict(os.environ)
+    env["PYTHON_ZSTANDARD_IMPORT_POLICY"] = "rust"
+    # Report files the CLI leaves open.
+    env["PYTHONWARNINGS"] = "always::ResourceWarning"
+
+    return subprocess.run(
+        [sys.executable, "-m", "zstandard"] + list(args),
+        input=input,
+        cwd=cwd,
+        env=env,
+        stdout=subprocess.PIPE,
+        stderr=subprocess.PIPE,
+    )
+
+
+@unittest.skipUnless(
+    "cli" in zstd.backend_features, "cli feature not available"
+)
+class TestCli(unittest.TestCase):
+    def setUp(self):
+        self.tempdir = tempfile.TemporaryDirectory()
+        self.path = self.tempdir.name
+
+    def tearDown(self):
+        self.tempdir.cleanup()
+
+    def write(self, name, data):
+        path = os.path.join(self.path, name)
+        with open(path, "wb") as fh:
+            fh.write(data)
+
+        return path
+
+    def read(self, name):
+        with open(os.path.join(self.path, name), "rb") as fh:
+            return fh.read()
+
+    def test_help(self):
+        res = run("--help")
+        self.assertEqual(res.returncode, 0)
+        self.assertIn(b"usage:", res.stdout)
+
+    def test_version(self):
+        res = run("--version")
+        self.assertEqual(res.returncode, 0)
+        self.assertTrue(res.stdout.startswith(b"zstandard "))
+
+    def test_usage_errors(self):
+        res = run()
+        self.assertEqual(res.returncode, 2)
+        self.assertIn(b"no command specified", res.stderr)
+
+        res = run("frobnicate", "foo")
+        self.assertEqual(res.returncode, 2)
+        self.assertIn(b"unknown command: frobnicate", res.stderr)
+
+        res = run("compress", "--bogus", "foo")
+        self.assertEqual(res.returncode, 2)
+        self.assertIn(b"unknown option: --bogus", res.stderr)
+
+        res = run("compress", "-l", "fast", "foo")
+        self.assertEqual(res.returncode, 2)
+        self.assertIn(b"invalid value for -l: fast", res.stderr)
+
+        res = run("compress")
+        self.assertEqual(res.returncode, 2)
+        self.assertIn(b"compress requires input files", res.stderr)
+
+    def test_compress_decompress(self):
+        source = self.write("data", b"foobar" * 8192)
+
+        res = run("compress", "-l", "5", "--checksum", source)
+        self.assertEqual(res.returncode, 0, res.stderr)
+
+        frame = self.read("data.zst")
+        params = zstd.get_frame_parameters(frame)
+        self.assertEqual(params.content_size, 49152)
+        self.assertTrue(params.has_checksum)
+
+        os.unlink(source)
+
+        res = run("decompress", source + ".zst")
+        self.assertEqual(res.returncode, 0, res.stderr)
+        self.assertEqual(self.read("data"), b"foobar" * 8192)
+
+    def test_existing_output(self):
+        source = self.write("data", b"foo")
+        self.write("data.zst", b"existing")
+
+        res = run("compress", source)
+        self.assertEqual(res.returncode, 1)
+        self.assertIn(b"already exists; use -f to overwrite", res.stderr)
+        self.assertNotIn(b"ResourceWarning", res.stderr)
+        self.assertEqual(self.read("data.zst"), b"existing")
+
+        res = run("compress", "-f", source)
+        self.assertEqual(res.returncode, 0, res.stderr)
+        self.assertEqual(
+            zstd.ZstdDecompressor().decompress(self.read("data.zst")), b"foo"
+        )
+
+    def test_stdio(self):
+        res = run("compress", "-", input=b"foo" * 100)
+        self.assertEqual(res.returncode, 0, res.stderr)
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(
+            dctx.decompress(res.stdout, max_output_size=300), b"foo" * 100
+        )
+
+        res = run("decompress", "-", input=res.stdout)
+        self.assertEqual(res.returncode, 0, res.stderr)
+        self.assertEqual(res.stdout, b"foo" * 100)
+
+    def test_decompress_unknown_suffix(self):
+        source = self.write("data", zstd.ZstdCompressor().compress(b"foo"))
+
+        res = run("decompress", source)
+        self.assertEqual(res.returncode, 1)
+        self.assertIn(b"unknown suffix; use -o to name output", res.stderr)
+
+        res = run("decompress", source, "-o", source + ".out")
+        self.assertEqual(res.returncode, 0, res.stderr)
+        self.assertEqual(self.read("data.out"), b"foo")
+
+    def test_decompress_invalid(self):
+        source = self.write("data.zst", b"not zstd data")
+
+        res = run("decompress", source)
+        self.assertEqual(res.returncode, 1)
+        self.assertIn(b"ZstdError", res.stderr)
+        self.assertFalse(os.path.exists(os.path.join(self.path, "data")))
+
+    def test_test(self):
+        cctx = zstd.ZstdCompressor()
+        good = self.write("good.zst", cctx.compress(b"foo"))
+        bad = self.write("bad.zst", b"garbage")
+
+        res = run("test", good)
+        self.assertEqual(res.returncode, 0, res.stderr)
+        self.assertEqual(res.stdout.strip(), ("%s: OK" % good).encode())
+
+        res = run("test", good, bad)
+        self.assertEqual(res.returncode, 1)
+        self.assertIn(("%s: OK" % good).encode(), res.stdout)
+        self.assertIn(("%s: ZstdError" % bad).encode(), res.stdout)
+        self.assertIn(b"1 of 2 files failed", res.stderr)
+
+        # Files that can't be opened count as failures.
+        missing = os.path.join(self.path, "missing.zst")
+        res = run("test", missing, good)
+        self.assertEqual(res.returncode, 1)
+        self.assertIn(("%s: " % missing).encode(), res.stdout)
+        self.assertIn(("%s: OK" % good).encode(), res.stdout)
+        self.assertIn(b"1 of 2 files failed", res.stderr)
+
+    def test_list(self):
+        cctx = zstd.ZstdCompressor()
+        source = self.write(
+            "data.zst", cctx.compress(b"foo") + cctx.compress(b"bar" * 100)
+        )
+
+        res = run("list", source)
+        self.assertEqual(res.returncode, 0, res.stderr)
+
+        lines = res.stdout.decode().splitlines()
+        self.assertEqual(lines[0], source)
+        self.assertEqual(len(lines), 12)
+        self.assertEqual(lines[2].split()[0:2], ["0", "0"])
+        self.assertEqual(lines[2].split()[3:], ["3", "zstd"])
+        self.assertEqual(lines[3].split()[3:], ["300", "zstd"])
+        self.assertEqual(lines[4], "  zstd frames: 2")
+        self.assertEqual(lines[5], "  skippable frames: 0")
+        self.assertEqual(lines[7], "  decompressed size: 303")
+        self.assertEqual(lines[10], "  check: none")
+        self.assertEqual(lines[11], "  dict IDs: none")
+
+    def test_list_stdin(self):
+        cctx = zstd.ZstdCompressor()
+        res = run("list", "-", input=cctx.compress(b"foo") * 2)
+        self.assertEqual(res.returncode, 0, res.stderr)
+
+        lines = res.stdout.decode().splitlines()
+        self.assertEqual(lines[0], "-")
+        self.assertEqual(lines[4], "  zstd frames: 2")
+
+    def test_standard_streams(self):
+        frame = zstd.ZstdCompressor().compress(b"foo")
+        stdin = io.TextIOWrapper(io.BytesIO(frame))
+        stdout = io.StringIO()
+
+        # Output goes through sys.stdout and stdin is left open.
+        old_stdin = sys.stdin
+        sys.stdin = stdin
+        try:
+            with contextlib.redirect_stdout(stdout):
+                self.assertEqual(zstd.backend_rust.cli_main(["test", "-"]), 0)
+        finally:
+            sys.stdin = old_stdin
+
+        self.assertFalse(stdin.buffer.closed)
+        self.assertEqual(stdout.getvalue(), "-: OK\n")
+
+    def test_train(self):
+        samples = [
+            self.write("sample%d" % i, sample)
+            for i, sample in enumerate(generate_samples())
+        ]
+        output = os.path.join(self.path, "dict")
+
+        res = run("train", "--maxdict", "8192", "-o", output, *samples)
+        self.assertEqual(res.returncode, 0, res.stderr)
+
+        d = zstd.ZstdCompressionDict(self.read("dict"))
+        self.assertLessEqual(len(d.as_bytes()), 8192)
+        self.assertNotEqual(d.dict_id(), 0)
+
+        source = self.write("data", b"foo" * 64)
+
+        res = run("compress", "-D", output, source)
+        self.assertEqual(res.returncode, 0, res.stderr)
+
+        frame = self.read("data.zst")
+        self.assertEqual(zstd.get_frame_parameters(frame).dict_id, d.dict_id())
+
+        os.unlink(source)
+
+        res = run("decompress", "-D", output, source + ".zst")
+        self.assertEqual(res.returncode, 0, res.stderr)
+        self.assertEqual(self.read("data"), b"foo" * 64)
+
+    def test_bench(self):
+        source = self.write("data", b"foobar" * 1000)
+
+        res = run("bench", "-i", "1", "-l", "1", source)
+        self.assertEqual(res.returncode, 0, res.stderr)
+        self.assertIn(b"level 1, 6000 -> ", res.stdout)
+        self.assertIn(b"MB/s decompress", res.stdout)
@@ -0,0 +1,129 @@
+import io
+import unittest
+
+import zstandard as zstd
+
+
+DATA = b"foobar" * 8192
+
+
+@unittest.skipUnless(
+    "compression_stats" in zstd.backend_features,
+    "compression_stats feature not available",
+)
+class TestCompressionStats(unittest.TestCase):
+    def test_defaults(self):
+        stats = zstd.CompressionStats()
+
+        self.assertEqual(stats.bytes_read, 0)
+        self.assertEqual(stats.bytes_written, 0)
+        self.assertEqual(stats.frames, 0)
+        self.assertEqual(stats.flushes, 0)
+        self.assertEqual(stats.zstd_time, 0.0)
+        self.assertEqual(stats.io_time, 0.0)
+        self.assertIsNone(stats.compression_level)
+        self.assertIsNone(stats.window_log)
+
+    def test_copy_stream(self):
+        stats = zstd.CompressionStats()
+        cctx = zstd.ZstdCompressor(level=5)
+
+        dest = io.BytesIO()
+        read, written = cctx.copy_stream(
+            io.BytesIO(DATA), dest, size=len(DATA), stats=stats
+        )
+
+        self.assertEqual(stats.bytes_read, read)
+        self.assertEqual(stats.bytes_written, written)
+        self.assertEqual(stats.bytes_written, len(dest.getvalue()))
+        self.assertEqual(stats.frames, 1)
+        self.assertEqual(stats.flushes, 0)
+        self.assertGreater(stats.zstd_time, 0.0)
+        self.assertGreater(stats.io_time, 0.0)
+        self.assertEqual(stats.compression_level, 5)
+
+        # The window is derived from the level and source size.
+        params = zstd.ZstdCompressionParameters.from_level(
+            5, source_size=len(DATA)
+        )
+        self.assertEqual(stats.window_log, params.window_log)
+
+    def test_stream_writer(self):
+        stats = zstd.CompressionStats()
+        cctx = zstd.ZstdCompressor()
+
+        dest = io.BytesIO()
+        with cctx.stream_writer(dest, closefd=False, stats=stats) as writer:
+            writer.write(DATA)
+            writer.flush()
+            writer.write(DATA)
+
+            self.assertEqual(stats.bytes_read, len(DATA) * 2)
+            self.assertEqual(stats.frames, 0)
+
+        self.assertEqual(stats.bytes_written, len(dest.getvalue()))
+        self.assertEqual(stats.frames, 1)
+        self.assertEqual(stats.flushes, 1)
+        self.assertEqual(stats.compression_level, 3)
+
+    def test_stream_writer_frames(self):
+        stats = zstd.CompressionStats()
+        cctx = zstd.ZstdCompressor()
+
+        dest = io.BytesIO()
+        with cctx.stream_writer(dest, closefd=False, stats=stats) as writer:
+            writer.write(DATA)
+            writer.flush(zstd.FLUSH_FRAME)
+            writer.write(DATA)
+            writer.flush(zstd.FLUSH_FRAME)
+
+        self.assertEqual(stats.flushes, 2)
+
+        # Closing after ending a frame writes an empty frame.
+        summary = zstd.summarize_frames(dest.getvalue())
+        self.assertEqual(summary.frame_count, 3)
+        self.assertEqual(stats.frames, 3)
+
+    def test_window_log(self):
+        params = zstd.ZstdCompressionParameters(window_log=20)
+        cctx = zstd.ZstdCompressor(compression_params=params)
+
+        stats = zstd.CompressionStats()
+        cctx.copy_stream(io.BytesIO(DATA), io.BytesIO(), stats=stats)
+        self.assertEqual(stats.window_log, 20)
+
+    def test_independent_frames(self):
+        stats = zstd.CompressionStats()
+        cctx = zstd.ZstdCompressor()
+
+        cctx.copy_stream(
+            io.BytesIO(DATA),
+            io.BytesIO(),
+            frame_size=8192,
+            threads=2,
+            stats=stats,
+        )
+
+        self.assertEqual(stats.bytes_read, len(DATA))
+        self.assertEqual(stats.frames, 6)
+
+        with cctx.stream_writer(
+            io.BytesIO(), frame_size=8192, threads=2, stats=stats
+        ) as writer:
+            writer.write(DATA)
+
+        self.assertEqual(stats.frames, 12)
+
+    def test_accumulates(self):
+        stats = zstd.CompressionStats()
+
+        zstd.ZstdCompressor(level=1).copy_stream(
+            io.BytesIO(DATA), io.BytesIO(), stats=stats
+        )
+        zstd.ZstdCompressor(level=9).copy_stream(
+            io.BytesIO(DATA), io.BytesIO(), stats=stats
+        )
+
+        self.assertEqual(stats.bytes_read, len(DATA) * 2)
+        self.assertEqual(stats.frames, 2)
+        self.assertEqual(stats.compression_level, 9)
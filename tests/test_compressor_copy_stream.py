@@ -6,6 +6,7 @@ import zstandard as zstd
 
 from .common import (
     CustomBytesIO,
+    split_pzstd_frames,
 )
 
 
@@ -193,3 +194,99 @@ class TestCompressor_copy_stream(unittest.TestCase):
 
         with self.assertRaisesRegex(IOError, "write"):
             cctx.copy_stream(source, dest)
+
+
+@unittest.skipUnless(
+    "compress_parallel_frames" in zstd.backend_features,
+    "compress_parallel_frames feature not available",
+)
+class TestCompressor_copy_stream_frames(unittest.TestCase):
+    def test_empty(self):
+        source = io.BytesIO()
+        dest = io.BytesIO()
+
+        cctx = zstd.ZstdCompressor()
+        r, w = cctx.copy_stream(source, dest, frame_size=1024)
+        self.assertEqual(r, 0)
+        self.assertEqual(w, len(dest.getvalue()))
+
+        frames = split_pzstd_frames(dest.getvalue())
+        self.assertEqual(len(frames), 1)
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(dctx.decompress(frames[0]), b"")
+
+    def test_frames(self):
+        data = b"".join(b"%d" % i for i in range(100000))
+        source = io.BytesIO(data)
+        dest = io.BytesIO()
+
+        cctx = zstd.ZstdCompressor()
+        r, w = cctx.copy_stream(
+            source, dest, frame_size=65536, threads=4, write_size=1000
+        )
+        self.assertEqual(r, len(data))
+        self.assertEqual(w, len(dest.getvalue()))
+
+        frames = split_pzstd_frames(dest.getvalue())
+        self.assertEqual(len(frames), (len(data) + 65535) // 65536)
+
+        dctx = zstd.ZstdDecompressor()
+        chunks = [dctx.decompress(frame) for frame in frames]
+        self.assertEqual(b"".join(chunks), data)
+
+        for chunk in chunks[:-1]:
+            self.assertEqual(len(chunk), 65536)
+
+        for frame in frames:
+            params = zstd.get_frame_parameters(frame)
+            self.assertNotEqual(params.content_size, zstd.CONTENTSIZE_UNKNOWN)
+
+    def test_deterministic(self):
+        data = b"".join(b"%d" % i for i in range(100000))
+
+        cctx = zstd.ZstdCompressor()
+        results = set()
+
+        for threads in (-1, 0, 1, 2, 4):
+            dest = io.BytesIO()
+            cctx.copy_stream(
+                io.BytesIO(data),
+                dest,
+                read_size=4096,
+                frame_size=32768,
+                threads=threads,
+            )
+            results.add(dest.getvalue())
+
+        self.assertEqual(len(results), 1)
+
+    @unittest.skipUnless(
+        "decompress_parallel" in zstd.backend_features,
+        "decompress_parallel feature not available",
+    )
+    def test_decompress_parallel(self):
+        data = b"foobar" * 100000
+
+        cctx = zstd.ZstdCompressor()
+        dest = io.BytesIO()
+        cctx.copy_stream(io.BytesIO(data), dest, frame_size=10000)
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(dctx.decompress_parallel(dest.getvalue()), data)
+
+    def test_bad_arguments(self):
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaisesRegex(ValueError, "frame_size must be positive"):
+            cctx.copy_stream(io.BytesIO(), io.BytesIO(), frame_size=0)
+
+        with self.assertRaisesRegex(ValueError, "threads requires frame_size"):
+            cctx.copy_stream(io.BytesIO(), io.BytesIO(), threads=2)
+
+        with self.assertRaisesRegex(
+            ValueError, "size cannot be specified with frame_size"
+        ):
+            cctx.copy_stream(
+                io.BytesIO(), io.BytesIO(), size=10, frame_size=1024
+            )
@@ -1,3 +1,4 @@
+import array
 import struct
 import unittest
 
@@ -129,3 +130,149 @@ class TestCompressor_multi_compress_to_buffer(unittest.TestCase):
                 self.assertEqual(result[i].tobytes(), reference[0])
             else:
                 self.assertEqual(result[i].tobytes(), reference[1])
+
+
+@unittest.skipUnless(
+    "multi_threads_auto" in zstd.backend_features,
+    "multi_threads_auto feature not available",
+)
+class TestCompressor_multi_compress_to_buffer_threads_auto(unittest.TestCase):
+    def test_invalid_threads(self):
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaisesRegex(
+            ValueError, "threads must be an integer or 'auto'"
+        ):
+            cctx.multi_compress_to_buffer([b"foo"], threads="all")
+
+    def test_auto(self):
+        cctx = zstd.ZstdCompressor()
+
+        for count, size in ((1, 16), (64, 16), (1024, 4096)):
+            data = [b"%d" % i * size for i in range(count)]
+            frames = [cctx.compress(d) for d in data]
+
+            result = cctx.multi_compress_to_buffer(data, threads="auto")
+
+            self.assertEqual(len(result), count)
+            self.assertEqual([o.tobytes() for o in result], frames)
+
+    def test_more_threads_than_items(self):
+        cctx = zstd.ZstdCompressor()
+
+        data = [b"foo", b"bar", b"baz"]
+        frames = [cctx.compress(d) for d in data]
+
+        result = cctx.multi_compress_to_buffer(data, threads=16)
+        self.assertEqual([o.tobytes() for o in result], frames)
+
+
+@unittest.skipUnless(
+    "multi_compress_to_buffer_sequences" in zstd.backend_features,
+    "multi_compress_to_buffer_sequences feature not available",
+)
+class TestCompressor_multi_compress_to_buffer_sequences(unittest.TestCase):
+    def test_sequence_inputs(self):
+        cctx = zstd.ZstdCompressor()
+
+        original = [b"foo" * 12, b"bar" * 6, b"baz"]
+        frames = [cctx.compress(c) for c in original]
+
+        inputs = [
+            tuple(original),
+            (c for c in original),
+            [memoryview(c) for c in original],
+            [bytearray(c) for c in original],
+        ]
+
+        for data in inputs:
+            result = cctx.multi_compress_to_buffer(data)
+            self.assertEqual([o.tobytes() for o in result], frames)
+
+    def test_generator_temporaries(self):
+        cctx = zstd.ZstdCompressor()
+
+        # Items are only referenced by the generator.
+        result = cctx.multi_compress_to_buffer(
+            b"%d" % i * 100 for i in range(100)
+        )
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(
+            [dctx.decompress(o.tobytes()) for o in result],
+            [b"%d" % i * 100 for i in range(100)],
+        )
+
+    def test_generator_exception(self):
+        def gen():
+            yield b"foo"
+            raise ValueError("from generator")
+
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaisesRegex(ValueError, "from generator"):
+            cctx.multi_compress_to_buffer(gen())
+
+    def test_columnar_input(self):
+        cctx = zstd.ZstdCompressor()
+
+        original = [b"foo" * 12, b"", b"bar" * 6, b"baz"]
+        frames = [cctx.compress(c) for c in original]
+        data = b"".join(original)
+
+        offsets = [0]
+        for c in original:
+            offsets.append(offsets[-1] + len(c))
+
+        for typecode in ("i", "I", "q", "Q"):
+            for buffer in (data, bytearray(data), memoryview(data)):
+                result = cctx.multi_compress_to_buffer(
+                    (buffer, array.array(typecode, offsets)), threads=2
+                )
+                self.assertEqual([o.tobytes() for o in result], frames)
+
+    def test_columnar_subset(self):
+        cctx = zstd.ZstdCompressor()
+
+        data = b"headerfoobarbaztrailer"
+        offsets = array.array("i", [6, 9, 12, 15])
+
+        result = cctx.multi_compress_to_buffer((data, offsets))
+        self.assertEqual(
+            [o.tobytes() for o in result],
+            [cctx.compress(c) for c in (b"foo", b"bar", b"baz")],
+        )
+
+    def test_columnar_invalid(self):
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaisesRegex(
+            TypeError, "data buffer not a bytes like object"
+        ):
+            cctx.multi_compress_to_buffer((u"foo", array.array("i", [0, 3])))
+
+        with self.assertRaisesRegex(
+            ValueError, "offsets must be non-decreasing"
+        ):
+            cctx.multi_compress_to_buffer((b"foo", array.array("i", [2, 1])))
+
+        with self.assertRaisesRegex(ValueError, "offsets must not be negative"):
+            cctx.multi_compress_to_buffer((b"foo", array.array("i", [-1, 1])))
+
+        with self.assertRaisesRegex(
+            ValueError, "offsets reference memory outside data buffer"
+        ):
+            cctx.multi_compress_to_buffer((b"foo", array.array("i", [0, 4])))
+
+        with self.assertRaisesRegex(ValueError, "no source elements found"):
+            cctx.multi_compress_to_buffer((b"foo", array.array("i", [0])))
+
+    def test_tuple_of_bytes(self):
+        # A pair of bytes is two items, not data and offsets.
+        cctx = zstd.ZstdCompressor()
+
+        result = cctx.multi_compress_to_buffer((b"foo", b"bar"))
+        self.assertEqual(
+            [o.tobytes() for o in result],
+            [cctx.compress(b"foo"), cctx.compress(b"bar")],
+        )
@@ -0,0 +1,146 @@
+import unittest
+
+import zstandard as zstd
+
+from .common import (
+    generate_samples,
+)
+
+
+@unittest.skipUnless(
+    "multi_compress_to_iter" in zstd.backend_features,
+    "multi_compress_to_iter feature not available",
+)
+class TestCompressor_multi_compress_to_iter(unittest.TestCase):
+    def test_invalid_inputs(self):
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaises(TypeError):
+            cctx.multi_compress_to_iter(True)
+
+        with self.assertRaisesRegex(
+            TypeError, "item 1 not a bytes like object"
+        ):
+            list(cctx.multi_compress_to_iter([b"foo", u"foo"]))
+
+        with self.assertRaisesRegex(
+            ValueError, "max_in_flight must be positive"
+        ):
+            cctx.multi_compress_to_iter([b"foo"], max_in_flight=0)
+
+    def test_empty_input(self):
+        cctx = zstd.ZstdCompressor()
+
+        self.assertEqual(list(cctx.multi_compress_to_iter([])), [])
+
+    def test_ordered(self):
+        cctx = zstd.ZstdCompressor(write_checksum=True)
+
+        samples = generate_samples()
+        frames = [cctx.compress(sample) for sample in samples]
+
+        for threads in (-1, 0, 1, 2, 4):
+            for max_in_flight in (None, 1, 3, 1000):
+                it = cctx.multi_compress_to_iter(
+                    samples, threads=threads, max_in_flight=max_in_flight
+                )
+
+                self.assertEqual(list(it), frames)
+
+    @unittest.skipUnless(
+        "multi_threads_auto" in zstd.backend_features,
+        "multi_threads_auto feature not available",
+    )
+    def test_threads_auto(self):
+        cctx = zstd.ZstdCompressor()
+
+        samples = generate_samples()
+        frames = [cctx.compress(sample) for sample in samples]
+
+        it = cctx.multi_compress_to_iter(samples, threads="auto")
+        self.assertEqual(list(it), frames)
+
+        with self.assertRaisesRegex(
+            ValueError, "threads must be an integer or 'auto'"
+        ):
+            cctx.multi_compress_to_iter(samples, threads="many")
+
+    def test_unordered(self):
+        cctx = zstd.ZstdCompressor()
+
+        samples = generate_samples()
+        frames = [cctx.compress(sample) for sample in samples]
+
+        results = list(
+            cctx.multi_compress_to_iter(samples, threads=4, ordered=False)
+        )
+
+        self.assertEqual(len(results), len(samples))
+        self.assertEqual(
+            sorted(index for index, _ in results), list(range(len(samples)))
+        )
+
+        for index, frame in results:
+            self.assertEqual(frame, frames[index])
+
+    def test_generator_input(self):
+        consumed = []
+
+        def gen():
+            for i in range(100):
+                consumed.append(i)
+                yield b"%d" % i * 1000
+
+        cctx = zstd.ZstdCompressor()
+        dctx = zstd.ZstdDecompressor()
+
+        it = cctx.multi_compress_to_iter(gen(), threads=2, max_in_flight=4)
+
+        # Items are read lazily and no more than max_in_flight are pending.
+        self.assertEqual(consumed, [])
+        self.assertEqual(dctx.decompress(next(it)), b"0" * 1000)
+        self.assertEqual(len(consumed), 4)
+
+        chunks = [dctx.decompress(frame) for frame in it]
+        self.assertEqual(len(chunks), 99)
+        self.assertEqual(chunks[-1], b"99" * 1000)
+
+    def test_dictionary(self):
+        samples = []
+        for i in range(128):
+            samples.append(b"foo" * 64)
+            samples.append(b"bar" * 64)
+            samples.append(b"foobar" * 64)
+
+        d = zstd.train_dictionary(8192, samples)
+
+        cctx = zstd.ZstdCompressor(dict_data=d)
+        frames = [cctx.compress(sample) for sample in samples]
+
+        it = cctx.multi_compress_to_iter(samples, threads=2)
+        del cctx
+        del d
+
+        self.assertEqual(list(it), frames)
+
+    def test_source_exception(self):
+        def gen():
+            yield b"foo"
+            raise ValueError("from generator")
+
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaisesRegex(ValueError, "from generator"):
+            list(cctx.multi_compress_to_iter(gen()))
+
+    def test_drop_in_flight(self):
+        samples = [b"foo" * 65536] * 64
+        cctx = zstd.ZstdCompressor(level=10)
+
+        it = cctx.multi_compress_to_iter(samples, threads=2, max_in_flight=32)
+        next(it)
+        del it
+
+        # Dropping an iterator with items in flight leaves nothing behind.
+        frames = list(cctx.multi_compress_to_iter(samples[0:4], threads=2))
+        self.assertEqual(frames, [cctx.compress(s) for s in samples[0:4]])
@@ -10,6 +10,7 @@ import zstandard as zstd
 from .common import (
     NonClosingBytesIO,
     CustomBytesIO,
+    split_pzstd_frames,
 )
 
 
@@ -591,3 +592,59 @@ class TestCompressor_stream_writer(unittest.TestCase):
             with tarfile.open(mode="r|", fileobj=reader) as tf:
                 for member in tf:
                     self.assertEqual(member.name, "test_compressor.py")
+
+
+@unittest.skipUnless(
+    "compress_parallel_frames" in zstd.backend_features,
+    "compress_parallel_frames feature not available",
+)
+class TestCompressor_stream_writer_frames(unittest.TestCase):
+    def test_frames(self):
+        data = b"".join(b"%d" % i for i in range(100000))
+
+        buffer = NonClosingBytesIO()
+        cctx = zstd.ZstdCompressor()
+
+        with cctx.stream_writer(buffer, frame_size=16384, threads=2) as writer:
+            for offset in range(0, len(data), 1000):
+                chunk = data[offset : offset + 1000]
+                self.assertEqual(writer.write(chunk), len(chunk))
+
+        frames = split_pzstd_frames(buffer.getvalue())
+        self.assertEqual(len(frames), (len(data) + 16383) // 16384)
+        self.assertEqual(writer.tell(), len(buffer.getvalue()))
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(
+            b"".join(dctx.decompress(frame) for frame in frames), data
+        )
+
+    def test_flush(self):
+        buffer = io.BytesIO()
+        cctx = zstd.ZstdCompressor()
+
+        writer = cctx.stream_writer(buffer, frame_size=1024, threads=1)
+        writer.write(b"foo")
+        self.assertEqual(buffer.getvalue(), b"")
+
+        # Flushing ends the current frame.
+        writer.flush()
+        writer.write(b"bar")
+        writer.flush(zstd.FLUSH_FRAME)
+
+        frames = split_pzstd_frames(buffer.getvalue())
+        self.assertEqual(len(frames), 2)
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(dctx.decompress(frames[0]), b"foo")
+        self.assertEqual(dctx.decompress(frames[1]), b"bar")
+
+    def test_write_return_read(self):
+        buffer = io.BytesIO()
+        cctx = zstd.ZstdCompressor()
+
+        writer = cctx.stream_writer(
+            buffer, frame_size=1024, threads=1, write_return_read=False
+        )
+        self.assertEqual(writer.write(b"foo"), 0)
+        self.assertEqual(writer.write(b"x" * 8192), len(buffer.getvalue()))
@@ -105,6 +105,23 @@ class TestCompressionParameters(unittest.TestCase):
             p.estimated_compression_context_size(), 1297424, delta=2000
         )
 
+    def test_estimated_compression_stream_size(self):
+        p = zstd.ZstdCompressionParameters(
+            window_log=20,
+            chain_log=16,
+            hash_log=17,
+            search_log=1,
+            min_match=5,
+            target_length=16,
+            strategy=zstd.STRATEGY_DFAST,
+        )
+
+        # Streaming adds input and output buffers to the context.
+        self.assertGreater(
+            p.estimated_compression_stream_size(),
+            p.estimated_compression_context_size(),
+        )
+
     def test_strategy(self):
         p = zstd.ZstdCompressionParameters(strategy=2)
         self.assertEqual(p.strategy, 2)
@@ -0,0 +1,219 @@
+import io
+import os
+import unittest
+
+import zstandard as zstd
+
+from .common import (
+    generate_samples,
+)
+
+
+def pzstd_frames(chunks, level=3):
+    """Compress chunks into independent frames, pzstd style.
+
+    Each frame is preceded by a skippable frame holding its size.
+    """
+    cctx = zstd.ZstdCompressor(level=level)
+    parts = []
+
+    for chunk in chunks:
+        frame = cctx.compress(chunk)
+        parts.append(b"\x50\x2a\x4d\x18\x04\x00\x00\x00")
+        parts.append(len(frame).to_bytes(4, "little"))
+        parts.append(frame)
+
+    return b"".join(parts)
+
+
+@unittest.skipUnless(
+    "decompress_parallel" in zstd.backend_features,
+    "decompress_parallel feature not available",
+)
+class TestDecompressor_decompress_parallel(unittest.TestCase):
+    def test_empty(self):
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(dctx.decompress_parallel(b""), b"")
+
+    def test_single_frame(self):
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(b"foobar" * 1000)
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(dctx.decompress_parallel(frame), b"foobar" * 1000)
+
+    def test_multiple_frames(self):
+        samples = generate_samples()
+
+        cctx = zstd.ZstdCompressor()
+        frames = b"".join(cctx.compress(sample) for sample in samples)
+
+        dctx = zstd.ZstdDecompressor()
+
+        for threads in (-1, 0, 1, 2, 4):
+            self.assertEqual(
+                dctx.decompress_parallel(frames, threads=threads),
+                b"".join(samples),
+            )
+
+    def test_pzstd_format(self):
+        chunks = [b"%d" % i * 10000 for i in range(32)]
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(
+            dctx.decompress_parallel(pzstd_frames(chunks), threads=4),
+            b"".join(chunks),
+        )
+
+    def test_unknown_content_size(self):
+        cctx = zstd.ZstdCompressor(write_content_size=False)
+        frames = b"".join(cctx.compress(b"foo" * 100000) for i in range(4))
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(
+            dctx.decompress_parallel(frames, threads=2), b"foo" * 400000
+        )
+
+    def test_dictionary(self):
+        samples = []
+        for i in range(128):
+            samples.append(b"foo" * 64)
+            samples.append(b"bar" * 64)
+            samples.append(b"foobar" * 64)
+
+        d = zstd.train_dictionary(8192, samples)
+
+        cctx = zstd.ZstdCompressor(dict_data=d)
+        frames = b"".join(cctx.compress(sample) for sample in samples)
+
+        dctx = zstd.ZstdDecompressor(dict_data=d)
+        self.assertEqual(
+            dctx.decompress_parallel(frames, threads=2), b"".join(samples)
+        )
+
+    def test_errors(self):
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(b"foo" * 100)
+
+        dctx = zstd.ZstdDecompressor()
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "truncated frame at offset %d" % len(frame)
+        ):
+            dctx.decompress_parallel(frame + frame[0:-1])
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "error parsing frame header at offset 0"
+        ):
+            dctx.decompress_parallel(b"foobarbaz")
+
+        dctx = zstd.ZstdDecompressor(format=zstd.FORMAT_ZSTD1_MAGICLESS)
+        with self.assertRaisesRegex(
+            ValueError, "parallel decompression requires the FORMAT_ZSTD1"
+        ):
+            dctx.decompress_parallel(frame)
+
+
+@unittest.skipUnless(
+    "decompress_parallel" in zstd.backend_features,
+    "decompress_parallel feature not available",
+)
+class TestDecompressor_copy_stream_parallel(unittest.TestCase):
+    def test_empty(self):
+        dest = io.BytesIO()
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(
+            dctx.copy_stream(io.BytesIO(), dest, threads=2), (0, 0)
+        )
+        self.assertEqual(dest.getvalue(), b"")
+
+    def test_multiple_frames(self):
+        chunks = [b"%d" % i * 10000 for i in range(64)]
+        source = pzstd_frames(chunks)
+
+        dctx = zstd.ZstdDecompressor()
+
+        for read_size in (1, 37, 8192, 1048576):
+            dest = io.BytesIO()
+            r, w = dctx.copy_stream(
+                io.BytesIO(source), dest, read_size=read_size, threads=4
+            )
+
+            self.assertEqual(r, len(source))
+            self.assertEqual(w, sum(len(chunk) for chunk in chunks))
+            self.assertEqual(dest.getvalue(), b"".join(chunks))
+
+    def test_write_size(self):
+        cctx = zstd.ZstdCompressor()
+        source = cctx.compress(b"foobar" * 100)
+
+        dest = io.BytesIO()
+        writes = []
+
+        class RecordingBytesIO(io.BytesIO):
+            def write(self, data):
+                writes.append(len(data))
+                return super().write(data)
+
+        dctx = zstd.ZstdDecompressor()
+        dctx.copy_stream(
+            io.BytesIO(source), RecordingBytesIO(), write_size=256, threads=-1
+        )
+
+        self.assertEqual(writes, [256, 256, 88])
+
+    def test_truncated(self):
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(b"foo" * 100)
+
+        dctx = zstd.ZstdDecompressor()
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "truncated frame at offset %d" % len(frame)
+        ):
+            dctx.copy_stream(
+                io.BytesIO(frame + frame[0:-1]), io.BytesIO(), threads=2
+            )
+
+    def test_large_frame(self):
+        # Incompressible, so the frame is larger than the input buffered per
+        # thread.
+        data = os.urandom(6 * 1048576)
+        cctx = zstd.ZstdCompressor(level=1)
+        small = cctx.compress(b"foo" * 100)
+        source = small + cctx.compress(data) + small
+
+        reads = []
+
+        class RecordingBytesIO(io.BytesIO):
+            def write(self, data):
+                if not reads:
+                    reads.append(source_fh.tell())
+                return super().write(data)
+
+        source_fh = io.BytesIO(source)
+        dest = RecordingBytesIO()
+
+        dctx = zstd.ZstdDecompressor()
+        r, w = dctx.copy_stream(source_fh, dest, threads=1)
+
+        self.assertEqual(r, len(source))
+        self.assertEqual(w, len(data) + 600)
+        self.assertEqual(dest.getvalue(), b"foo" * 100 + data + b"foo" * 100)
+
+        # Output is written before the large frame was read entirely.
+        self.assertLess(reads[0], len(source) - len(small))
+
+    def test_large_frame_truncated(self):
+        data = os.urandom(6 * 1048576)
+        cctx = zstd.ZstdCompressor(level=1)
+        small = cctx.compress(b"foo" * 100)
+        source = small + cctx.compress(data)[0:-1]
+
+        dctx = zstd.ZstdDecompressor()
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "truncated frame at offset %d" % len(small)
+        ):
+            dctx.copy_stream(io.BytesIO(source), io.BytesIO(), threads=1)
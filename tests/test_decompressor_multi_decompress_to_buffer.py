@@ -229,3 +229,152 @@ class TestDecompressor_multi_decompress_to_buffer(unittest.TestCase):
             "Destination buffer is too small)",
         ):
             dctx.multi_decompress_to_buffer(frames, threads=2)
+
+
+@unittest.skipUnless(
+    "multi_decompress_to_buffer_errors" in zstd.backend_features,
+    "multi_decompress_to_buffer_errors feature not available",
+)
+class TestDecompressor_multi_decompress_to_buffer_errors(unittest.TestCase):
+    def test_invalid_errors(self):
+        dctx = zstd.ZstdDecompressor()
+
+        with self.assertRaisesRegex(
+            ValueError, "errors must be one of 'strict' or 'collect'"
+        ):
+            dctx.multi_decompress_to_buffer([b"foo"], errors="ignore")
+
+    def test_strict(self):
+        cctx = zstd.ZstdCompressor()
+        frames = [cctx.compress(b"foo")]
+
+        dctx = zstd.ZstdDecompressor()
+        result = dctx.multi_decompress_to_buffer(frames, errors="strict")
+        self.assertIsInstance(result, zstd.BufferWithSegmentsCollection)
+
+    def test_collect_no_errors(self):
+        cctx = zstd.ZstdCompressor()
+        frames = [cctx.compress(b"foo"), cctx.compress(b"bar")]
+
+        dctx = zstd.ZstdDecompressor()
+        result, errors = dctx.multi_decompress_to_buffer(
+            frames, errors="collect"
+        )
+
+        self.assertIsInstance(result, zstd.BufferWithSegmentsCollection)
+        self.assertEqual([o.tobytes() for o in result], [b"foo", b"bar"])
+        self.assertEqual(errors, [])
+
+    def test_collect(self):
+        cctx = zstd.ZstdCompressor()
+        frames = [cctx.compress(b"x" * 128) for i in range(64)]
+
+        # Corrupt one frame and make another lack a content size.
+        frames[10] = frames[10][0:15] + b"extra" + frames[10][15:]
+        frames[20] = b"foobarbaz"
+
+        dctx = zstd.ZstdDecompressor()
+
+        for threads in (0, 2, -1):
+            result, errors = dctx.multi_decompress_to_buffer(
+                frames, threads=threads, errors="collect"
+            )
+
+            self.assertEqual(len(result), 64)
+            self.assertEqual(result.size(), 62 * 128)
+
+            for i, segment in enumerate(result):
+                if i in (10, 20):
+                    self.assertEqual(segment.tobytes(), b"")
+                else:
+                    self.assertEqual(segment.tobytes(), b"x" * 128)
+
+            self.assertEqual([index for index, _ in errors], [10, 20])
+            self.assertIsInstance(errors[0][1], zstd.ZstdError)
+            self.assertRegex(
+                str(errors[0][1]),
+                "error decompressing item 10: ("
+                "Corrupted block|"
+                "Destination buffer is too small)",
+            )
+            self.assertIsInstance(errors[1][1], ValueError)
+            self.assertEqual(
+                str(errors[1][1]),
+                "could not determine decompressed size of item 20",
+            )
+
+    def test_collect_truncated(self):
+        cctx = zstd.ZstdCompressor()
+        frames = [cctx.compress(b"x" * 2500) for i in range(4)]
+
+        # A frame missing its end leaves the context mid frame.
+        frames[1] = frames[1][0:-4]
+
+        dctx = zstd.ZstdDecompressor()
+
+        for threads in (0, 2):
+            result, errors = dctx.multi_decompress_to_buffer(
+                frames, threads=threads, errors="collect"
+            )
+
+            self.assertEqual(
+                [len(segment) for segment in result], [2500, 0, 2500, 2500]
+            )
+            self.assertEqual([index for index, _ in errors], [1])
+            self.assertEqual(
+                str(errors[0][1]),
+                "error decompressing item 1: frame is incomplete",
+            )
+
+    def test_collect_all_failed(self):
+        dctx = zstd.ZstdDecompressor()
+        result, errors = dctx.multi_decompress_to_buffer(
+            [b"foo", b"bar"], errors="collect"
+        )
+
+        self.assertEqual(len(result), 2)
+        self.assertEqual(result.size(), 0)
+        self.assertEqual([index for index, _ in errors], [0, 1])
+
+
+@unittest.skipUnless(
+    "multi_threads_auto" in zstd.backend_features,
+    "multi_threads_auto feature not available",
+)
+class TestDecompressor_multi_decompress_to_buffer_threads_auto(
+    unittest.TestCase
+):
+    def test_invalid_threads(self):
+        dctx = zstd.ZstdDecompressor()
+
+        with self.assertRaisesRegex(
+            ValueError, "threads must be an integer or 'auto'"
+        ):
+            dctx.multi_decompress_to_buffer([b"foo"], threads="all")
+
+    def test_auto(self):
+        cctx = zstd.ZstdCompressor()
+        dctx = zstd.ZstdDecompressor()
+
+        for count, size in ((1, 16), (64, 16), (1024, 4096)):
+            data = [b"%d" % i * size for i in range(count)]
+            frames = [cctx.compress(d) for d in data]
+
+            result = dctx.multi_decompress_to_buffer(frames, threads="auto")
+
+            self.assertEqual(len(result), count)
+            self.assertEqual([o.tobytes() for o in result], data)
+
+    def test_collect_errors_across_threads(self):
+        cctx = zstd.ZstdCompressor()
+        frames = [cctx.compress(b"x" * 128) for i in range(10)]
+        frames[3] = b"foobarbaz"
+        frames[7] = b"foobarbaz"
+
+        dctx = zstd.ZstdDecompressor()
+        result, errors = dctx.multi_decompress_to_buffer(
+            frames, threads=3, errors="collect"
+        )
+
+        self.assertEqual(len(result), 10)
+        self.assertEqual([index for index, _ in errors], [3, 7])
@@ -24,17 +24,18 @@ class TestDecompressor_stream_reader(unittest.TestCase):
         dctx = zstd.ZstdDecompressor()
 
         with dctx.stream_reader(b"foo") as reader:
-            with self.assertRaises(io.UnsupportedOperation):
-                reader.readline()
+            if "decompression_reader_readline" not in zstd.backend_features:
+                with self.assertRaises(io.UnsupportedOperation):
+                    reader.readline()
 
-            with self.assertRaises(io.UnsupportedOperation):
-                reader.readlines()
+                with self.assertRaises(io.UnsupportedOperation):
+                    reader.readlines()
 
-            with self.assertRaises(io.UnsupportedOperation):
-                iter(reader)
+                with self.assertRaises(io.UnsupportedOperation):
+                    iter(reader)
 
-            with self.assertRaises(io.UnsupportedOperation):
-                next(reader)
+                with self.assertRaises(io.UnsupportedOperation):
+                    next(reader)
 
             with self.assertRaises(io.UnsupportedOperation):
                 reader.write(b"foo")
@@ -49,7 +50,10 @@ class TestDecompressor_stream_reader(unittest.TestCase):
             self.assertFalse(reader.closed)
             self.assertTrue(reader.readable())
             self.assertFalse(reader.writable())
-            self.assertFalse(reader.seekable())
+            self.assertEqual(
+                reader.seekable(),
+                "decompression_reader_backward_seek" in zstd.backend_features,
+            )
             self.assertFalse(reader.isatty())
             self.assertFalse(reader.closed)
             self.assertIsNone(reader.flush())
@@ -288,21 +292,26 @@ class TestDecompressor_stream_reader(unittest.TestCase):
 
             reader.read(1)
 
-            with self.assertRaisesRegex(
-                OSError, "cannot seek zstd decompression stream backwards"
+            if (
+                "decompression_reader_backward_seek"
+                not in zstd.backend_features
             ):
-                reader.seek(0, os.SEEK_SET)
-
-            with self.assertRaisesRegex(
-                OSError, "cannot seek zstd decompression stream backwards"
-            ):
-                reader.seek(-1, os.SEEK_CUR)
-
-            with self.assertRaisesRegex(
-                OSError,
-                "zstd decompression streams cannot be seeked with SEEK_END",
-            ):
-                reader.seek(0, os.SEEK_END)
+                with self.assertRaisesRegex(
+                    OSError, "cannot seek zstd decompression stream backwards"
+                ):
+                    reader.seek(0, os.SEEK_SET)
+
+                with self.assertRaisesRegex(
+                    OSError, "cannot seek zstd decompression stream backwards"
+                ):
+                    reader.seek(-1, os.SEEK_CUR)
+
+            if "decompression_reader_seek_end" not in zstd.backend_features:
+                with self.assertRaisesRegex(
+                    OSError,
+                    "zstd decompression streams cannot be seeked with SEEK_END",
+                ):
+                    reader.seek(0, os.SEEK_END)
 
             reader.close()
 
@@ -590,3 +599,355 @@ class TestDecompressor_stream_reader(unittest.TestCase):
 
         self.assertEqual(len(lines), 1024)
         self.assertEqual(b"".join(lines), source)
+
+
+@unittest.skipUnless(
+    "decompression_reader_readline" in zstd.backend_features,
+    "readline() not available",
+)
+class TestDecompressor_stream_reader_readline(unittest.TestCase):
+    def test_readline(self):
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(b"foo\nbar\n\nbaz")
+
+        dctx = zstd.ZstdDecompressor()
+        reader = dctx.stream_reader(frame)
+
+        self.assertEqual(reader.readline(), b"foo\n")
+        self.assertEqual(reader.tell(), 4)
+        self.assertEqual(reader.readline(), b"bar\n")
+        self.assertEqual(reader.readline(), b"\n")
+        self.assertEqual(reader.readline(), b"baz")
+        self.assertEqual(reader.readline(), b"")
+        self.assertEqual(reader.tell(), 12)
+
+    def test_readline_size(self):
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(b"foobar\nbaz\n")
+
+        dctx = zstd.ZstdDecompressor()
+        reader = dctx.stream_reader(frame)
+
+        self.assertEqual(reader.readline(0), b"")
+        self.assertEqual(reader.readline(4), b"foob")
+        self.assertEqual(reader.readline(3), b"ar\n")
+        self.assertEqual(reader.readline(size=-1), b"baz\n")
+        self.assertEqual(reader.readline(2), b"")
+
+    def test_readline_mixed_reads(self):
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(b"foo\nbar\nbaz\n")
+
+        dctx = zstd.ZstdDecompressor()
+        reader = dctx.stream_reader(frame)
+
+        self.assertEqual(reader.readline(), b"foo\n")
+        self.assertEqual(reader.read(2), b"ba")
+        self.assertEqual(reader.readline(), b"r\n")
+
+        b = bytearray(2)
+        self.assertEqual(reader.readinto(b), 2)
+        self.assertEqual(b, b"ba")
+        self.assertEqual(reader.read1(), b"z\n")
+        self.assertEqual(reader.read(), b"")
+
+    def test_readline_small_read_size(self):
+        source = b"".join(b"line %d\n" % i for i in range(128))
+
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(source)
+
+        dctx = zstd.ZstdDecompressor()
+        reader = dctx.stream_reader(io.BytesIO(frame), read_size=1)
+
+        self.assertEqual(reader.readline(), b"line 0\n")
+        self.assertEqual(reader.readline(), b"line 1\n")
+        self.assertEqual(reader.read(), source[14:])
+
+    def test_readline_multiple_frames(self):
+        cctx = zstd.ZstdCompressor()
+        source = cctx.compress(b"foo\nba") + cctx.compress(b"r\nbaz\n")
+
+        dctx = zstd.ZstdDecompressor()
+        reader = dctx.stream_reader(source)
+
+        self.assertEqual(reader.readline(), b"foo\n")
+        self.assertEqual(reader.readline(), b"bar\n")
+        self.assertEqual(reader.readline(), b"baz\n")
+        self.assertEqual(reader.readline(), b"")
+
+    def test_readlines(self):
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(b"foo\nbar\nbaz\n")
+
+        dctx = zstd.ZstdDecompressor()
+
+        reader = dctx.stream_reader(frame)
+        self.assertEqual(reader.readlines(), [b"foo\n", b"bar\n", b"baz\n"])
+
+        reader = dctx.stream_reader(frame)
+        self.assertEqual(reader.readlines(5), [b"foo\n", b"bar\n"])
+        self.assertEqual(reader.readlines(), [b"baz\n"])
+
+    def test_iter(self):
+        source = b"".join(b"%d\n" % i for i in range(100000))
+
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(source)
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(io.BytesIO(frame)) as reader:
+            lines = list(reader)
+
+        self.assertEqual(len(lines), 100000)
+        self.assertEqual(lines[42], b"42\n")
+        self.assertEqual(b"".join(lines), source)
+
+    def test_closed(self):
+        dctx = zstd.ZstdDecompressor()
+
+        reader = dctx.stream_reader(b"")
+        reader.close()
+
+        with self.assertRaisesRegex(ValueError, "stream is closed"):
+            reader.readline()
+
+        with self.assertRaisesRegex(ValueError, "stream is closed"):
+            iter(reader)
+
+
+class NonSeekableBytesIO(io.BytesIO):
+    def seekable(self):
+        return False
+
+
+@unittest.skipUnless(
+    "decompression_reader_backward_seek" in zstd.backend_features,
+    "backward seeking not available",
+)
+class TestDecompressor_stream_reader_backward_seek(unittest.TestCase):
+    def test_buffer_source(self):
+        source = b"foobar" * 60
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(source)
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(frame) as reader:
+            self.assertTrue(reader.seekable())
+            self.assertEqual(reader.read(9), b"foobarfoo")
+
+            self.assertEqual(reader.seek(3), 3)
+            self.assertEqual(reader.read(3), b"bar")
+
+            self.assertEqual(reader.seek(-4, os.SEEK_CUR), 2)
+            self.assertEqual(reader.read(4), b"obar")
+
+            self.assertEqual(reader.seek(0), 0)
+            self.assertEqual(reader.read(), source)
+
+            with self.assertRaisesRegex(
+                OSError, "cannot seek to negative position with SEEK_CUR"
+            ):
+                reader.seek(-1000, os.SEEK_CUR)
+
+    def test_file_source(self):
+        source = b"foobar" * 60
+        cctx = zstd.ZstdCompressor()
+
+        # The reader starts wherever the source is positioned.
+        fh = io.BytesIO(b"prefix" + cctx.compress(source))
+        fh.seek(6)
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(fh, read_size=16) as reader:
+            self.assertTrue(reader.seekable())
+            self.assertEqual(reader.read(200), source[0:200])
+
+            self.assertEqual(reader.seek(10), 10)
+            self.assertEqual(reader.tell(), 10)
+            self.assertEqual(reader.read(20), source[10:30])
+            self.assertEqual(reader.read(), source[30:])
+
+    def test_non_seekable_source(self):
+        cctx = zstd.ZstdCompressor()
+        fh = NonSeekableBytesIO(cctx.compress(b"foobar" * 60))
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(fh) as reader:
+            self.assertFalse(reader.seekable())
+            reader.read(10)
+
+            with self.assertRaisesRegex(
+                OSError, "cannot seek zstd decompression stream backwards"
+            ):
+                reader.seek(0, os.SEEK_SET)
+
+            with self.assertRaisesRegex(
+                OSError, "cannot seek zstd decompression stream backwards"
+            ):
+                reader.seek(-1, os.SEEK_CUR)
+
+    def test_seek_discards_lines(self):
+        cctx = zstd.ZstdCompressor()
+        frame = cctx.compress(b"foo\nbar\n")
+
+        dctx = zstd.ZstdDecompressor()
+        reader = dctx.stream_reader(frame)
+
+        self.assertEqual(reader.readline(), b"foo\n")
+        reader.seek(1)
+        self.assertEqual(reader.readline(), b"oo\n")
+        self.assertEqual(reader.readline(), b"bar\n")
+
+    def test_checkpoints(self):
+        cctx = zstd.ZstdCompressor()
+        frames = [cctx.compress(b"%d" % i * 1000) for i in range(10)]
+        source = b"".join(b"%d" % i * 1000 for i in range(10))
+
+        class CountingBytesIO(io.BytesIO):
+            seeks = []
+
+            def seek(self, pos, whence=os.SEEK_SET):
+                self.seeks.append(pos)
+                return super().seek(pos, whence)
+
+        fh = CountingBytesIO(b"".join(frames))
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(
+            fh, read_across_frames=True, seek_checkpoints=True
+        ) as reader:
+            self.assertEqual(reader.read(), source)
+
+            # Seeking into the 6th frame restarts at its beginning.
+            reader.seek(5500)
+            self.assertEqual(
+                fh.seeks[-1], sum(len(frame) for frame in frames[0:5])
+            )
+            self.assertEqual(reader.read(10), source[5500:5510])
+
+            reader.seek(999)
+            self.assertEqual(fh.seeks[-1], 0)
+            self.assertEqual(reader.read(2), b"01")
+
+            # Seeking exactly onto a boundary uses that boundary.
+            reader.read()
+            reader.seek(2000)
+            self.assertEqual(
+                fh.seeks[-1], sum(len(frame) for frame in frames[0:2])
+            )
+            self.assertEqual(reader.read(), source[2000:])
+
+
+@unittest.skipUnless(
+    "decompression_reader_seek_end" in zstd.backend_features,
+    "SEEK_END not available",
+)
+class TestDecompressor_stream_reader_seek_end(unittest.TestCase):
+    def test_decompressed_size(self):
+        cctx = zstd.ZstdCompressor()
+        source = cctx.compress(b"foo" * 100) + cctx.compress(b"bar" * 50)
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(source) as reader:
+            self.assertEqual(reader.decompressed_size(), 450)
+            self.assertEqual(reader.tell(), 0)
+
+        fh = io.BytesIO(source)
+        with dctx.stream_reader(fh, read_size=8) as reader:
+            self.assertEqual(reader.read(4), b"foof")
+            position = fh.tell()
+            self.assertEqual(reader.decompressed_size(), 450)
+            self.assertEqual(fh.tell(), position)
+            self.assertEqual(reader.read(4), b"oofo")
+
+    def test_skippable_frames(self):
+        cctx = zstd.ZstdCompressor()
+        skippable = b"\x50\x2a\x4d\x18\x03\x00\x00\x00foo"
+        source = skippable + cctx.compress(b"foo" * 100) + skippable
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(source) as reader:
+            self.assertEqual(reader.decompressed_size(), 300)
+
+    def test_unknown_size(self):
+        cctx = zstd.ZstdCompressor()
+        source = cctx.compress(b"foo" * 100)
+
+        # Frames from streaming compression don't record a content size.
+        cobj = cctx.compressobj()
+        source += cobj.compress(b"bar" * 100) + cobj.flush()
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(source, read_across_frames=True) as reader:
+            self.assertIsNone(reader.decompressed_size())
+
+            with self.assertRaisesRegex(
+                OSError,
+                "cannot be seeked with SEEK_END unless all frames declare",
+            ):
+                reader.seek(0, os.SEEK_END)
+
+            self.assertEqual(reader.read(6), b"foofoo")
+            self.assertEqual(reader.decompressed_size(full_scan=True), 600)
+            self.assertEqual(reader.tell(), 6)
+            self.assertEqual(reader.read(3), b"foo")
+
+            self.assertEqual(reader.seek(-3, os.SEEK_END), 597)
+            self.assertEqual(reader.read(), b"bar")
+
+    def test_non_seekable_source(self):
+        cctx = zstd.ZstdCompressor()
+        fh = NonSeekableBytesIO(cctx.compress(b"foo" * 100))
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(fh) as reader:
+            with self.assertRaisesRegex(
+                OSError, "cannot determine decompressed size of non-seekable"
+            ):
+                reader.decompressed_size()
+
+    def test_truncated(self):
+        cctx = zstd.ZstdCompressor()
+        source = cctx.compress(b"foo" * 100)
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(source[0:-1]) as reader:
+            with self.assertRaisesRegex(
+                zstd.ZstdError, "truncated frame at offset 0"
+            ):
+                reader.decompressed_size()
+
+    def test_seek_end(self):
+        source = b"".join(b"%d" % i * 1000 for i in range(10))
+
+        cctx = zstd.ZstdCompressor()
+        frames = b"".join(
+            cctx.compress(source[i : i + 1000]) for i in range(0, 10000, 1000)
+        )
+
+        dctx = zstd.ZstdDecompressor()
+
+        with dctx.stream_reader(
+            io.BytesIO(frames), read_across_frames=True
+        ) as reader:
+            self.assertEqual(reader.seek(0, os.SEEK_END), 10000)
+            self.assertEqual(reader.read(), b"")
+
+            self.assertEqual(reader.seek(-1500, os.SEEK_END), 8500)
+            self.assertEqual(reader.read(1000), source[8500:9500])
+
+            with self.assertRaisesRegex(
+                OSError, "cannot seek to negative position with SEEK_END"
+            ):
+                reader.seek(-10001, os.SEEK_END)
@@ -0,0 +1,181 @@
+import io
+import struct
+import unittest
+
+import zstandard as zstd
+
+
+# Magic numbers of zstd v0.1 through v0.7.
+LEGACY_MAGIC_NUMBERS = (
+    0xFD2FB51E,
+    0xFD2FB522,
+    0xFD2FB523,
+    0xFD2FB524,
+    0xFD2FB525,
+    0xFD2FB526,
+    0xFD2FB527,
+)
+
+
+def legacy_frame(data, version=7):
+    """Construct a zstd v0.7 frame holding ``data`` in a raw block.
+
+    ``version`` only changes the magic number, so the frame is only valid for
+    version 7.
+    """
+    assert len(data) < 256
+
+    return (
+        struct.pack("<I", LEGACY_MAGIC_NUMBERS[version - 1])
+        # Single segment with 1 byte content size.
+        + bytes([0x20, len(data)])
+        # Raw block.
+        + bytes([0x40, 0, len(data)])
+        + data
+        # End block.
+        + bytes([0xC0, 0, 0])
+    )
+
+
+@unittest.skipUnless(
+    "legacy_frames" in zstd.backend_features,
+    "legacy_frames feature not available",
+)
+class TestLegacyFrames(unittest.TestCase):
+    def test_frame_legacy_version(self):
+        for version in range(1, 8):
+            self.assertEqual(
+                zstd.frame_legacy_version(legacy_frame(b"foo", version)),
+                version,
+            )
+
+        frame = zstd.ZstdCompressor().compress(b"foo")
+
+        self.assertEqual(zstd.frame_legacy_version(frame), 0)
+        self.assertEqual(zstd.frame_legacy_version(b""), 0)
+        self.assertEqual(zstd.frame_legacy_version(b"\x27\xb5\x2f"), 0)
+
+    def test_get_frame_parameters(self):
+        params = zstd.get_frame_parameters(legacy_frame(b"hello"))
+        self.assertEqual(params.legacy_version, 7)
+        self.assertEqual(params.content_size, 5)
+        self.assertEqual(params.window_size, 0)
+        self.assertEqual(params.dict_id, 0)
+        self.assertFalse(params.has_checksum)
+
+        frame = zstd.ZstdCompressor().compress(b"foo")
+        self.assertEqual(zstd.get_frame_parameters(frame).legacy_version, 0)
+
+    def test_summarize_frames(self):
+        frame = zstd.ZstdCompressor().compress(b"foo")
+        data = frame + legacy_frame(b"hello")
+
+        summary = zstd.summarize_frames(data)
+        self.assertEqual(summary.frame_count, 2)
+        self.assertEqual(summary.compressed_size, len(data))
+        self.assertEqual(summary.decompressed_size, 8)
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "truncated frame at offset %d" % len(frame)
+        ):
+            zstd.summarize_frames(data[0:-1])
+
+    @unittest.skipUnless(
+        "decompress_parallel" in zstd.backend_features,
+        "decompress_parallel feature not available",
+    )
+    def test_decompress_parallel(self):
+        data = legacy_frame(b"hello") + zstd.ZstdCompressor().compress(b"foo")
+
+        dctx = zstd.ZstdDecompressor()
+        self.assertEqual(dctx.decompress_parallel(data, 2), b"hellofoo")
+
+        dest = io.BytesIO()
+        dctx.copy_stream(io.BytesIO(data), dest, threads=2)
+        self.assertEqual(dest.getvalue(), b"hellofoo")
+
+        dctx = zstd.ZstdDecompressor(allow_legacy=False)
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "legacy zstd frames are not allowed"
+        ):
+            dctx.decompress_parallel(data, 2)
+
+    def test_decompress_allowed(self):
+        frame = legacy_frame(b"hello")
+        dctx = zstd.ZstdDecompressor()
+
+        self.assertEqual(dctx.decompress(frame), b"hello")
+        self.assertEqual(dctx.decompressobj().decompress(frame), b"hello")
+
+    def test_decompress_refused(self):
+        frame = legacy_frame(b"hello")
+        dctx = zstd.ZstdDecompressor(allow_legacy=False)
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "legacy zstd frames are not allowed"
+        ):
+            dctx.decompress(frame)
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "legacy zstd frames are not allowed"
+        ):
+            dctx.decompressobj().decompress(frame)
+
+        # Refusal doesn't affect regular frames.
+        frame = zstd.ZstdCompressor().compress(b"foo")
+        self.assertEqual(dctx.decompress(frame), b"foo")
+
+    def test_streaming_byte_at_a_time(self):
+        dctx = zstd.ZstdDecompressor(allow_legacy=False)
+
+        frame = zstd.ZstdCompressor().compress(b"foo")
+        dobj = dctx.decompressobj()
+        chunks = [dobj.decompress(frame[i : i + 1]) for i in range(len(frame))]
+        self.assertEqual(b"".join(chunks), b"foo")
+
+        frame = legacy_frame(b"hello")
+        dobj = dctx.decompressobj()
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "legacy zstd frames are not allowed"
+        ):
+            for i in range(len(frame)):
+                dobj.decompress(frame[i : i + 1])
+
+    def test_later_frame_refused(self):
+        cctx = zstd.ZstdCompressor()
+        data = cctx.compress(b"foo") + legacy_frame(b"hello")
+
+        dctx = zstd.ZstdDecompressor()
+        reader = dctx.stream_reader(data, read_across_frames=True)
+        self.assertEqual(reader.read(), b"foohello")
+
+        dctx = zstd.ZstdDecompressor(allow_legacy=False)
+        reader = dctx.stream_reader(data, read_across_frames=True)
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "legacy zstd frames are not allowed"
+        ):
+            reader.read()
+
+        dest = io.BytesIO()
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "legacy zstd frames are not allowed"
+        ):
+            dctx.copy_stream(io.BytesIO(data), dest)
+
+    def test_magicless(self):
+        # Frames without magic numbers are never considered legacy.
+        cctx = zstd.ZstdCompressor(
+            compression_params=zstd.ZstdCompressionParameters(
+                format=zstd.FORMAT_ZSTD1_MAGICLESS
+            )
+        )
+        frame = cctx.compress(b"foo")
+
+        dctx = zstd.ZstdDecompressor(
+            format=zstd.FORMAT_ZSTD1_MAGICLESS, allow_legacy=False
+        )
+        self.assertEqual(dctx.decompressobj().decompress(frame), b"foo")
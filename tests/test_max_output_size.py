@@ -0,0 +1,162 @@
+import io
+import struct
+import unittest
+
+import zstandard as zstd
+
+
+DATA = b"foobar" * 8192
+
+
+def frames(count=2, write_content_size=True):
+    cctx = zstd.ZstdCompressor(write_content_size=write_content_size)
+    return b"".join(cctx.compress(DATA) for _ in range(count))
+
+
+@unittest.skipUnless(
+    "max_output_size" in zstd.backend_features,
+    "max_output_size feature not available",
+)
+class TestMaxOutputSize(unittest.TestCase):
+    def test_exception(self):
+        self.assertTrue(issubclass(zstd.ZstdOutputLimitError, zstd.ZstdError))
+
+    def test_decompress(self):
+        frame = frames(1)
+
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA))
+        self.assertEqual(dctx.decompress(frame), DATA)
+
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA) - 1)
+        with self.assertRaisesRegex(
+            zstd.ZstdOutputLimitError, "exceeds max_output_size"
+        ):
+            dctx.decompress(frame)
+
+    def test_decompress_huge_content_size(self):
+        # A frame header declaring a content size of 2**40 bytes.
+        header = zstd.FRAME_HEADER + b"\xe0" + (2**40).to_bytes(8, "little")
+        self.assertEqual(zstd.frame_content_size(header), 2**40)
+
+        dctx = zstd.ZstdDecompressor(max_output_size=2**20)
+        with self.assertRaises(zstd.ZstdOutputLimitError):
+            dctx.decompress(header + b"\x01\x00\x00")
+
+    def test_decompress_unknown_content_size(self):
+        frame = frames(1, write_content_size=False)
+
+        dctx = zstd.ZstdDecompressor(max_output_size=1024)
+        with self.assertRaises(zstd.ZstdOutputLimitError):
+            dctx.decompress(frame, max_output_size=len(DATA))
+
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA))
+        self.assertEqual(dctx.decompress(frame, max_output_size=2**30), DATA)
+
+    def test_decompressobj(self):
+        data = frames(1, write_content_size=False)
+
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA))
+        dobj = dctx.decompressobj()
+        self.assertEqual(dobj.decompress(data), DATA)
+
+        dctx = zstd.ZstdDecompressor(max_output_size=1024)
+        dobj = dctx.decompressobj()
+        with self.assertRaises(zstd.ZstdOutputLimitError):
+            dobj.decompress(data)
+
+    def test_stream_reader(self):
+        data = frames(2)
+
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA))
+        with dctx.stream_reader(data, read_across_frames=True) as reader:
+            with self.assertRaises(zstd.ZstdOutputLimitError):
+                reader.read()
+
+        # Each reader counts its own output.
+        dctx = zstd.ZstdDecompressor(max_output_size=2 * len(DATA))
+        for _ in range(2):
+            with dctx.stream_reader(data, read_across_frames=True) as reader:
+                self.assertEqual(reader.read(), DATA * 2)
+
+    def test_stream_writer(self):
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA))
+        writer = dctx.stream_writer(io.BytesIO())
+
+        writer.write(frames(1))
+        with self.assertRaises(zstd.ZstdOutputLimitError):
+            writer.write(frames(1))
+
+    def test_read_to_iter(self):
+        dctx = zstd.ZstdDecompressor(max_output_size=1024)
+
+        with self.assertRaises(zstd.ZstdOutputLimitError):
+            list(dctx.read_to_iter(io.BytesIO(frames(1))))
+
+    def test_copy_stream(self):
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA))
+
+        dest = io.BytesIO()
+        dctx.copy_stream(io.BytesIO(frames(1)), dest)
+        self.assertEqual(dest.getvalue(), DATA)
+
+        with self.assertRaises(zstd.ZstdOutputLimitError):
+            dctx.copy_stream(io.BytesIO(frames(2)), io.BytesIO())
+
+    @unittest.skipUnless(
+        "decompress_parallel" in zstd.backend_features,
+        "decompress_parallel feature not available",
+    )
+    def test_parallel(self):
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA) * 2)
+
+        self.assertEqual(dctx.decompress_parallel(frames(2)), DATA * 2)
+
+        for write_content_size in (True, False):
+            data = frames(3, write_content_size=write_content_size)
+
+            with self.assertRaises(zstd.ZstdOutputLimitError):
+                dctx.decompress_parallel(data, threads=2)
+
+            with self.assertRaises(zstd.ZstdOutputLimitError):
+                dctx.copy_stream(io.BytesIO(data), io.BytesIO(), threads=2)
+
+    def test_multi_decompress_to_buffer(self):
+        cctx = zstd.ZstdCompressor()
+        items = [cctx.compress(b"x" * 600)] * 3
+
+        # The limit applies to each item, however items are spread across
+        # threads.
+        dctx = zstd.ZstdDecompressor(max_output_size=1000)
+        for threads in (0, 2):
+            result = dctx.multi_decompress_to_buffer(items, threads=threads)
+            self.assertEqual(len(result), 3)
+
+        items[1] = cctx.compress(b"x" * 1001)
+        for threads in (0, 2):
+            with self.assertRaises(zstd.ZstdOutputLimitError):
+                dctx.multi_decompress_to_buffer(items, threads=threads)
+
+        result, errors = dctx.multi_decompress_to_buffer(
+            items, threads=2, errors="collect"
+        )
+        self.assertEqual([len(segment) for segment in result], [600, 0, 600])
+        self.assertEqual([index for index, _ in errors], [1])
+        self.assertIsInstance(errors[0][1], zstd.ZstdOutputLimitError)
+
+        # Sizes are also enforced for frames not declaring them.
+        items = [zstd.ZstdCompressor(write_content_size=False).compress(DATA)]
+        with self.assertRaises(zstd.ZstdOutputLimitError):
+            dctx.multi_decompress_to_buffer(
+                items, decompressed_sizes=struct.pack("=Q", len(DATA))
+            )
+
+    def test_content_dict_chain(self):
+        chain = [zstd.ZstdCompressor().compress(DATA)] * 3
+
+        # The limit applies to each chunk.
+        dctx = zstd.ZstdDecompressor(max_output_size=len(DATA))
+        self.assertEqual(dctx.decompress_content_dict_chain(chain), DATA)
+
+        dctx = zstd.ZstdDecompressor(max_output_size=1024)
+        with self.assertRaises(zstd.ZstdOutputLimitError):
+            dctx.decompress_content_dict_chain(chain)
@@ -0,0 +1,184 @@
+import io
+import unittest
+
+import zstandard as zstd
+
+
+@unittest.skipUnless(
+    "memory_tracker" in zstd.backend_features,
+    "memory_tracker feature not available",
+)
+class TestMemoryTracker(unittest.TestCase):
+    def tearDown(self):
+        zstd.set_memory_tracker(None)
+
+    def test_defaults(self):
+        tracker = zstd.MemoryTracker()
+
+        self.assertEqual(tracker.live_bytes, 0)
+        self.assertEqual(tracker.peak_bytes, 0)
+        self.assertEqual(tracker.allocation_count, 0)
+        self.assertEqual(tracker.failed_allocation_count, 0)
+        self.assertIsNone(tracker.limit)
+
+    def test_limit(self):
+        tracker = zstd.MemoryTracker(limit=1024)
+        self.assertEqual(tracker.limit, 1024)
+
+        tracker.limit = 2048
+        self.assertEqual(tracker.limit, 2048)
+
+        tracker.limit = None
+        self.assertIsNone(tracker.limit)
+
+        with self.assertRaises(OverflowError):
+            zstd.MemoryTracker(limit=-1)
+
+    def test_compressor(self):
+        tracker = zstd.MemoryTracker()
+        cctx = zstd.ZstdCompressor(level=10, memory_tracker=tracker)
+
+        self.assertGreater(tracker.live_bytes, 0)
+        self.assertGreater(tracker.allocation_count, 0)
+
+        frame = cctx.compress(b"foobar" * 8192)
+
+        # Contexts allocate their working memory when first used.
+        self.assertEqual(tracker.live_bytes, cctx.memory_size())
+        self.assertEqual(tracker.peak_bytes, tracker.live_bytes)
+
+        del cctx
+        self.assertEqual(tracker.live_bytes, 0)
+        self.assertGreater(tracker.peak_bytes, 0)
+
+        self.assertEqual(zstd.decompress(frame), b"foobar" * 8192)
+
+    def test_decompressor(self):
+        frame = zstd.ZstdCompressor().compress(b"foobar" * 8192)
+
+        tracker = zstd.MemoryTracker()
+        dctx = zstd.ZstdDecompressor(memory_tracker=tracker)
+
+        self.assertEqual(dctx.decompress(frame), b"foobar" * 8192)
+        self.assertEqual(tracker.live_bytes, dctx.memory_size())
+
+        del dctx
+        self.assertEqual(tracker.live_bytes, 0)
+
+    def test_shared(self):
+        tracker = zstd.MemoryTracker()
+
+        cctx = zstd.ZstdCompressor(memory_tracker=tracker)
+        dctx = zstd.ZstdDecompressor(memory_tracker=tracker)
+
+        dctx.decompress(cctx.compress(b"foobar" * 8192))
+
+        self.assertEqual(
+            tracker.live_bytes, cctx.memory_size() + dctx.memory_size()
+        )
+
+    def test_reset_peak(self):
+        tracker = zstd.MemoryTracker()
+        cctx = zstd.ZstdCompressor(level=10, memory_tracker=tracker)
+        cctx.compress(b"foobar" * 8192)
+        del cctx
+
+        self.assertGreater(tracker.peak_bytes, 0)
+        tracker.reset_peak()
+        self.assertEqual(tracker.peak_bytes, 0)
+
+    def test_multi_compress_to_buffer(self):
+        tracker = zstd.MemoryTracker()
+        cctx = zstd.ZstdCompressor(memory_tracker=tracker)
+        live = tracker.live_bytes
+
+        result = cctx.multi_compress_to_buffer([b"foo" * 1024] * 4, threads=2)
+        self.assertEqual(len(result), 4)
+
+        # Worker contexts were tracked and freed.
+        self.assertGreater(tracker.peak_bytes, live)
+        self.assertEqual(tracker.live_bytes, live)
+
+    def test_compressor_limit(self):
+        tracker = zstd.MemoryTracker(limit=64 * 1024)
+        cctx = zstd.ZstdCompressor(level=19, memory_tracker=tracker)
+
+        with self.assertRaisesRegex(MemoryError, "Allocation error"):
+            cctx.compress(b"foobar" * 8192)
+
+        self.assertGreater(tracker.failed_allocation_count, 0)
+        self.assertLessEqual(tracker.peak_bytes, 64 * 1024)
+
+        # Raising the limit allows the operation to succeed.
+        tracker.limit = None
+        frame = cctx.compress(b"foobar" * 8192)
+        self.assertEqual(zstd.decompress(frame), b"foobar" * 8192)
+
+    def test_stream_limit(self):
+        tracker = zstd.MemoryTracker(limit=64 * 1024)
+        cctx = zstd.ZstdCompressor(level=19, memory_tracker=tracker)
+
+        with self.assertRaises(MemoryError):
+            with cctx.stream_writer(io.BytesIO()) as compressor:
+                compressor.write(b"foobar" * 8192)
+
+    def test_context_limit(self):
+        tracker = zstd.MemoryTracker(limit=16)
+
+        with self.assertRaises(MemoryError):
+            zstd.ZstdCompressor(memory_tracker=tracker)
+
+        with self.assertRaises(MemoryError):
+            zstd.ZstdDecompressor(memory_tracker=tracker)
+
+        self.assertEqual(tracker.failed_allocation_count, 2)
+        self.assertEqual(tracker.live_bytes, 0)
+
+    def test_decompressor_limit(self):
+        cctx = zstd.ZstdCompressor(write_content_size=False)
+        frame = cctx.compress(b"foobar" * 8192)
+
+        tracker = zstd.MemoryTracker()
+        dctx = zstd.ZstdDecompressor(memory_tracker=tracker)
+        tracker.limit = tracker.live_bytes
+
+        # Streaming decompression needs a window buffer.
+        with self.assertRaisesRegex(MemoryError, "Allocation error"):
+            dctx.decompressobj().decompress(frame)
+
+    def test_process_wide(self):
+        self.assertIsNone(zstd.get_memory_tracker())
+
+        tracker = zstd.MemoryTracker()
+        zstd.set_memory_tracker(tracker)
+        self.assertIs(zstd.get_memory_tracker(), tracker)
+
+        cctx = zstd.ZstdCompressor()
+        dctx = zstd.ZstdDecompressor()
+        live = tracker.live_bytes
+        self.assertGreater(live, 0)
+
+        # An explicit tracker takes precedence.
+        other = zstd.MemoryTracker()
+        zstd.ZstdCompressor(memory_tracker=other)
+        self.assertEqual(tracker.live_bytes, live)
+
+        zstd.set_memory_tracker(None)
+        self.assertIsNone(zstd.get_memory_tracker())
+
+        # Existing objects keep using the tracker.
+        dctx.decompress(cctx.compress(b"foobar" * 8192))
+        self.assertGreater(tracker.live_bytes, live)
+
+        del cctx, dctx
+        self.assertEqual(tracker.live_bytes, 0)
+
+    def test_process_wide_dictionary(self):
+        samples = [b"foo%dbar%d" % (i, i) * 64 for i in range(128)]
+        d = zstd.train_dictionary(8192, samples)
+
+        tracker = zstd.MemoryTracker()
+        zstd.set_memory_tracker(tracker)
+
+        d.precompute_compress(level=3)
+        self.assertGreater(tracker.live_bytes, 0)
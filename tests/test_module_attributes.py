@@ -20,9 +20,33 @@ class TestModuleAttributes(unittest.TestCase):
             },
             "cffi": set(),
             "rust": {
+                "benchmark",
+                "buffer_from_file",
+                "buffer_offsets",
+                "buffer_segments_array",
+                "buffer_sequence_ops",
+                "buffer_serialization",
                 "buffer_types",
+                "cancellation",
+                "cli",
+                "compress_parallel_frames",
+                "compression_stats",
+                "decompress_parallel",
+                "decompression_reader_backward_seek",
+                "decompression_reader_readline",
+                "decompression_reader_seek_end",
+                "legacy_frames",
+                "max_output_size",
+                "memory_tracker",
                 "multi_compress_to_buffer",
+                "multi_compress_to_buffer_sequences",
+                "multi_compress_to_iter",
                 "multi_decompress_to_buffer",
+                "multi_decompress_to_buffer_errors",
+                "multi_threads_auto",
+                "progress_callbacks",
+                "static_contexts",
+                "summarize_frames",
             },
         }[zstd.backend]
 
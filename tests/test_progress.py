@@ -0,0 +1,232 @@
+import io
+import unittest
+
+import zstandard as zstd
+
+
+DATA = b"foobar" * 8192
+
+
+class Recorder:
+    def __init__(self, cancel_after=None):
+        self.calls = []
+        self.cancel_after = cancel_after
+
+    def __call__(self, read, written):
+        self.calls.append((read, written))
+
+        if self.cancel_after is not None:
+            if len(self.calls) >= self.cancel_after:
+                return zstd.PROGRESS_CANCEL
+
+
+@unittest.skipUnless(
+    "progress_callbacks" in zstd.backend_features,
+    "progress_callbacks feature not available",
+)
+class TestProgress(unittest.TestCase):
+    def test_exception(self):
+        self.assertTrue(issubclass(zstd.ZstdCancelledError, zstd.ZstdError))
+
+    def test_not_callable(self):
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaisesRegex(TypeError, "progress must be callable"):
+            cctx.copy_stream(io.BytesIO(DATA), io.BytesIO(), progress=True)
+
+        with self.assertRaisesRegex(TypeError, "progress must be callable"):
+            cctx.multi_compress_to_buffer([DATA], progress=True)
+
+    def test_compress_copy_stream(self):
+        progress = Recorder()
+        cctx = zstd.ZstdCompressor()
+
+        read, written = cctx.copy_stream(
+            io.BytesIO(DATA), io.BytesIO(), read_size=8192, progress=progress
+        )
+
+        self.assertEqual(len(progress.calls), 7)
+        self.assertEqual(
+            [c[0] for c in progress.calls[0:6]],
+            [8192 * i for i in range(1, 7)],
+        )
+        self.assertEqual(progress.calls[-1], (read, written))
+
+        for previous, current in zip(progress.calls, progress.calls[1:]):
+            self.assertLessEqual(previous[0], current[0])
+            self.assertLessEqual(previous[1], current[1])
+
+    def test_compress_copy_stream_interval(self):
+        progress = Recorder()
+        cctx = zstd.ZstdCompressor()
+
+        read, written = cctx.copy_stream(
+            io.BytesIO(DATA),
+            io.BytesIO(),
+            read_size=8192,
+            progress=progress,
+            progress_interval=16384,
+        )
+
+        self.assertEqual(
+            [c[0] for c in progress.calls], [16384, 32768, 49152, 49152]
+        )
+        self.assertEqual(progress.calls[-1], (read, written))
+
+    def test_compress_copy_stream_empty(self):
+        progress = Recorder()
+        cctx = zstd.ZstdCompressor()
+
+        read, written = cctx.copy_stream(
+            io.BytesIO(), io.BytesIO(), progress=progress
+        )
+
+        self.assertEqual(progress.calls, [(0, written)])
+
+    def test_compress_copy_stream_cancel(self):
+        progress = Recorder(cancel_after=2)
+        cctx = zstd.ZstdCompressor()
+
+        with self.assertRaisesRegex(
+            zstd.ZstdCancelledError, "cancelled by progress callback"
+        ):
+            cctx.copy_stream(
+                io.BytesIO(DATA),
+                io.BytesIO(),
+                read_size=8192,
+                progress=progress,
+            )
+
+        self.assertEqual(len(progress.calls), 2)
+
+    @unittest.skipUnless(
+        "compress_parallel_frames" in zstd.backend_features,
+        "compress_parallel_frames feature not available",
+    )
+    def test_compress_copy_stream_frames(self):
+        progress = Recorder()
+        cctx = zstd.ZstdCompressor()
+
+        dest = io.BytesIO()
+        read, written = cctx.copy_stream(
+            io.BytesIO(DATA),
+            dest,
+            frame_size=8192,
+            threads=2,
+            progress=progress,
+        )
+
+        self.assertEqual(progress.calls[-1], (read, written))
+        self.assertEqual(written, len(dest.getvalue()))
+
+        progress = Recorder(cancel_after=1)
+        with self.assertRaises(zstd.ZstdCancelledError):
+            cctx.copy_stream(
+                io.BytesIO(DATA),
+                io.BytesIO(),
+                read_size=8192,
+                frame_size=8192,
+                threads=2,
+                progress=progress,
+            )
+
+        self.assertEqual(len(progress.calls), 1)
+
+    def test_decompress_copy_stream(self):
+        frame = zstd.ZstdCompressor().compress(DATA)
+        progress = Recorder()
+        dctx = zstd.ZstdDecompressor()
+
+        read, written = dctx.copy_stream(
+            io.BytesIO(frame), io.BytesIO(), read_size=8, progress=progress
+        )
+
+        self.assertEqual((read, written), (len(frame), len(DATA)))
+        self.assertGreater(len(progress.calls), 1)
+        self.assertEqual(progress.calls[-1], (read, written))
+
+        progress = Recorder(cancel_after=1)
+        with self.assertRaises(zstd.ZstdCancelledError):
+            dctx.copy_stream(
+                io.BytesIO(frame),
+                io.BytesIO(),
+                read_size=8,
+                progress=progress,
+            )
+
+        self.assertEqual(len(progress.calls), 1)
+
+    @unittest.skipUnless(
+        "decompress_parallel" in zstd.backend_features,
+        "decompress_parallel feature not available",
+    )
+    def test_decompress_copy_stream_parallel(self):
+        frame = zstd.ZstdCompressor().compress(DATA)
+        progress = Recorder()
+        dctx = zstd.ZstdDecompressor()
+
+        read, written = dctx.copy_stream(
+            io.BytesIO(frame * 4),
+            io.BytesIO(),
+            read_size=len(frame),
+            threads=2,
+            progress=progress,
+        )
+
+        self.assertEqual(written, len(DATA) * 4)
+        self.assertEqual(progress.calls[-1], (read, written))
+
+        progress = Recorder(cancel_after=1)
+        with self.assertRaises(zstd.ZstdCancelledError):
+            dctx.copy_stream(
+                io.BytesIO(frame * 4),
+                io.BytesIO(),
+                read_size=len(frame),
+                threads=2,
+                progress=progress,
+            )
+
+        self.assertEqual(len(progress.calls), 1)
+
+    def test_multi_compress_to_buffer(self):
+        items = [DATA[0:i] for i in range(1, 20)]
+        cctx = zstd.ZstdCompressor()
+
+        for threads in (1, 2):
+            progress = Recorder()
+            result = cctx.multi_compress_to_buffer(
+                items, threads=threads, progress=progress
+            )
+
+            self.assertEqual(len(progress.calls), len(items) + 1)
+            self.assertEqual(
+                progress.calls[-1],
+                (sum(map(len, items)), sum(len(s) for s in result)),
+            )
+
+    def test_multi_compress_to_buffer_interval(self):
+        items = [DATA[0:i] for i in range(1, 11)]
+        cctx = zstd.ZstdCompressor()
+
+        progress = Recorder()
+        cctx.multi_compress_to_buffer(
+            items, progress=progress, progress_interval=4
+        )
+
+        # Reported after 4 and 8 items, then once more when done.
+        self.assertEqual(len(progress.calls), 3)
+        self.assertEqual(progress.calls[-1][0], sum(map(len, items)))
+
+    def test_multi_compress_to_buffer_cancel(self):
+        items = [DATA] * 64
+        cctx = zstd.ZstdCompressor()
+
+        for threads in (1, 2):
+            progress = Recorder(cancel_after=1)
+
+            with self.assertRaises(zstd.ZstdCancelledError):
+                cctx.multi_compress_to_buffer(
+                    items, threads=threads, progress=progress
+                )
+
+            self.assertEqual(len(progress.calls), 1)
@@ -0,0 +1,178 @@
+import io
+import unittest
+
+import zstandard as zstd
+
+
+DATA = b"foobar" * 8192
+
+
+@unittest.skipUnless(
+    "static_contexts" in zstd.backend_features,
+    "static_contexts feature not available",
+)
+class TestStaticCompressor(unittest.TestCase):
+    def params(self):
+        return zstd.ZstdCompressionParameters.from_level(
+            3, source_size=len(DATA)
+        )
+
+    def test_compress(self):
+        params = self.params()
+        size = params.estimated_compression_stream_size()
+
+        cctx = zstd.ZstdCompressor(
+            compression_params=params, static_context_size=size
+        )
+        self.assertGreaterEqual(cctx.memory_size(), size)
+
+        frame = cctx.compress(DATA)
+        self.assertEqual(zstd.decompress(frame), DATA)
+
+        # Contexts can be reused.
+        self.assertEqual(cctx.compress(DATA), frame)
+
+    def test_too_small(self):
+        with self.assertRaisesRegex(ValueError, "size is too small"):
+            zstd.ZstdCompressor(static_context_size=16)
+
+    def test_needs_more_memory(self):
+        size = self.params().estimated_compression_stream_size()
+        cctx = zstd.ZstdCompressor(level=19, static_context_size=size)
+
+        with self.assertRaisesRegex(MemoryError, "Allocation error"):
+            cctx.compress(DATA)
+
+        # Compression streams data, which needs buffers beyond the context.
+        params = self.params()
+        cctx = zstd.ZstdCompressor(
+            compression_params=params,
+            static_context_size=params.estimated_compression_context_size(),
+        )
+
+        with self.assertRaisesRegex(MemoryError, "Allocation error"):
+            cctx.compress(DATA)
+
+    def test_stream_writer(self):
+        params = self.params()
+        cctx = zstd.ZstdCompressor(
+            compression_params=params,
+            static_context_size=params.estimated_compression_stream_size(),
+        )
+
+        dest = io.BytesIO()
+        with cctx.stream_writer(dest, closefd=False) as compressor:
+            compressor.write(DATA)
+
+        dobj = zstd.ZstdDecompressor().decompressobj()
+        self.assertEqual(dobj.decompress(dest.getvalue()), DATA)
+
+    def test_precomputed_dict(self):
+        samples = [b"foo%dbar%d" % (i, i) * 64 for i in range(128)]
+        d = zstd.train_dictionary(8192, samples)
+        d.precompute_compress(level=3)
+
+        size = self.params().estimated_compression_stream_size()
+        cctx = zstd.ZstdCompressor(dict_data=d, static_context_size=size)
+
+        frame = cctx.compress(DATA)
+        dctx = zstd.ZstdDecompressor(dict_data=d)
+        self.assertEqual(dctx.decompress(frame), DATA)
+
+    def test_incompatible_arguments(self):
+        with self.assertRaisesRegex(
+            ValueError, "cannot define static_context_size and threads"
+        ):
+            zstd.ZstdCompressor(threads=2, static_context_size=2**20)
+
+        with self.assertRaisesRegex(
+            ValueError, "cannot define static_context_size and memory_tracker"
+        ):
+            zstd.ZstdCompressor(
+                memory_tracker=zstd.MemoryTracker(), static_context_size=2**20
+            )
+
+    def test_additional_contexts(self):
+        cctx = zstd.ZstdCompressor(static_context_size=2**21)
+
+        with self.assertRaisesRegex(ValueError, "cannot be used with a static"):
+            cctx.multi_compress_to_buffer([b"foo"])
+
+        with self.assertRaisesRegex(ValueError, "cannot be used with a static"):
+            cctx.multi_compress_to_iter([b"foo"])
+
+        with self.assertRaisesRegex(
+            ValueError, "frame_size cannot be used with a static"
+        ):
+            cctx.stream_writer(io.BytesIO(), frame_size=1024)
+
+    def test_memory_tracker_not_used(self):
+        tracker = zstd.MemoryTracker()
+        zstd.set_memory_tracker(tracker)
+
+        try:
+            cctx = zstd.ZstdCompressor(
+                compression_params=self.params(), static_context_size=2**21
+            )
+            cctx.compress(DATA)
+        finally:
+            zstd.set_memory_tracker(None)
+
+        self.assertEqual(tracker.allocation_count, 0)
+
+
+@unittest.skipUnless(
+    "static_contexts" in zstd.backend_features,
+    "static_contexts feature not available",
+)
+class TestStaticDecompressor(unittest.TestCase):
+    def test_decompress(self):
+        frame = zstd.ZstdCompressor().compress(DATA)
+
+        size = zstd.estimate_decompression_context_size()
+        dctx = zstd.ZstdDecompressor(static_context_size=size)
+        self.assertGreaterEqual(dctx.memory_size(), size)
+
+        self.assertEqual(dctx.decompress(frame), DATA)
+        self.assertEqual(dctx.decompress(frame), DATA)
+
+    def test_too_small(self):
+        with self.assertRaisesRegex(ValueError, "size is too small"):
+            zstd.ZstdDecompressor(static_context_size=16)
+
+    def test_needs_more_memory(self):
+        cctx = zstd.ZstdCompressor(write_content_size=False)
+        frame = cctx.compress(DATA)
+
+        size = zstd.estimate_decompression_context_size()
+        dctx = zstd.ZstdDecompressor(static_context_size=size)
+
+        # Frames without a content size need a window buffer.
+        with self.assertRaisesRegex(MemoryError, "Allocation error"):
+            dctx.decompressobj().decompress(frame)
+
+        dctx = zstd.ZstdDecompressor(static_context_size=size + 2**20)
+        self.assertEqual(dctx.decompressobj().decompress(frame), DATA)
+
+    def test_incompatible_arguments(self):
+        with self.assertRaisesRegex(
+            ValueError, "cannot define static_context_size and memory_tracker"
+        ):
+            zstd.ZstdDecompressor(
+                memory_tracker=zstd.MemoryTracker(), static_context_size=2**20
+            )
+
+    def test_additional_contexts(self):
+        frame = zstd.ZstdCompressor().compress(DATA)
+        dctx = zstd.ZstdDecompressor(static_context_size=2**20)
+
+        with self.assertRaisesRegex(ValueError, "cannot be used with a static"):
+            dctx.multi_decompress_to_buffer([frame])
+
+        with self.assertRaisesRegex(ValueError, "cannot be used with a static"):
+            dctx.decompress_parallel(frame)
+
+        with self.assertRaisesRegex(
+            ValueError, "threads cannot be used with a static"
+        ):
+            dctx.copy_stream(io.BytesIO(frame), io.BytesIO(), threads=2)
@@ -0,0 +1,144 @@
+import io
+import os
+import pathlib
+import struct
+import tempfile
+import unittest
+
+import zstandard as zstd
+
+
+def skippable_frame(data):
+    return struct.pack("<II", 0x184D2A50, len(data)) + data
+
+
+@unittest.skipUnless(
+    "summarize_frames" in zstd.backend_features,
+    "summarize_frames feature not available",
+)
+class TestSummarizeFrames(unittest.TestCase):
+    def test_empty(self):
+        summary = zstd.summarize_frames(b"")
+
+        self.assertEqual(summary.frame_count, 0)
+        self.assertEqual(summary.skippable_frame_count, 0)
+        self.assertEqual(summary.compressed_size, 0)
+        self.assertEqual(summary.decompressed_size, 0)
+        self.assertIsNone(summary.ratio)
+        self.assertIsNone(summary.checksum)
+        self.assertEqual(summary.window_size, 0)
+        self.assertEqual(summary.dict_ids, [])
+
+    def test_single_frame(self):
+        frame = zstd.ZstdCompressor(write_checksum=True).compress(
+            b"foobar" * 1000
+        )
+
+        summary = zstd.summarize_frames(frame)
+
+        self.assertEqual(summary.frame_count, 1)
+        self.assertEqual(summary.skippable_frame_count, 0)
+        self.assertEqual(summary.compressed_size, len(frame))
+        self.assertEqual(summary.decompressed_size, 6000)
+        self.assertAlmostEqual(summary.ratio, 6000 / len(frame))
+        self.assertEqual(summary.checksum, "XXH64")
+        self.assertEqual(
+            summary.window_size, zstd.get_frame_parameters(frame).window_size
+        )
+        self.assertEqual(summary.dict_ids, [])
+
+    def test_multiple_frames(self):
+        cctx = zstd.ZstdCompressor(write_checksum=True)
+        cctx_no_checksum = zstd.ZstdCompressor(write_checksum=False)
+
+        data = (
+            cctx.compress(b"foo")
+            + skippable_frame(b"metadata")
+            + cctx_no_checksum.compress(b"bar" * 100)
+        )
+
+        summary = zstd.summarize_frames(data)
+
+        self.assertEqual(summary.frame_count, 2)
+        self.assertEqual(summary.skippable_frame_count, 1)
+        self.assertEqual(summary.compressed_size, len(data))
+        self.assertEqual(summary.decompressed_size, 303)
+        self.assertEqual(summary.checksum, "mixed")
+
+    def test_unknown_content_size(self):
+        cctx = zstd.ZstdCompressor()
+        chunks = []
+        cobj = cctx.compressobj()
+        chunks.append(cobj.compress(b"foo" * 100))
+        chunks.append(cobj.flush())
+        data = b"".join(chunks) + cctx.compress(b"bar")
+
+        summary = zstd.summarize_frames(data)
+
+        self.assertEqual(summary.frame_count, 2)
+        self.assertIsNone(summary.decompressed_size)
+        self.assertIsNone(summary.ratio)
+
+    def test_dict_ids(self):
+        samples = []
+        for i in range(128):
+            samples.append(b"foo" * 64)
+            samples.append(b"bar" * 64)
+            samples.append(b"foobar" * 64)
+
+        d = zstd.train_dictionary(8192, samples)
+
+        data = zstd.ZstdCompressor(dict_data=d).compress(b"foo" * 64)
+        data += zstd.ZstdCompressor().compress(b"bar")
+        data += zstd.ZstdCompressor(dict_data=d).compress(b"bar" * 64)
+
+        summary = zstd.summarize_frames(data)
+        self.assertEqual(summary.dict_ids, [d.dict_id()])
+
+    def test_sources(self):
+        cctx = zstd.ZstdCompressor()
+        data = cctx.compress(b"foo") + cctx.compress(b"bar" * 100)
+
+        with tempfile.TemporaryDirectory() as td:
+            path = os.path.join(td, "data.zst")
+            with open(path, "wb") as fh:
+                fh.write(data)
+
+            for source in (path, pathlib.Path(path)):
+                summary = zstd.summarize_frames(source)
+                self.assertEqual(summary.frame_count, 2)
+                self.assertEqual(summary.compressed_size, len(data))
+
+            with open(path, "rb") as fh:
+                summary = zstd.summarize_frames(fh)
+                self.assertEqual(summary.frame_count, 2)
+
+        summary = zstd.summarize_frames(memoryview(data))
+        self.assertEqual(summary.decompressed_size, 303)
+
+    def test_file_position(self):
+        cctx = zstd.ZstdCompressor()
+        data = b"header" + cctx.compress(b"foo")
+
+        fh = io.BytesIO(data)
+        fh.seek(6)
+
+        summary = zstd.summarize_frames(fh)
+        self.assertEqual(summary.frame_count, 1)
+        self.assertEqual(summary.compressed_size, len(data) - 6)
+
+    def test_invalid(self):
+        frame = zstd.ZstdCompressor().compress(b"foo" * 100)
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "truncated frame at offset 0"
+        ):
+            zstd.summarize_frames(frame[:-1])
+
+        with self.assertRaisesRegex(
+            zstd.ZstdError, "error parsing frame header at offset 0"
+        ):
+            zstd.summarize_frames(b"garbage")
+
+        with self.assertRaises(TypeError):
+            zstd.summarize_frames(42)
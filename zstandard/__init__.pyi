@@ -7,8 +7,10 @@
 import os
 
 from typing import (
+    Any,
     BinaryIO,
     ByteString,
+    Callable,
     Generator,
     IO,
     Iterable,
@@ -17,6 +19,7 @@ from typing import (
     Set,
     Tuple,
     Union,
+    overload,
 )
 
 FLUSH_BLOCK: int
@@ -25,6 +28,8 @@ FLUSH_FRAME: int
 COMPRESSOBJ_FLUSH_FINISH: int
 COMPRESSOBJ_FLUSH_BLOCK: int
 
+PROGRESS_CANCEL: object
+
 CONTENTSIZE_UNKNOWN: int
 CONTENTSIZE_ERROR: int
 
@@ -85,11 +90,14 @@ backend_features: Set[str]
 __version__: str
 
 class ZstdError(Exception): ...
+class ZstdOutputLimitError(ZstdError): ...
+class ZstdCancelledError(ZstdError): ...
 
 class BufferSegment(object):
     offset: int
     def __len__(self) -> int: ...
     def tobytes(self) -> bytes: ...
+    def __eq__(self, other: object) -> bool: ...
 
 class BufferSegments(object):
     def __len__(self) -> int: ...
@@ -99,15 +107,42 @@ class BufferWithSegments(object):
     size: int
     def __init__(self, data: ByteString, segments: ByteString): ...
     def __len__(self) -> int: ...
+    @overload
     def __getitem__(self, i: int) -> BufferSegment: ...
+    @overload
+    def __getitem__(self, i: slice) -> "BufferWithSegments": ...
+    def __iter__(self) -> Generator[BufferSegment, None, None]: ...
     def segments(self): ...
     def tobytes(self) -> bytes: ...
+    @staticmethod
+    def from_file(
+        path: Union[bytes, str, os.PathLike], segments: ByteString
+    ) -> "BufferWithSegments": ...
+    @staticmethod
+    def from_offsets(
+        data: ByteString, offsets: ByteString
+    ) -> "BufferWithSegments": ...
+    def offsets(self, large: bool = ...) -> bytes: ...
 
 class BufferWithSegmentsCollection(object):
     def __init__(self, *args): ...
     def __len__(self) -> int: ...
     def __getitem__(self, i: int) -> BufferSegment: ...
     def size(self) -> int: ...
+    def __iter__(self) -> Generator[BufferSegment, None, None]: ...
+    def __add__(
+        self, other: "BufferWithSegmentsCollection"
+    ) -> "BufferWithSegmentsCollection": ...
+    def extend(self, other: "BufferWithSegmentsCollection") -> None: ...
+    def flatten(self) -> BufferWithSegments: ...
+    def to_bytes(self) -> bytes: ...
+    def to_file(self, path: Union[bytes, str, os.PathLike]) -> None: ...
+    @staticmethod
+    def from_bytes(data: ByteString) -> "BufferWithSegmentsCollection": ...
+    @staticmethod
+    def from_file(
+        path: Union[bytes, str, os.PathLike]
+    ) -> "BufferWithSegmentsCollection": ...
 
 class ZstdCompressionParameters(object):
     @staticmethod
@@ -181,6 +216,7 @@ class ZstdCompressionParameters(object):
     @property
     def threads(self) -> int: ...
     def estimated_compression_context_size(self) -> int: ...
+    def estimated_compression_stream_size(self) -> int: ...
 
 class CompressionParameters(ZstdCompressionParameters): ...
 
@@ -271,9 +307,15 @@ class ZstdCompressor(object):
         write_content_size: Optional[bool] = ...,
         write_dict_id: Optional[bool] = ...,
         threads: int = ...,
+        memory_tracker: Optional[MemoryTracker] = ...,
+        static_context_size: Optional[int] = ...,
     ): ...
     def memory_size(self) -> int: ...
-    def compress(self, data: ByteString) -> bytes: ...
+    def compress(
+        self,
+        data: ByteString,
+        cancellation_token: Optional[CancellationToken] = ...,
+    ) -> bytes: ...
     def compressobj(self, size: int = ...) -> ZstdCompressionObj: ...
     def chunker(
         self, size: int = ..., chunk_size: int = ...
@@ -285,6 +327,11 @@ class ZstdCompressor(object):
         size: int = ...,
         read_size: int = ...,
         write_size: int = ...,
+        frame_size: Optional[int] = ...,
+        threads: Optional[int] = ...,
+        stats: Optional[CompressionStats] = ...,
+        progress: Optional[Callable[[int, int], Any]] = ...,
+        progress_interval: Optional[int] = ...,
     ) -> Tuple[int, int]: ...
     def stream_reader(
         self,
@@ -302,6 +349,9 @@ class ZstdCompressor(object):
         write_return_read: bool = ...,
         *,
         closefd: bool = ...,
+        frame_size: Optional[int] = ...,
+        threads: Optional[int] = ...,
+        stats: Optional[CompressionStats] = ...,
     ) -> ZstdCompressionWriter: ...
     def read_to_iter(
         self,
@@ -314,10 +364,23 @@ class ZstdCompressor(object):
     def multi_compress_to_buffer(
         self,
         data: Union[
-            BufferWithSegments, BufferWithSegmentsCollection, List[ByteString],
+            BufferWithSegments,
+            BufferWithSegmentsCollection,
+            Iterable[ByteString],
+            Tuple[ByteString, ByteString],
         ],
-        threads: int = ...,
+        threads: Union[int, str] = ...,
+        progress: Optional[Callable[[int, int], Any]] = ...,
+        progress_interval: Optional[int] = ...,
+        cancellation_token: Optional[CancellationToken] = ...,
     ) -> BufferWithSegmentsCollection: ...
+    def multi_compress_to_iter(
+        self,
+        data: Iterable[ByteString],
+        threads: int = ...,
+        max_in_flight: Optional[int] = ...,
+        ordered: bool = ...,
+    ) -> Generator[Union[bytes, Tuple[int, bytes]], None, None]: ...
 
 class ZstdDecompressionObj(object):
     def decompress(self, data: ByteString) -> bytes: ...
@@ -348,6 +411,7 @@ class ZstdDecompressionReader(BinaryIO):
     def read1(self, size: int = ...) -> bytes: ...
     def readinto1(self, b) -> int: ...
     def seek(self, pos: int, whence: int = ...) -> int: ...
+    def decompressed_size(self, full_scan: bool = ...) -> Optional[int]: ...
 
 class ZstdDecompressionWriter(BinaryIO):
     def __enter__(self) -> "ZstdDecompressionWriter": ...
@@ -379,6 +443,10 @@ class ZstdDecompressor(object):
         dict_data: Optional[ZstdCompressionDict] = ...,
         max_window_size: int = ...,
         format: int = ...,
+        allow_legacy: bool = ...,
+        memory_tracker: Optional[MemoryTracker] = ...,
+        static_context_size: Optional[int] = ...,
+        max_output_size: Optional[int] = ...,
     ): ...
     def memory_size(self) -> int: ...
     def decompress(
@@ -391,6 +459,7 @@ class ZstdDecompressor(object):
         read_across_frames: bool = ...,
         *,
         closefd=False,
+        seek_checkpoints: bool = ...,
     ) -> ZstdDecompressionReader: ...
     def decompressobj(self, write_size: int = ...) -> ZstdDecompressionObj: ...
     def read_to_iter(
@@ -414,29 +483,105 @@ class ZstdDecompressor(object):
         ofh: IO[bytes],
         read_size: int = ...,
         write_size: int = ...,
+        threads: int = ...,
+        progress: Optional[Callable[[int, int], Any]] = ...,
+        progress_interval: Optional[int] = ...,
     ) -> Tuple[int, int]: ...
     def decompress_content_dict_chain(
         self, frames: list[ByteString]
     ) -> bytes: ...
+    def decompress_parallel(
+        self, data: ByteString, threads: int = ...
+    ) -> bytes: ...
     def multi_decompress_to_buffer(
         self,
         frames: Union[
             BufferWithSegments, BufferWithSegmentsCollection, List[ByteString],
         ],
         decompressed_sizes: ByteString = ...,
-        threads: int = ...,
-    ) -> BufferWithSegmentsCollection: ...
+        threads: Union[int, str] = ...,
+        errors: str = ...,
+    ) -> Union[
+        BufferWithSegmentsCollection,
+        Tuple[BufferWithSegmentsCollection, List[Tuple[int, Exception]]],
+    ]: ...
 
 class FrameParameters(object):
     content_size: int
     window_size: int
     dict_id: int
     has_checksum: bool
+    legacy_version: int
+
+class FramesSummary(object):
+    frame_count: int
+    skippable_frame_count: int
+    compressed_size: int
+    decompressed_size: Optional[int]
+    ratio: Optional[float]
+    checksum: Optional[str]
+    window_size: int
+    dict_ids: List[int]
 
+class BenchmarkResult(object):
+    level: Optional[int]
+    compression_params: Optional[ZstdCompressionParameters]
+    size: int
+    compressed_size: int
+    ratio: float
+    compress_time: float
+    decompress_time: float
+    compress_speed: float
+    decompress_speed: float
+    compress_iterations: int
+    decompress_iterations: int
+    compressor_memory_size: int
+    decompressor_memory_size: int
+
+class CompressionStats(object):
+    bytes_read: int
+    bytes_written: int
+    frames: int
+    flushes: int
+    zstd_time: float
+    io_time: float
+    compression_level: Optional[int]
+    window_log: Optional[int]
+    def __init__(self): ...
+
+class CancellationToken(object):
+    cancelled: bool
+    def __init__(self): ...
+    def cancel(self) -> None: ...
+
+class MemoryTracker(object):
+    live_bytes: int
+    peak_bytes: int
+    allocation_count: int
+    failed_allocation_count: int
+    limit: Optional[int]
+    def __init__(self, limit: Optional[int] = ...): ...
+    def reset_peak(self) -> None: ...
+
+def benchmark(
+    data: Union[ByteString, Iterable[ByteString]],
+    levels: Optional[Iterable[int]] = ...,
+    params: Optional[Iterable[ZstdCompressionParameters]] = ...,
+    dict_data: Optional[ZstdCompressionDict] = ...,
+    threads: int = ...,
+    min_time: float = ...,
+    max_iterations: Optional[int] = ...,
+) -> List[BenchmarkResult]: ...
 def estimate_decompression_context_size() -> int: ...
 def frame_content_size(data: ByteString) -> int: ...
 def frame_header_size(data: ByteString) -> int: ...
+def frame_legacy_version(data: ByteString) -> int: ...
 def get_frame_parameters(data: ByteString) -> FrameParameters: ...
+def get_memory_tracker() -> Optional[MemoryTracker]: ...
+def set_memory_tracker(tracker: Optional[MemoryTracker]) -> None: ...
+def summarize_frames(
+    source: Union[str, os.PathLike, BinaryIO, ByteString]
+) -> FramesSummary: ...
 def train_dictionary(
     dict_size: int,
     samples: list[ByteString],
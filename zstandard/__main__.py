@@ -0,0 +1,29 @@
+# Copyright (c) 2021-present, Gregory Szorc
+# All rights reserved.
+#
+# This software may be modified and distributed under the terms
+# of the BSD license. See the LICENSE file for details.
+
+"""Command line interface to zstandard.
+
+The command line interface is implemented by the Rust backend.
+"""
+
+import sys
+
+
+def main():
+    try:
+        from . import backend_rust
+    except ImportError:
+        print(
+            "zstandard: command line interface requires the Rust backend",
+            file=sys.stderr,
+        )
+        return 1
+
+    return backend_rust.cli_main(sys.argv[1:])
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -10,10 +10,13 @@ from __future__ import absolute_import, unicode_literals
 
 # This should match what the C extension exports.
 __all__ = [
+    "BenchmarkResult",
     "BufferSegment",
     "BufferSegments",
     "BufferWithSegments",
     "BufferWithSegmentsCollection",
+    "CancellationToken",
+    "CompressionStats",
     "ZstdCompressionChunker",
     "ZstdCompressionDict",
     "ZstdCompressionObj",
@@ -25,17 +28,27 @@ __all__ = [
     "ZstdDecompressionReader",
     "ZstdDecompressionWriter",
     "ZstdDecompressor",
+    "ZstdCancelledError",
     "ZstdError",
+    "ZstdOutputLimitError",
     "FrameParameters",
+    "FramesSummary",
+    "MemoryTracker",
     "backend_features",
+    "benchmark",
     "estimate_decompression_context_size",
     "frame_content_size",
     "frame_header_size",
+    "frame_legacy_version",
     "get_frame_parameters",
+    "get_memory_tracker",
+    "set_memory_tracker",
+    "summarize_frames",
     "train_dictionary",
     # Constants.
     "FLUSH_BLOCK",
     "FLUSH_FRAME",
+    "PROGRESS_CANCEL",
     "COMPRESSOBJ_FLUSH_FINISH",
     "COMPRESSOBJ_FLUSH_BLOCK",
     "ZSTD_VERSION",
@@ -158,6 +171,9 @@ FLUSH_FRAME = 1
 COMPRESSOBJ_FLUSH_FINISH = 0
 COMPRESSOBJ_FLUSH_BLOCK = 1
 
+# Returned by progress callbacks to cancel the operation.
+PROGRESS_CANCEL = object()
+
 
 def _cpu_count():
     # os.cpu_count() was introducd in Python 3.4.
@@ -198,6 +214,25 @@ class BufferSegment:
         """Obtain bytes copy of this segment."""
         raise NotImplementedError()
 
+    def __eq__(self, other):
+        """Whether the segment holds the same bytes as another object.
+
+        (Experimental. Not available in CFFI backend.)
+
+        ``other`` can be another ``BufferSegment`` or any bytes-like object.
+        """
+        raise NotImplementedError()
+
+    def __hash__(self):
+        """Hash of the segment's bytes.
+
+        (Experimental. Not available in CFFI backend.)
+
+        Equal to the hash of ``bytes`` holding the same data, so segments and
+        ``bytes`` can be used interchangeably as dictionary keys.
+        """
+        raise NotImplementedError()
+
 
 class BufferSegments:
     """Represents an array of ``(offset, length)`` integers.
@@ -206,7 +241,10 @@ class BufferSegments:
 
     The array members are 64-bit unsigned integers using host/native bit order.
 
-    Instances conform to the buffer protocol.
+    Instances conform to the buffer protocol. The Rust backend exposes a
+    read-only, 2 dimensional ``(N, 2)`` buffer of ``Q`` (``uint64``) items, so
+    ``numpy.asarray(b.segments())`` and ``memoryview(b.segments()).tolist()``
+    yield one ``[offset, length]`` row per segment without unpacking.
     """
 
 
@@ -244,10 +282,23 @@ class BufferWithSegments:
 
         The returned object references memory within this buffer.
 
+        If ``i`` is a slice, a new ``BufferWithSegments`` holding the selected
+        segments is returned. It references the span of memory covering those
+        segments without copying it. Offsets of its segments are relative to
+        the start of that span. (Slicing is experimental and not available in
+        CFFI backend.)
+
         :param i:
-           Integer index of segment to retrieve.
+           Integer index or slice of segments to retrieve.
         :return:
-           :py:class:`BufferSegment`
+           :py:class:`BufferSegment` or :py:class:`BufferWithSegments`
+        """
+        raise NotImplementedError()
+
+    def __iter__(self):
+        """Iterate over segments in the buffer.
+
+        (Experimental. Not available in CFFI backend.)
         """
         raise NotImplementedError()
 
@@ -263,6 +314,71 @@ class BufferWithSegments:
         """Obtain bytes copy of this instance."""
         raise NotImplementedError()
 
+    @staticmethod
+    def from_file(path, segments):
+        """Construct an instance backed by a memory mapped file.
+
+        (Experimental. Not available in CFFI backend.)
+
+        The file is mapped read-only and segment data is read from disk on
+        demand instead of being loaded into memory up front. This allows APIs
+        like :py:meth:`ZstdDecompressor.multi_decompress_to_buffer` to operate
+        directly on on-disk data.
+
+        The file must not be truncated while the instance or any object
+        referencing its memory is alive.
+
+        :param path:
+           Path of the file to map.
+        :param segments:
+           Array of ``(offset, length)`` 64-bit unsigned native-endian
+           integers defining segments within the file, as accepted by the
+           constructor.
+        :return:
+           :py:class:`BufferWithSegments`
+        """
+        raise NotImplementedError()
+
+    @staticmethod
+    def from_offsets(data, offsets):
+        """Construct an instance from Arrow style offsets.
+
+        (Experimental. Not available in CFFI backend.)
+
+        ``offsets`` is an array of N + 1 32 or 64-bit integers delimiting N
+        segments within ``data``. Segment ``i`` is
+        ``data[offsets[i]:offsets[i + 1]]``. This is the layout Arrow uses for
+        variable length binary arrays, so the data and offsets buffers of such
+        an array can be passed directly.
+
+        :param data:
+           Bytes-like object holding segment data.
+        :param offsets:
+           Object exposing a buffer of 32 or 64-bit integers, such as a numpy
+           array or ``array.array``.
+        :return:
+           :py:class:`BufferWithSegments`
+        """
+        raise NotImplementedError()
+
+    def offsets(self, large=False):
+        """Obtain Arrow style offsets describing segments.
+
+        (Experimental. Not available in CFFI backend.)
+
+        Segments must be adjacent to each other, with each segment starting
+        where the previous one ends. This is always the case for instances
+        constructed with :py:meth:`from_offsets`.
+
+        :param large:
+           Whether to emit 64-bit offsets, as used by Arrow's ``large_binary``
+           type. By default, 32-bit offsets are emitted and ``ValueError`` is
+           raised if they can't represent the data.
+        :return:
+           ``bytes`` holding N + 1 native-endian integers.
+        """
+        raise NotImplementedError()
+
 
 class BufferWithSegmentsCollection:
     """A virtual spanning view over multiple BufferWithSegments.
@@ -285,11 +401,122 @@ class BufferWithSegmentsCollection:
         """Obtain the ``BufferSegment`` at an offset."""
         raise NotImplementedError()
 
+    def __iter__(self):
+        """Iterate over segments within all ``BufferWithSegments``.
+
+        (Experimental. Not available in CFFI backend.)
+        """
+        raise NotImplementedError()
+
+    def __add__(self, other):
+        """Combine the segments of 2 collections into a new collection.
+
+        (Experimental. Not available in CFFI backend.)
+
+        Buffers are shared with the operands and are not copied.
+        """
+        raise NotImplementedError()
+
+    def extend(self, other):
+        """Append the segments of another collection to this one.
+
+        (Experimental. Not available in CFFI backend.)
+
+        Buffers are shared with ``other`` and are not copied.
+        """
+        raise NotImplementedError()
+
+    def flatten(self):
+        """Copy all segments into a single ``BufferWithSegments``.
+
+        (Experimental. Not available in CFFI backend.)
+
+        Segments are laid out adjacent to each other in order, so the result
+        can be passed to APIs wanting a single contiguous buffer and its
+        offsets can be obtained with :py:meth:`BufferWithSegments.offsets`.
+
+        :return:
+           :py:class:`BufferWithSegments`
+        """
+        raise NotImplementedError()
+
+    def to_bytes(self):
+        """Serialize the collection to bytes.
+
+        (Experimental. Not available in CFFI backend.)
+
+        The serialized form is a single contiguous layout consisting of a
+        small header, the segment tables of every buffer, and the data of
+        every buffer, in order. All integers are little-endian and every
+        section, including the data of each buffer, is zero padded to 8 byte
+        alignment, so the layout is suitable for memory mapping and for
+        exchanging between machines.
+
+        Use :py:meth:`from_bytes` or :py:meth:`from_file` to read it back.
+
+        :return:
+           ``bytes``
+        """
+        raise NotImplementedError()
+
+    def to_file(self, path):
+        """Serialize the collection to a file.
+
+        (Experimental. Not available in CFFI backend.)
+
+        Writes the same data as :py:meth:`to_bytes` without first assembling
+        it in memory.
+
+        :param path:
+           Path of the file to write. Existing files are overwritten.
+        """
+        raise NotImplementedError()
+
+    @staticmethod
+    def from_bytes(data):
+        """Construct an instance from data produced by :py:meth:`to_bytes`.
+
+        (Experimental. Not available in CFFI backend.)
+
+        Buffers in the returned collection reference memory within ``data``
+        without copying it.
+
+        :param data:
+           Bytes-like object holding serialized data.
+        :return:
+           :py:class:`BufferWithSegmentsCollection`
+        """
+        raise NotImplementedError()
+
+    @staticmethod
+    def from_file(path):
+        """Construct an instance from a file written by :py:meth:`to_file`.
+
+        (Experimental. Not available in CFFI backend.)
+
+        The file is memory mapped read-only and buffers in the returned
+        collection reference the mapping without copying it.
+
+        :param path:
+           Path of the file to read.
+        :return:
+           :py:class:`BufferWithSegmentsCollection`
+        """
+        raise NotImplementedError()
+
 
 class ZstdError(Exception):
     pass
 
 
+class ZstdOutputLimitError(ZstdError):
+    """Raised when decompressed output exceeds a ``max_output_size`` limit."""
+
+
+class ZstdCancelledError(ZstdError):
+    """Raised when an operation is cancelled before completing."""
+
+
 def _zstd_error(zresult):
     # Resolves to bytes on Python 2 and 3. We use the string for formatting
     # into error messages, which will be literal unicode. So convert it to
@@ -593,6 +820,14 @@ class ZstdCompressionParameters(object):
         """Estimated size in bytes needed to compress with these parameters."""
         return lib.ZSTD_estimateCCtxSize_usingCCtxParams(self._params)
 
+    def estimated_compression_stream_size(self):
+        """Estimated size in bytes needed for streaming compression.
+
+        This includes the buffers needed by streaming APIs in addition to the
+        compression context.
+        """
+        return lib.ZSTD_estimateCStreamSize_usingCCtxParams(self._params)
+
 
 def estimate_decompression_context_size():
     """Estimate the memory size requirements for a decompressor instance.
@@ -603,6 +838,95 @@ def estimate_decompression_context_size():
     return lib.ZSTD_estimateDCtxSize()
 
 
+class BenchmarkResult:
+    """Result of benchmarking a compression configuration.
+
+    (Experimental. Not available in CFFI backend.)
+
+    Instances are returned by :py:func:`benchmark` and have the following
+    attributes:
+
+    ``level``
+       Integer compression level. ``None`` for results of ``params``.
+
+    ``compression_params``
+       :py:class:`ZstdCompressionParameters` that were benchmarked. ``None``
+       for results of ``levels``.
+
+    ``size``
+       Integer total size of input data.
+
+    ``compressed_size``
+       Integer total size of compressed frames.
+
+    ``ratio``
+       Float of ``size / compressed_size``.
+
+    ``compress_time``, ``decompress_time``
+       Float seconds taken by the fastest iteration compressing or
+       decompressing all data.
+
+    ``compress_speed``, ``decompress_speed``
+       Float speed of the fastest iteration in MB/s, measured against the
+       size of input data. MB is 1,000,000 bytes.
+
+    ``compress_iterations``, ``decompress_iterations``
+       Integer number of times data was compressed or decompressed.
+
+    ``compressor_memory_size``, ``decompressor_memory_size``
+       Integer memory used by the compression and decompression contexts, as
+       reported by ``memory_size()``.
+    """
+
+
+def benchmark(
+    data,
+    levels=None,
+    params=None,
+    dict_data=None,
+    threads=0,
+    min_time=1.0,
+    max_iterations=None,
+):
+    """Measure compression and decompression of data.
+
+    (Experimental. Not available in CFFI backend.)
+
+    Each compression level in ``levels`` and each
+    :py:class:`ZstdCompressionParameters` in ``params`` is benchmarked in
+    turn. If neither is specified, level 3 is benchmarked.
+
+    Data is compressed and decompressed with the GIL released. Data is first
+    verified to round trip. Then compression and decompression are each
+    repeated until ``min_time`` seconds have elapsed and the fastest
+    iteration hasn't improved for several iterations, or until
+    ``max_iterations`` is reached. Speeds are reported for the fastest
+    iteration.
+
+    :param data:
+       Bytes-like object or iterable of bytes-like objects. Each object is
+       compressed into its own frame, so data like small records can be
+       benchmarked as it would be compressed.
+    :param levels:
+       Iterable of integer compression levels.
+    :param params:
+       Iterable of :py:class:`ZstdCompressionParameters`.
+    :param dict_data:
+       :py:class:`ZstdCompressionDict` to compress and decompress with.
+    :param threads:
+       Number of compression threads to use for ``levels``. Negative uses
+       all logical CPUs. ``params`` define their own thread count.
+    :param min_time:
+       Minimum float seconds to spend measuring each operation.
+    :param max_iterations:
+       Maximum number of times to repeat each operation.
+    :return:
+       List of :py:class:`BenchmarkResult`, for ``levels`` followed by
+       ``params``.
+    """
+    raise NotImplementedError()
+
+
 def _set_compression_parameter(params, param, value):
     zresult = lib.ZSTD_CCtxParams_setParameter(params, param, value)
     if lib.ZSTD_isError(zresult):
@@ -1777,6 +2101,25 @@ class ZstdCompressor(object):
        compression operations are performed on multiple threads. The default
        value (0) disables multi-threaded compression. A value of ``-1`` means
        to set the number of threads to the number of detected logical CPUs.
+    :param memory_tracker:
+       A :py:class:`MemoryTracker` accounting for memory allocated by zstd
+       for this compressor. Defaults to the process-wide tracker installed
+       with :py:func:`set_memory_tracker`, if any.
+
+       (Experimental. Not available in CFFI backend.)
+    :param static_context_size:
+       If defined, the compression context is confined to a buffer of this
+       many bytes allocated up front. Operations needing more memory raise
+       ``MemoryError`` instead of allocating. The buffer is not accounted to
+       any :py:class:`MemoryTracker`.
+
+       :py:meth:`ZstdCompressionParameters.estimated_compression_stream_size`
+       gives a suitable size. Cannot be combined with ``threads`` or
+       ``memory_tracker``. APIs that create additional contexts, such as
+       ``multi_compress_to_buffer()`` or ``frame_size``, raise
+       ``ValueError``.
+
+       (Experimental. Not available in CFFI backend.)
     """
 
     def __init__(
@@ -1788,7 +2131,12 @@ class ZstdCompressor(object):
         write_content_size=None,
         write_dict_id=None,
         threads=0,
+        memory_tracker=None,
+        static_context_size=None,
     ):
+        if memory_tracker is not None or static_context_size is not None:
+            raise NotImplementedError()
+
         if level > lib.ZSTD_maxCLevel():
             raise ValueError(
                 "level must be less than %d" % lib.ZSTD_maxCLevel()
@@ -1906,7 +2254,7 @@ class ZstdCompressor(object):
         """
         return lib.ZSTD_sizeof_CCtx(self._cctx)
 
-    def compress(self, data):
+    def compress(self, data, cancellation_token=None):
         """
         Compress data in a single operation.
 
@@ -1918,14 +2266,27 @@ class ZstdCompressor(object):
         values can result in excessive memory usage. For this reason, one of the
         streaming based APIs is preferred for larger values.
 
+        Large inputs are compressed in chunks. Between chunks, pending signal
+        handlers run, so e.g. Ctrl-C raises ``KeyboardInterrupt``, and the
+        optional cancellation token is checked. The token is also checked
+        before compression starts.
+
         :param data:
            Source data to compress
+        :param cancellation_token:
+           A :py:class:`CancellationToken` that aborts compression with
+           :py:class:`ZstdCancelledError` once cancelled.
+
+           (Experimental. Not available in CFFI backend.)
         :return:
            Compressed data
 
         >>> cctx = zstandard.ZstdCompressor()
         >>> compressed = cctx.compress(b"data to compress")
         """
+        if cancellation_token is not None:
+            raise NotImplementedError()
+
         lib.ZSTD_CCtx_reset(self._cctx, lib.ZSTD_reset_session_only)
 
         data_buffer = ffi.from_buffer(data)
@@ -2030,6 +2391,11 @@ class ZstdCompressor(object):
         size=-1,
         read_size=COMPRESSION_RECOMMENDED_INPUT_SIZE,
         write_size=COMPRESSION_RECOMMENDED_OUTPUT_SIZE,
+        frame_size=None,
+        threads=None,
+        stats=None,
+        progress=None,
+        progress_interval=None,
     ):
         """
         Copy data between 2 streams while compressing it.
@@ -2059,6 +2425,21 @@ class ZstdCompressor(object):
         >>> cctx = zstandard.ZstdCompressor()
         >>> read_count, write_count = cctx.copy_stream(ifh, ofh)
 
+        Input can be split into independent frames of ``frame_size`` bytes
+        that are compressed on multiple threads:
+
+        >>> cctx = zstandard.ZstdCompressor()
+        >>> cctx.copy_stream(ifh, ofh, frame_size=4 * 1024 * 1024, threads=4)
+
+        Output is in the format produced by ``pzstd``: each frame is preceded
+        by a skippable frame recording its compressed size. It can be
+        decompressed by any zstd decoder and can be decompressed in parallel
+        by ``pzstd -d`` and :py:meth:`ZstdDecompressor.decompress_parallel`.
+        This differs from the compressor's ``threads`` argument, which
+        produces a single frame that can only be decompressed serially.
+        Splitting input into frames reduces the compression ratio because
+        frames can't reference data in other frames.
+
         :param ifh:
            Source stream to read from
         :param ofh:
@@ -2070,10 +2451,50 @@ class ZstdCompressor(object):
            Chunk sizes that source stream should be ``read()`` from.
         :param write_size:
            Chunk sizes that destination stream should be ``write()`` to.
+        :param frame_size:
+           Size in bytes of input to compress into each independent frame.
+
+           (Experimental. Not available in CFFI backend.)
+
+           Cannot be combined with ``size``.
+        :param threads:
+           How many threads to use to compress independent frames.
+
+           (Experimental. Not available in CFFI backend.)
+
+           Requires ``frame_size``. Defaults to the number of logical CPUs
+           on the machine, which is also used for negative values.
+        :param stats:
+           A :py:class:`CompressionStats` to record statistics of the
+           operation into.
+
+           (Experimental. Not available in CFFI backend.)
+        :param progress:
+           Callable receiving the numbers of bytes read and written so far.
+
+           (Experimental. Not available in CFFI backend.)
+
+           Called as compression progresses and once more when it completes.
+           Returning :py:data:`PROGRESS_CANCEL` aborts the operation with
+           :py:class:`ZstdCancelledError`.
+        :param progress_interval:
+           Minimum number of bytes read between calls to ``progress``.
+           Defaults to calling ``progress`` after every read.
+
+           (Experimental. Not available in CFFI backend.)
         :return:
            2-tuple of ints of bytes read and written, respectively.
         """
 
+        if (
+            frame_size is not None
+            or threads is not None
+            or stats is not None
+            or progress is not None
+            or progress_interval is not None
+        ):
+            raise NotImplementedError()
+
         if not hasattr(ifh, "read"):
             raise ValueError("first argument must have a read() method")
         if not hasattr(ofh, "write"):
@@ -2202,6 +2623,9 @@ class ZstdCompressor(object):
         write_size=COMPRESSION_RECOMMENDED_OUTPUT_SIZE,
         write_return_read=True,
         closefd=True,
+        frame_size=None,
+        threads=None,
+        stats=None,
     ):
         """
         Create a stream that will write compressed data into another stream.
@@ -2227,9 +2651,33 @@ class ZstdCompressor(object):
            consumed from the input.
         :param closefd:
            Whether to ``close`` the ``writer`` when this stream is closed.
+        :param frame_size:
+           Size in bytes of input to compress into each independent frame.
+
+           (Experimental. Not available in CFFI backend.)
+
+           Output is in the ``pzstd`` format. See :py:meth:`copy_stream`.
+           Input is buffered until enough is available to keep all threads
+           busy. ``flush()`` ends the current frame regardless of the flush
+           mode, so frequent flushing produces small frames.
+        :param threads:
+           How many threads to use to compress independent frames.
+
+           (Experimental. Not available in CFFI backend.)
+
+           Requires ``frame_size``. Defaults to the number of logical CPUs
+           on the machine, which is also used for negative values.
+        :param stats:
+           A :py:class:`CompressionStats` to record statistics of the stream
+           into. Statistics are updated as data is written.
+
+           (Experimental. Not available in CFFI backend.)
         :return:
            :py:class:`ZstdCompressionWriter`
         """
+        if frame_size is not None or threads is not None or stats is not None:
+            raise NotImplementedError()
+
         if not hasattr(writer, "write"):
             raise ValueError("must pass an object with a write() method")
 
@@ -2411,7 +2859,14 @@ class ZstdCompressor(object):
             if zresult == 0:
                 break
 
-    def multi_compress_to_buffer(self, data, threads=-1):
+    def multi_compress_to_buffer(
+        self,
+        data,
+        threads=-1,
+        progress=None,
+        progress_interval=None,
+        cancellation_token=None,
+    ):
         """
         Compress multiple pieces of data as a single function call.
 
@@ -2421,13 +2876,27 @@ class ZstdCompressor(object):
         as as possible with as little overhead as possible.
 
         Data to be compressed can be passed as a ``BufferWithSegmentsCollection``,
-        a ``BufferWithSegments``, or a list containing byte like objects. Each
-        element of the container will be compressed individually using the
-        configured parameters on the ``ZstdCompressor`` instance.
+        a ``BufferWithSegments``, or any iterable of byte like objects, such as
+        a list, tuple, or generator. Each element of the container will be
+        compressed individually using the configured parameters on the
+        ``ZstdCompressor`` instance.
+
+        Columnar data can be passed as a ``(data, offsets)`` tuple, where
+        ``data`` is a byte like object and ``offsets`` is an array of N + 1
+        32 or 64-bit integers (e.g. a numpy array or ``array.array``)
+        delimiting N items within ``data``. Item ``i`` is
+        ``data[offsets[i]:offsets[i + 1]]``. This is the layout Arrow uses for
+        variable length binary columns.
 
         The ``threads`` argument controls how many threads to use for
         compression. The default is ``0`` which means to use a single thread.
-        Negative values use the number of logical CPUs in the machine.
+        Negative values use the number of logical CPUs in the machine. The
+        string ``"auto"`` chooses a thread count from the number of inputs and
+        their total size, using a single thread for small inputs. The thread
+        count never exceeds the number of inputs.
+
+        Work is distributed on a process-wide thread pool that is created on
+        first use and shared across calls.
 
         The function returns a ``BufferWithSegmentsCollection``. This type
         represents N discrete memory allocations, each holding 1 or more
@@ -2441,10 +2910,6 @@ class ZstdCompressor(object):
         The API and behavior of this function is experimental and will likely
         change. Known deficiencies include:
 
-        * If asked to use multiple threads, it will always spawn that many
-          threads, even if the input is too small to use them. It should
-          automatically lower the thread count when the extra threads would
-          just add overhead.
         * The buffer allocation strategy is fixed. There is room to make it
           dynamic, perhaps even to allow one output buffer per input,
           facilitating a variation of the API to return a list without the
@@ -2454,12 +2919,67 @@ class ZstdCompressor(object):
            Source to read discrete pieces of data to compress.
 
            Can be a ``BufferWithSegmentsCollection``, a ``BufferWithSegments``,
-           or a ``list[bytes]``.
+           an iterable of byte like objects, or a ``(data, offsets)`` tuple.
+        :param progress:
+           Callable receiving the numbers of bytes read and written so far.
+
+           Called as items are compressed and once more when all items
+           were compressed. Returning :py:data:`PROGRESS_CANCEL` aborts the
+           operation with :py:class:`ZstdCancelledError`.
+        :param progress_interval:
+           Minimum number of items compressed between calls to ``progress``.
+           Defaults to calling ``progress`` after every item.
+        :param cancellation_token:
+           A :py:class:`CancellationToken` that aborts compression with
+           :py:class:`ZstdCancelledError` once cancelled. Pending signal
+           handlers also run periodically while compressing, so e.g. Ctrl-C
+           raises ``KeyboardInterrupt``.
         :return:
            BufferWithSegmentsCollection holding compressed data.
         """
         raise NotImplementedError()
 
+    def multi_compress_to_iter(
+        self, data, threads=0, max_in_flight=None, ordered=True
+    ):
+        """
+        Compress discrete pieces of data from an iterable in parallel.
+
+        (Experimental. Not available in CFFI backend.)
+
+        This is a streaming variant of :py:meth:`multi_compress_to_buffer`.
+        Items are read from ``data`` lazily and each is compressed into its
+        own frame on a pool of threads. At most ``max_in_flight`` items are
+        read from ``data`` but not yet emitted, so memory usage is bounded
+        regardless of how many items there are.
+
+        >>> cctx = zstandard.ZstdCompressor()
+        >>> for frame in cctx.multi_compress_to_iter(records, threads=4):
+        ...     write_record(frame)
+
+        By default, compressed frames are emitted in input order. This can
+        stall output when an item takes longer to compress than the items
+        after it. With ``ordered=False``, ``(index, frame)`` tuples are
+        emitted as soon as compression of each item completes.
+
+        :param data:
+           Iterable of objects conforming to the buffer protocol.
+        :param threads:
+           How many threads to use for compression. ``0`` or ``1`` use a single
+           thread. Negative values and ``"auto"`` use the number of logical
+           CPUs in the machine, since the number and size of items aren't
+           known up front.
+        :param max_in_flight:
+           Maximum number of items being compressed or waiting to be emitted.
+           Defaults to 4 items per thread.
+        :param ordered:
+           Whether to emit frames in input order.
+        :return:
+           Iterator of ``bytes``, or of ``(int, bytes)`` if ``ordered`` is
+           false.
+        """
+        raise NotImplementedError()
+
     def frame_progression(self):
         """
         Return information on how much work the compressor has done.
@@ -2495,6 +3015,13 @@ class FrameParameters(object):
     ``has_checksum``
        Bool indicating whether a 4 byte content checksum is stored at the end
        of the frame.
+
+    ``legacy_version``
+       Integer version of the legacy zstd format (1 through 7) of the frame.
+       ``0`` if the frame isn't in a legacy format. Only ``content_size`` is
+       available for legacy frames; other attributes are ``0`` or ``False``.
+
+       (Experimental. Not available in CFFI backend.)
     """
 
     def __init__(self, fparams):
@@ -2542,6 +3069,24 @@ def frame_header_size(data):
     return zresult
 
 
+def frame_legacy_version(data):
+    """Obtain the legacy zstd format version of a frame.
+
+    (Experimental. Not available in CFFI backend.)
+
+    zstd versions before 0.8 wrote frames in formats with different magic
+    numbers. These frames can still be decompressed, but can't be inspected
+    by most APIs in this module. This function is like ``ZSTD_isLegacy()``.
+
+    :param data:
+       Data beginning with a frame.
+    :return:
+       Integer legacy version from 1 through 7. ``0`` if the data isn't a
+       legacy frame.
+    """
+    raise NotImplementedError()
+
+
 def get_frame_parameters(data):
     """
     Parse a zstd frame header into frame parameters.
@@ -2551,6 +3096,9 @@ def get_frame_parameters(data):
     in to fully parse the frame parameters, ``ZstdError`` is raised. To ensure
     frame parameters can be parsed, pass in at least 18 bytes.
 
+    Headers of frames in legacy zstd formats can't be parsed. For these frames,
+    only ``content_size`` and ``legacy_version`` are meaningful.
+
     :param data:
        Data from which to read frame parameters.
     :return:
@@ -2573,6 +3121,209 @@ def get_frame_parameters(data):
     return FrameParameters(params[0])
 
 
+class FramesSummary:
+    """Summary of the frames within zstd compressed data.
+
+    (Experimental. Not available in CFFI backend.)
+
+    Instances are returned by :py:func:`summarize_frames` and have the
+    following attributes:
+
+    ``frame_count``
+       Integer number of zstd frames.
+
+    ``skippable_frame_count``
+       Integer number of skippable frames.
+
+    ``compressed_size``
+       Integer total size of all frames, in bytes.
+
+    ``decompressed_size``
+       Integer total decompressed size of all frames. ``None`` if any zstd
+       frame doesn't record its content size.
+
+    ``ratio``
+       Float of ``decompressed_size / compressed_size``. ``None`` if the
+       decompressed size is unknown or there is no data.
+
+    ``checksum``
+       ``"XXH64"`` if all zstd frames have content checksums, ``"mixed"`` if
+       only some do and ``None`` if none do.
+
+    ``window_size``
+       Integer largest window size of any zstd frame. This is the amount of
+       memory needed to decompress the data.
+
+    ``dict_ids``
+       Sorted list of integer non-zero dictionary IDs used by frames.
+    """
+
+
+def summarize_frames(source):
+    """Summarize the frames within zstd compressed data.
+
+    (Experimental. Not available in CFFI backend.)
+
+    This is similar to ``zstd -l``. Frames are found by reading frame and
+    block headers, so data isn't decompressed.
+
+    ``ZstdError`` is raised if the data contains anything other than complete
+    zstd and skippable frames.
+
+    :param source:
+       Filesystem path, file object or bytes-like object holding compressed
+       data. File objects must be seekable and are read from their current
+       position.
+    :return:
+       :py:class:`FramesSummary`
+    """
+    raise NotImplementedError()
+
+
+class CompressionStats:
+    """Statistics of a streaming compression operation.
+
+    (Experimental. Not available in CFFI backend.)
+
+    Pass an instance as the ``stats`` argument of
+    :py:meth:`ZstdCompressor.copy_stream` or
+    :py:meth:`ZstdCompressor.stream_writer` to have it record the operation.
+    Counters and times accumulate if an instance is used for several
+    operations.
+
+    Instances have the following attributes:
+
+    ``bytes_read``
+       Integer number of uncompressed bytes fed to the compressor.
+
+    ``bytes_written``
+       Integer number of compressed bytes written to the destination.
+
+    ``frames``
+       Integer number of zstd frames completed.
+
+    ``flushes``
+       Integer number of times a stream writer was explicitly flushed.
+
+    ``zstd_time``
+       Float seconds spent compressing in zstd, during which the GIL is
+       released.
+
+    ``io_time``
+       Float seconds spent in ``read()`` and ``write()`` calls on the source
+       and destination streams.
+
+    ``compression_level``
+       Integer compression level used by the most recent operation. ``None``
+       if nothing was recorded.
+
+    ``window_log``
+       Integer base 2 log of the window size used by the most recent
+       operation. ``None`` if nothing was recorded.
+    """
+
+    def __init__(self):
+        raise NotImplementedError()
+
+
+class CancellationToken:
+    """Requests cancellation of a running operation.
+
+    (Experimental. Not available in CFFI backend.)
+
+    Pass an instance as the ``cancellation_token`` argument of
+    :py:meth:`ZstdCompressor.compress` or
+    :py:meth:`ZstdCompressor.multi_compress_to_buffer`. Calling
+    :py:meth:`cancel` from another thread makes the operation raise
+    :py:class:`ZstdCancelledError` soon after. Operations given a token
+    that is already cancelled may still complete if their input is small.
+
+    Instances have a ``cancelled`` attribute indicating whether
+    :py:meth:`cancel` was called.
+    """
+
+    def __init__(self):
+        raise NotImplementedError()
+
+    def cancel(self):
+        """Cancel operations using this token."""
+        raise NotImplementedError()
+
+
+class MemoryTracker:
+    """Accounts for memory allocated by zstd.
+
+    (Experimental. Not available in CFFI backend.)
+
+    Compressors and decompressors allocate their zstd contexts, and any
+    memory those contexts need later, through the tracker given to them via
+    ``memory_tracker``. Objects not given a tracker use the process-wide
+    tracker installed with :py:func:`set_memory_tracker`, if any. Precomputed
+    dictionaries always use the process-wide tracker.
+
+    Allocations that would take ``live_bytes`` above ``limit`` are refused.
+    The operation needing the memory raises ``MemoryError``.
+
+    A tracker may be shared by objects used from multiple threads.
+
+    :param limit:
+       Integer maximum number of bytes that may be allocated at once.
+       ``None`` for no limit.
+
+    Instances have the following attributes:
+
+    ``live_bytes``
+       Integer number of bytes currently allocated.
+
+    ``peak_bytes``
+       Integer highest value of ``live_bytes``.
+
+    ``allocation_count``
+       Integer number of successful allocations.
+
+    ``failed_allocation_count``
+       Integer number of refused or failed allocations.
+
+    ``limit``
+       Integer maximum of ``live_bytes`` or ``None``. Can be changed. Memory
+       already allocated isn't affected by lowering the limit.
+    """
+
+    def __init__(self, limit=None):
+        raise NotImplementedError()
+
+    def reset_peak(self):
+        """Reset ``peak_bytes`` to the current ``live_bytes``."""
+        raise NotImplementedError()
+
+
+def set_memory_tracker(tracker):
+    """Install a process-wide :py:class:`MemoryTracker`.
+
+    (Experimental. Not available in CFFI backend.)
+
+    The tracker is used by compressors, decompressors and precomputed
+    dictionaries created afterwards without an explicit tracker. Existing
+    objects keep using the tracker they were created with.
+
+    :param tracker:
+       :py:class:`MemoryTracker` to use or ``None`` to stop tracking.
+    """
+    raise NotImplementedError()
+
+
+def get_memory_tracker():
+    """Obtain the process-wide :py:class:`MemoryTracker`.
+
+    (Experimental. Not available in CFFI backend.)
+
+    :return:
+       :py:class:`MemoryTracker` installed by :py:func:`set_memory_tracker`
+       or ``None``.
+    """
+    raise NotImplementedError()
+
+
 class ZstdCompressionDict(object):
     """Represents a computed compression dictionary.
 
@@ -3045,6 +3796,22 @@ class ZstdDecompressionReader(object):
     to the end of stream are not allowed and will raise ``ValueError``
     if attempted.
 
+    Backends advertising the ``decompression_reader_backward_seek`` feature
+    also support seeking backwards when the source is a buffer or a seekable
+    stream. The source is rewound and decompression restarts from the
+    beginning. If ``stream_reader()`` was called with
+    ``seek_checkpoints=True``, the offsets of frame boundaries are recorded
+    during reading and decompression restarts from the nearest boundary
+    instead.
+
+    Backends advertising the ``decompression_reader_seek_end`` feature
+    support ``SEEK_END`` and a ``decompressed_size(full_scan=False)`` method
+    when the source is a buffer or a seekable stream. The size is the sum of
+    the content sizes recorded in all frame headers. If a frame doesn't
+    record its content size, ``decompressed_size()`` returns ``None`` and
+    ``SEEK_END`` raises ``OSError``. Passing ``full_scan=True`` decompresses
+    the remaining data to measure the size instead.
+
     ``tell()`` returns the number of decompressed bytes read so far.
 
     Not all I/O methods are implemented. Notably missing is support for
@@ -3059,6 +3826,10 @@ class ZstdDecompressionReader(object):
     ...     text_stream = io.TextIOWrapper(stream_reader, encoding='utf-8')
     ...     for line in text_stream:
     ...         ...
+
+    Backends advertising the ``decompression_reader_readline`` feature
+    implement ``readline()``, ``readlines()``, and iteration over binary
+    lines natively. Lines are split on ``b"\\n"`` and span frame boundaries.
     """
 
     def __init__(
@@ -3611,9 +4382,68 @@ class ZstdDecompressor(object):
        By default this is ``zstandard.FORMAT_ZSTD1``. It can be set to
        ``zstandard.FORMAT_ZSTD1_MAGICLESS`` to allow decoding frames without
        the 4 byte magic header. Not all decompression APIs support this mode.
+    :param allow_legacy:
+       Whether to decompress frames in legacy zstd formats (v0.1 through
+       v0.7).
+
+       (Experimental. Not available in CFFI backend.)
+
+       If false, decompression operations raise ``ZstdError`` upon
+       encountering a legacy frame. Legacy decoders have received less
+       scrutiny than the current one, so services handling untrusted input
+       may wish to disable them.
+    :param memory_tracker:
+       A :py:class:`MemoryTracker` accounting for memory allocated by zstd
+       for this decompressor. Defaults to the process-wide tracker installed
+       with :py:func:`set_memory_tracker`, if any.
+
+       (Experimental. Not available in CFFI backend.)
+    :param static_context_size:
+       If defined, the decompression context is confined to a buffer of this
+       many bytes allocated up front. Operations needing more memory raise
+       ``MemoryError`` instead of allocating. The buffer is not accounted to
+       any :py:class:`MemoryTracker`.
+
+       :py:func:`estimate_decompression_context_size` gives the size needed to
+       decompress frames whose content size is known when all input and
+       output are available at once. Decompressing other frames needs room
+       for a window buffer. Cannot be combined with ``memory_tracker``. APIs
+       that create additional contexts, such as
+       ``multi_decompress_to_buffer()`` or ``threads``, raise ``ValueError``.
+
+       (Experimental. Not available in CFFI backend.)
+    :param max_output_size:
+       Maximum number of bytes a single decompression operation may produce.
+       Applies to ``decompress()``, ``decompressobj()``, readers, writers,
+       ``copy_stream()`` and ``multi_decompress_to_buffer()``, where it limits
+       each item. Frames declaring a larger content size are refused before
+       output memory is allocated. Exceeding the limit raises
+       :py:class:`ZstdOutputLimitError`.
+
+       Combine with ``max_window_size`` to bound memory when handling
+       untrusted input.
+
+       (Experimental. Not available in CFFI backend.)
     """
 
-    def __init__(self, dict_data=None, max_window_size=0, format=FORMAT_ZSTD1):
+    def __init__(
+        self,
+        dict_data=None,
+        max_window_size=0,
+        format=FORMAT_ZSTD1,
+        allow_legacy=True,
+        memory_tracker=None,
+        static_context_size=None,
+        max_output_size=None,
+    ):
+        if (
+            not allow_legacy
+            or memory_tracker is not None
+            or static_context_size is not None
+            or max_output_size is not None
+        ):
+            raise NotImplementedError()
+
         self._dict_data = dict_data
         self._max_window_size = max_window_size
         self._format = format
@@ -3992,6 +4822,9 @@ class ZstdDecompressor(object):
         ofh,
         read_size=DECOMPRESSION_RECOMMENDED_INPUT_SIZE,
         write_size=DECOMPRESSION_RECOMMENDED_OUTPUT_SIZE,
+        threads=0,
+        progress=None,
+        progress_interval=None,
     ):
         """
         Copy data between streams, decompressing in the process.
@@ -4028,11 +4861,39 @@ class ZstdDecompressor(object):
         :param write_size:
            The number of bytes to ``write()`` to the destination in a single
            operation.
+        :param threads:
+           How many threads to use to decompress frames in parallel.
+
+           (Experimental. Not available in CFFI backend.)
+
+           ``0`` (the default) decompresses serially. Other values read
+           input until a batch of complete frames is available and
+           decompress the frames in the batch concurrently, writing output
+           in order. At most 4 MiB of input per thread is buffered: frames
+           larger than that are decompressed serially as they are read.
+           Negative values use the same number of threads as logical CPUs
+           on the machine. See :py:meth:`decompress_parallel`.
+        :param progress:
+           Callable receiving the numbers of bytes read and written so far.
+
+           (Experimental. Not available in CFFI backend.)
+
+           Called as decompression progresses and once more when it
+           completes. Returning :py:data:`PROGRESS_CANCEL` aborts the
+           operation with :py:class:`ZstdCancelledError`.
+        :param progress_interval:
+           Minimum number of bytes read between calls to ``progress``.
+           Defaults to calling ``progress`` after every read.
+
+           (Experimental. Not available in CFFI backend.)
         :return:
            2-tuple of integers representing the number of bytes read and
            written, respectively.
         """
 
+        if threads or progress is not None or progress_interval is not None:
+            raise NotImplementedError()
+
         if not hasattr(ifh, "read"):
             raise ValueError("first argument must have a read() method")
         if not hasattr(ofh, "write"):
@@ -4230,8 +5091,38 @@ class ZstdDecompressor(object):
 
         return ffi.buffer(last_buffer, len(last_buffer))[:]
 
+    def decompress_parallel(self, data, threads=-1):
+        """
+        Decompress data containing multiple zstd frames in parallel.
+
+        (Experimental. Not available in CFFI backend.)
+
+        Frame boundaries are found by walking frame and block headers. The
+        frames are then decompressed concurrently and the output is
+        concatenated in input order.
+
+        >>> dctx = zstandard.ZstdDecompressor()
+        >>> data = dctx.decompress_parallel(compressed, threads=4)
+
+        This is only faster than :py:meth:`decompress` when the input consists
+        of many independent frames, such as files produced by ``pzstd``.
+        Skippable frames, including ``pzstd`` size headers and seek tables,
+        are ignored. Frames don't need to record their content size.
+
+        :param data:
+           Object conforming to the buffer protocol holding zstd frames.
+        :param threads:
+           How many threads to use for decompression operations.
+
+           Negative values will use the same number of threads as logical CPUs
+           on the machine. Values ``0`` or ``1`` use a single thread.
+        :return:
+           ``bytes`` holding the decompressed data of all frames.
+        """
+        raise NotImplementedError()
+
     def multi_decompress_to_buffer(
-        self, frames, decompressed_sizes=None, threads=0
+        self, frames, decompressed_sizes=None, threads=0, errors="strict"
     ):
         """
         Decompress multiple zstd frames to output buffers as a single operation.
@@ -4289,10 +5180,10 @@ class ZstdDecompressor(object):
         they need to access data for multiple frames, such as when  *delta chains* are
         being used.
 
-        Currently, the implementation always spawns multiple threads when requested,
-        even if the amount of work to do is small. In the future, it will be smarter
-        about avoiding threads and their associated overhead when the amount of
-        work to do is small.
+        Work is distributed on a process-wide thread pool that is created on
+        first use and shared across calls. Use ``threads="auto"`` to avoid
+        the overhead of multiple threads when the amount of work to do is
+        small.
 
         :param frames:
            Source defining zstd frames to decompress.
@@ -4302,9 +5193,21 @@ class ZstdDecompressor(object):
            How many threads to use for decompression operations.
 
            Negative values will use the same number of threads as logical CPUs
-           on the machine. Values ``0`` or ``1`` use a single thread.
+           on the machine. Values ``0`` or ``1`` use a single thread. The
+           string ``"auto"`` chooses a thread count from the number of frames
+           and their total size.
+        :param errors:
+           How to handle frames that fail to decompress.
+
+           ``strict`` (the default) raises an exception for the first failure.
+           ``collect`` decompresses all other frames and returns a 2-tuple of
+           the ``BufferWithSegmentsCollection`` and a list of
+           ``(index, exception)`` for each failed frame. Failed frames are
+           represented by empty segments so indices in the collection match
+           indices of ``frames``.
         :return:
-           ``BufferWithSegmentsCollection``
+           ``BufferWithSegmentsCollection``, or a 2-tuple of it and a list of
+           errors if ``errors`` is ``collect``.
         """
         raise NotImplementedError()
 